@@ -0,0 +1,54 @@
+/*
+ * C interface to gpxsplit, built with `cargo build --release --features ffi`
+ * as libgpxsplit. Functions that can fail return a negative number, and
+ * gpxsplit_last_error() then describes what went wrong.
+ */
+
+#ifndef GPXSPLIT_H
+#define GPXSPLIT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The contents of a GPX file for a part, from gpxsplit_split_bytes(). */
+typedef struct GpxsplitPart {
+    uint8_t *data;
+    size_t len;
+} GpxsplitPart;
+
+/*
+ * Splits the GPX file at in_path into files of km kilometers and at most
+ * max_points points, either of which can be zero to not use it. The files are
+ * numbered after the input file, like route_01.gpx, and written to out_dir,
+ * or next to the input file if it's NULL. Existing files are overwritten.
+ * Returns the number of files.
+ */
+int32_t gpxsplit_split_file(const char *in_path, double km, uint32_t max_points,
+                            const char *out_dir);
+
+/*
+ * Splits the GPX file in the len bytes at input, with km and max_points like
+ * gpxsplit_split_file(). The parts are put in a new array at *parts, which has
+ * to be freed with gpxsplit_free_parts(). Returns the number of parts.
+ */
+int32_t gpxsplit_split_bytes(const uint8_t *input, size_t len, double km,
+                             uint32_t max_points, GpxsplitPart **parts);
+
+/* Frees the count parts from gpxsplit_split_bytes(). */
+void gpxsplit_free_parts(GpxsplitPart *parts, size_t count);
+
+/*
+ * Describes the last error on this thread, or NULL if there hasn't been one.
+ * The string is valid until the next call on this thread.
+ */
+const char *gpxsplit_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,39 @@
+# Copyright 2025 natesm@gmail.com
+#
+# Permission to use, copy, modify, and/or distribute this software for any
+# purpose with or without fee is hereby granted, provided that the above
+# copyright notice and this permission notice appear in all copies.
+#
+# THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+# WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+# MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+# ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+# WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+# ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+# IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+# Types of the module built with `cargo build --release --features python`,
+# see src/python.rs.
+
+from typing import Iterable
+
+def split(
+    gpx: str | bytes,
+    *,
+    km_per_file: float | None = None,
+    hours_per_file: float | None = None,
+    gain_per_file: float | None = None,
+    est_hours_per_file: float | None = None,
+    speed: float | None = None,
+    climbing_per_hour: float | None = None,
+    max_points: int | None = None,
+    at_km: Iterable[float] | None = None,
+    overlap_points: int | None = None,
+    overlap_km: float | None = None,
+    no_overlap: bool = False,
+    exclude_overlap: bool = False,
+    three_d_distance: bool = False,
+) -> list[str]:
+    """Splits the first segment of the first track of the GPX file in `gpx`,
+    returning the contents of a GPX file for each part. Options that don't
+    make sense raise ValueError."""
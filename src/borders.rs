@@ -0,0 +1,180 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+use gpx::Waypoint;
+
+use crate::json::Json;
+
+/// Regions, such as countries or states, loaded from the polygon and
+/// multipolygon features of a GeoJSON file.
+pub struct Regions {
+    regions: Vec<Region>,
+}
+
+/// A single feature's polygons, each made of an outer ring followed by any
+/// holes, as longitude and latitude pairs.
+struct Region {
+    polygons: Vec<Vec<Vec<(f64, f64)>>>,
+    min: (f64, f64),
+    max: (f64, f64),
+}
+
+impl Regions {
+    pub fn from_geojson(text: &str) -> Result<Regions> {
+        let geojson = Json::parse(text)?;
+
+        let features = match geojson.get("features") {
+            Some(features) => features
+                .as_array()
+                .ok_or_else(|| anyhow!("geojson features isn't an array"))?
+                .iter()
+                .collect(),
+            None => vec![&geojson],
+        };
+
+        let mut regions = Vec::new();
+        for feature in features {
+            let geometry = feature.get("geometry").unwrap_or(feature);
+            let coordinates = geometry
+                .get("coordinates")
+                .ok_or_else(|| anyhow!("geojson geometry missing coordinates"))?;
+
+            let polygons = match geometry.get("type").and_then(Json::as_str) {
+                Some("Polygon") => vec![polygon(coordinates)?],
+                Some("MultiPolygon") => array(coordinates)?
+                    .iter()
+                    .map(polygon)
+                    .collect::<Result<_>>()?,
+                // points and lines can't contain anything, so they're skipped
+                _ => continue,
+            };
+
+            let points = polygons.iter().flatten().flatten();
+            let min = points.clone().fold((f64::MAX, f64::MAX), |min, point| {
+                (min.0.min(point.0), min.1.min(point.1))
+            });
+            let max = points.fold((f64::MIN, f64::MIN), |max, point| {
+                (max.0.max(point.0), max.1.max(point.1))
+            });
+
+            regions.push(Region { polygons, min, max });
+        }
+
+        if regions.is_empty() {
+            bail!("geojson file has no polygons");
+        }
+
+        Ok(Regions { regions })
+    }
+
+    /// Index of the region containing `waypoint`, checking `hint` first since
+    /// consecutive waypoints are almost always in the same region.
+    fn containing(&self, waypoint: &Waypoint, hint: Option<usize>) -> Option<usize> {
+        let point = waypoint.point();
+        let point = (point.x(), point.y());
+
+        if let Some(hint) = hint {
+            if self.regions[hint].contains(point) {
+                return Some(hint);
+            }
+        }
+
+        self.regions
+            .iter()
+            .position(|region| region.contains(point))
+    }
+}
+
+impl Region {
+    fn contains(&self, point: (f64, f64)) -> bool {
+        if point.0 < self.min.0
+            || point.0 > self.max.0
+            || point.1 < self.min.1
+            || point.1 > self.max.1
+        {
+            return false;
+        }
+
+        // the outer ring and holes alternate the result, so a point in a hole is
+        // outside of the polygon
+        self.polygons.iter().any(|rings| {
+            rings
+                .iter()
+                .filter(|ring| ring_contains(ring, point))
+                .count()
+                % 2
+                == 1
+        })
+    }
+}
+
+/// Ray casting test for whether `point` is inside of `ring`.
+fn ring_contains(ring: &[(f64, f64)], point: (f64, f64)) -> bool {
+    let mut inside = false;
+    for (index, a) in ring.iter().enumerate() {
+        let b = ring[(index + 1) % ring.len()];
+        if (a.1 > point.1) != (b.1 > point.1)
+            && point.0 < (b.0 - a.0) * (point.1 - a.1) / (b.1 - a.1) + a.0
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Indices of the waypoints that are followed by a waypoint in a different
+/// region. Leaving all of the regions counts as a change too.
+pub fn region_change_indices(waypoints: &[Waypoint], regions: &Regions) -> Vec<usize> {
+    let mut indices = Vec::new();
+    let mut previous = None;
+
+    for (index, waypoint) in waypoints.iter().enumerate() {
+        let current = regions.containing(waypoint, previous);
+        if index > 0 && current != previous {
+            indices.push(index - 1);
+        }
+        previous = current;
+    }
+
+    indices
+}
+
+fn polygon(coordinates: &Json) -> Result<Vec<Vec<(f64, f64)>>> {
+    array(coordinates)?
+        .iter()
+        .map(|ring| {
+            array(ring)?
+                .iter()
+                .map(|position| {
+                    let position = array(position)?;
+                    match (
+                        position.first().and_then(Json::as_f64),
+                        position.get(1).and_then(Json::as_f64),
+                    ) {
+                        (Some(longitude), Some(latitude)) => Ok((longitude, latitude)),
+                        _ => bail!("invalid geojson position"),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn array(json: &Json) -> Result<&[Json]> {
+    json.as_array()
+        .ok_or_else(|| anyhow!("expected an array of geojson coordinates"))
+}
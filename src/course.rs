@@ -0,0 +1,116 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use gpx::Waypoint;
+use time::OffsetDateTime;
+
+/// A file's route as course formats like TCX describe it, where every point
+/// has a distance along the course and a time.
+pub struct Course {
+    pub name: String,
+    pub track: Vec<TrackPoint>,
+    pub course_points: Vec<CoursePoint>,
+}
+
+pub struct TrackPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub elevation: Option<f64>,
+    /// Distance from the start of the course.
+    pub meters: f64,
+    pub time: OffsetDateTime,
+}
+
+/// A point of interest along the course that devices alert about.
+pub struct CoursePoint {
+    pub name: String,
+    /// Index of the nearest point in the track.
+    pub index: usize,
+    pub kind: CoursePointKind,
+}
+
+/// The kinds of course points that both TCX and FIT have.
+#[derive(Clone, Copy)]
+pub enum CoursePointKind {
+    Generic,
+    Summit,
+    Valley,
+    Water,
+    Food,
+    Danger,
+    FirstAid,
+}
+
+impl CoursePointKind {
+    /// Guesses the kind of course point for a waypoint from its symbol, type,
+    /// or name.
+    pub fn of(waypoint: &Waypoint) -> CoursePointKind {
+        [&waypoint.symbol, &waypoint.type_, &waypoint.name]
+            .into_iter()
+            .flatten()
+            .map(|text| CoursePointKind::guess(text))
+            .find(|kind| !matches!(kind, CoursePointKind::Generic))
+            .unwrap_or(CoursePointKind::Generic)
+    }
+
+    /// Name of the kind, as TCX files write it.
+    pub fn name(self) -> &'static str {
+        match self {
+            CoursePointKind::Generic => "Generic",
+            CoursePointKind::Summit => "Summit",
+            CoursePointKind::Valley => "Valley",
+            CoursePointKind::Water => "Water",
+            CoursePointKind::Food => "Food",
+            CoursePointKind::Danger => "Danger",
+            CoursePointKind::FirstAid => "First Aid",
+        }
+    }
+
+    /// Guesses the kind from a waypoint's symbol or type, which apps don't
+    /// name consistently.
+    fn guess(symbol: &str) -> CoursePointKind {
+        let symbol = symbol.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| symbol.contains(word));
+        if has(&["water", "drink"]) {
+            CoursePointKind::Water
+        } else if has(&["food", "restaurant", "store", "shop", "resupply"]) {
+            CoursePointKind::Food
+        } else if has(&["summit", "peak", "pass"]) {
+            CoursePointKind::Summit
+        } else if has(&["valley"]) {
+            CoursePointKind::Valley
+        } else if has(&["danger", "caution", "hazard"]) {
+            CoursePointKind::Danger
+        } else if has(&["first aid", "medical", "hospital"]) {
+            CoursePointKind::FirstAid
+        } else {
+            CoursePointKind::Generic
+        }
+    }
+}
+
+impl Course {
+    /// Length of the course in meters.
+    pub fn meters(&self) -> f64 {
+        self.track.last().map_or(0., |point| point.meters)
+    }
+
+    /// Time from the start of the course to the end, in seconds.
+    pub fn seconds(&self) -> f64 {
+        match (self.track.first(), self.track.last()) {
+            (Some(first), Some(last)) => (last.time - first.time).as_seconds_f64(),
+            _ => 0.,
+        }
+    }
+}
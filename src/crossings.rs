@@ -0,0 +1,120 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::collections::HashMap;
+
+use geo_types::Point;
+use gpx::Waypoint;
+
+/// Size of the grid cells used to find nearby legs, in degrees. Legs are short
+/// compared to this, so most of them only fall into a single cell.
+const CELL_DEGREES: f64 = 0.01;
+
+/// Legs that would fall into more cells than this are checked against every
+/// other leg instead, so that a single long leg can't fill the grid.
+const MAX_CELLS: usize = 1024;
+
+/// A straight line between two consecutive points, in longitude and latitude.
+#[derive(Clone, Copy)]
+struct Leg {
+    start: Point<f64>,
+    end: Point<f64>,
+}
+
+/// Indices of the waypoints closest to where the route crosses any of the
+/// `lines`. Coordinates are treated as planar, which is accurate enough over
+/// the length of a single leg.
+pub fn crossing_indices(waypoints: &[Waypoint], lines: &[Vec<Waypoint>]) -> Vec<usize> {
+    // bucket the other legs by grid cell, so that each leg of the route only
+    // needs to be checked against the legs near it
+    let mut grid: HashMap<(i64, i64), Vec<Leg>> = HashMap::new();
+    let mut long_legs = Vec::new();
+    for line in lines {
+        for leg in legs(line) {
+            if cells(&leg).nth(MAX_CELLS).is_some() {
+                long_legs.push(leg);
+                continue;
+            }
+            for cell in cells(&leg) {
+                grid.entry(cell).or_default().push(leg);
+            }
+        }
+    }
+
+    let mut indices = Vec::new();
+    for (index, leg) in legs(waypoints).enumerate() {
+        let crossing = if cells(&leg).nth(MAX_CELLS).is_some() {
+            grid.values()
+                .flatten()
+                .chain(&long_legs)
+                .find_map(|other| intersection(&leg, other))
+        } else {
+            cells(&leg)
+                .filter_map(|cell| grid.get(&cell))
+                .flatten()
+                .chain(&long_legs)
+                .find_map(|other| intersection(&leg, other))
+        };
+
+        if let Some(fraction) = crossing {
+            // split at whichever end of the leg is closer to the crossing
+            indices.push(if fraction < 0.5 { index } else { index + 1 });
+        }
+    }
+
+    indices.dedup();
+    indices
+}
+
+fn legs(waypoints: &[Waypoint]) -> impl Iterator<Item = Leg> + '_ {
+    waypoints.windows(2).map(|pair| Leg {
+        start: pair[0].point(),
+        end: pair[1].point(),
+    })
+}
+
+fn cells(leg: &Leg) -> impl Iterator<Item = (i64, i64)> {
+    let cell = |value: f64| (value / CELL_DEGREES).floor() as i64;
+
+    let (min_x, max_x) = min_max(cell(leg.start.x()), cell(leg.end.x()));
+    let (min_y, max_y) = min_max(cell(leg.start.y()), cell(leg.end.y()));
+
+    (min_x..=max_x).flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+}
+
+fn min_max(a: i64, b: i64) -> (i64, i64) {
+    (a.min(b), a.max(b))
+}
+
+/// Fraction of the way along `leg` that it crosses `other`, if it does.
+fn intersection(leg: &Leg, other: &Leg) -> Option<f64> {
+    let r = leg.end - leg.start;
+    let s = other.end - other.start;
+    let denominator = cross(r, s);
+
+    // parallel legs never count as a crossing, even if they overlap
+    if denominator == 0. {
+        return None;
+    }
+
+    let offset = other.start - leg.start;
+    let t = cross(offset, s) / denominator;
+    let u = cross(offset, r) / denominator;
+
+    ((0. ..=1.).contains(&t) && (0. ..=1.).contains(&u)).then_some(t)
+}
+
+fn cross(a: Point<f64>, b: Point<f64>) -> f64 {
+    a.x() * b.y() - a.y() * b.x()
+}
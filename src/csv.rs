@@ -0,0 +1,45 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io::Write;
+
+use anyhow::Result;
+use gpx::Waypoint;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Writes a row for each of `waypoints`, with its distance from the first
+/// one in `meters`. Missing elevations and times are left empty.
+pub fn write_csv<W: Write>(mut writer: W, waypoints: &[Waypoint], meters: &[f64]) -> Result<()> {
+    writeln!(writer, "latitude,longitude,elevation_m,time,distance_m")?;
+    for (waypoint, meters) in waypoints.iter().zip(meters) {
+        let point = waypoint.point();
+        let elevation = waypoint
+            .elevation
+            .map(|elevation| elevation.to_string())
+            .unwrap_or_default();
+        let time = match waypoint.time {
+            Some(time) => OffsetDateTime::from(time).format(&Rfc3339)?,
+            None => String::new(),
+        };
+        writeln!(
+            writer,
+            "{},{},{elevation},{time},{meters:.1}",
+            point.y(),
+            point.x()
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
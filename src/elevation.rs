@@ -0,0 +1,368 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use geo_types::Point;
+use gpx::Waypoint;
+
+use crate::json::Json;
+
+/// Replaces the elevation of each point in `waypoints` with the median of
+/// the elevations in a window of `window` points around it, which takes out
+/// spikes and jitter from GPS elevations while keeping the shape of real
+/// climbs. The window is cut short at the ends of the route, and points
+/// without an elevation are left out of it and keep not having one.
+pub fn smooth_elevations(waypoints: &mut [Waypoint], window: usize) {
+    let elevations: Vec<(usize, f64)> = waypoints
+        .iter()
+        .enumerate()
+        .filter_map(|(index, waypoint)| Some((index, waypoint.elevation?)))
+        .collect();
+    let before = (window - 1) / 2;
+    let after = window / 2;
+
+    let mut sorted = Vec::with_capacity(window);
+    for (position, (index, _)) in elevations.iter().enumerate() {
+        let start = position.saturating_sub(before);
+        let end = (position + after + 1).min(elevations.len());
+        sorted.clear();
+        sorted.extend(
+            elevations[start..end]
+                .iter()
+                .map(|(_, elevation)| *elevation),
+        );
+        sorted.sort_by(f64::total_cmp);
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 {
+            (sorted[middle - 1] + sorted[middle]) / 2.
+        } else {
+            sorted[middle]
+        };
+        waypoints[*index].elevation = Some(median);
+    }
+}
+
+/// Service to look up elevations from when `--fill-elevation` doesn't say
+/// where, which asks for no more than one request per second.
+const OPEN_TOPO_DATA_URL: &str = "https://api.opentopodata.org/v1/srtm90m";
+
+/// Most points to look up in a single request, which is the most that Open
+/// Topo Data allows.
+const BATCH_POINTS: usize = 100;
+
+/// Value of the samples in SRTM tiles that have no elevation.
+const VOID: i16 = -32768;
+
+/// Where to look up the elevations of points that don't have one.
+pub enum ElevationSource {
+    Service(ElevationService),
+    Tiles(Tiles),
+}
+
+/// An elevation service like Open Topo Data or Open-Elevation, which both take
+/// the points in a `locations` parameter and return a list of `results` with
+/// an `elevation` each. Elevations are cached on disk by service and
+/// position, so that running again over the same route doesn't need the
+/// network.
+pub struct ElevationService {
+    url: String,
+    cache_path: Option<PathBuf>,
+    /// Elevations by position, for each service.
+    cache: Vec<(String, Json)>,
+    requested: bool,
+}
+
+/// SRTM `.hgt` tiles, named after their south west corner like
+/// `N46W110.hgt`, either in a directory or a single tile.
+pub struct Tiles {
+    path: PathBuf,
+    loaded: HashMap<String, Option<Tile>>,
+}
+
+/// A grid of elevations in meters covering a degree of latitude and
+/// longitude, starting from the north west corner.
+struct Tile {
+    size: usize,
+    samples: Vec<i16>,
+}
+
+impl ElevationSource {
+    /// Tiles if `source` is a file or directory, or otherwise a service at
+    /// the URL `source`, or Open Topo Data if it's missing.
+    pub fn new(source: Option<&str>) -> ElevationSource {
+        match source {
+            Some(path) if Path::new(path).exists() => ElevationSource::Tiles(Tiles {
+                path: PathBuf::from(path),
+                loaded: HashMap::new(),
+            }),
+            source => ElevationSource::Service(ElevationService::new(
+                source.unwrap_or(OPEN_TOPO_DATA_URL),
+            )),
+        }
+    }
+
+    /// Looks up the elevation of each of `waypoints` that doesn't have one,
+    /// returning how many were found.
+    pub fn fill(&mut self, waypoints: &mut [Waypoint]) -> Result<usize> {
+        let missing: Vec<usize> = (0..waypoints.len())
+            .filter(|index| waypoints[*index].elevation.is_none())
+            .collect();
+        let points: Vec<Point> = missing
+            .iter()
+            .map(|index| waypoints[*index].point())
+            .collect();
+        let elevations = match self {
+            ElevationSource::Service(service) => service.elevations(&points)?,
+            ElevationSource::Tiles(tiles) => points
+                .iter()
+                .map(|point| tiles.elevation(*point))
+                .collect::<Result<_>>()?,
+        };
+
+        let mut filled = 0;
+        for (index, elevation) in missing.into_iter().zip(elevations) {
+            if elevation.is_some() {
+                waypoints[index].elevation = elevation;
+                filled += 1;
+            }
+        }
+        Ok(filled)
+    }
+
+    /// Writes any new elevations from a service to its cache.
+    pub fn save(&self) -> Result<()> {
+        match self {
+            ElevationSource::Service(service) => service.save(),
+            ElevationSource::Tiles(_) => Ok(()),
+        }
+    }
+}
+
+impl ElevationService {
+    /// Loads the cache, if there is one.
+    fn new(url: &str) -> ElevationService {
+        let cache_path = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|cache| cache.join("gpxsplit").join("elevations.json"));
+
+        // a missing or broken cache just means everything is looked up again
+        let cache = cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| Json::parse(&text).ok())
+            .and_then(|json| match json {
+                Json::Object(entries) => Some(entries),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        ElevationService {
+            url: url.to_owned(),
+            cache_path,
+            cache,
+            requested: false,
+        }
+    }
+
+    /// Elevations of `points` from the cache, or otherwise from the service in
+    /// batches.
+    fn elevations(&mut self, points: &[Point]) -> Result<Vec<Option<f64>>> {
+        if !self.cache.iter().any(|(url, _)| *url == self.url) {
+            self.cache
+                .push((self.url.clone(), Json::Object(Vec::new())));
+        }
+        let cached = self
+            .cache
+            .iter_mut()
+            .find_map(|(url, cached)| match cached {
+                Json::Object(cached) if *url == self.url => Some(cached),
+                _ => None,
+            })
+            .ok_or_else(|| anyhow!("elevation cache is broken, delete it to start over"))?;
+        let mut found: HashMap<String, f64> = cached
+            .iter()
+            .filter_map(|(key, elevation)| Some((key.clone(), elevation.as_f64()?)))
+            .collect();
+
+        let keys: Vec<String> = points
+            .iter()
+            .map(|point| format!("{:.5},{:.5}", point.y(), point.x()))
+            .collect();
+        let mut uncached: Vec<&str> = keys
+            .iter()
+            .map(String::as_str)
+            .filter(|key| !found.contains_key(*key))
+            .collect();
+        uncached.sort();
+        uncached.dedup();
+
+        for batch in uncached.chunks(BATCH_POINTS) {
+            if self.requested {
+                sleep(Duration::from_secs(1));
+            }
+            self.requested = true;
+            let elevations = request(&self.url, batch)
+                .with_context(|| format!("failed to look up elevations from {}", self.url))?;
+            for (key, elevation) in batch.iter().zip(elevations) {
+                if let Some(elevation) = elevation {
+                    cached.push((key.to_string(), Json::Number(elevation)));
+                    found.insert(key.to_string(), elevation);
+                }
+            }
+        }
+        Ok(keys.iter().map(|key| found.get(key).copied()).collect())
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        if !self.requested {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let cache = Json::Object(self.cache.clone());
+        fs::write(path, cache.to_string())
+            .with_context(|| format!("failed to write elevation cache {}", path.display()))
+    }
+}
+
+/// Looks up the elevations of `locations`, which are like `46.5,-110.2`, from
+/// the service at `url`, using `curl` so that this doesn't need an HTTP client
+/// of its own. Places the service has no data for don't have an elevation.
+fn request(url: &str, locations: &[&str]) -> Result<Vec<Option<f64>>> {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    let output = Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--max-time",
+            "30",
+        ])
+        .args(["--user-agent", "gpxsplit"])
+        .arg(format!("{url}{separator}locations={}", locations.join("|")))
+        .output()
+        .context("failed to run curl")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let json = Json::parse(&String::from_utf8_lossy(&output.stdout))?;
+    let results = json
+        .get("results")
+        .and_then(Json::as_array)
+        .ok_or_else(|| anyhow!("response has no results"))?;
+    if results.len() != locations.len() {
+        bail!(
+            "asked for {} elevations but got {}",
+            locations.len(),
+            results.len()
+        );
+    }
+    Ok(results
+        .iter()
+        .map(|result| result.get("elevation").and_then(Json::as_f64))
+        .collect())
+}
+
+impl Tiles {
+    /// Elevation at `point` from the tile covering it, which is loaded the
+    /// first time it's needed. Points without a tile don't have an elevation.
+    fn elevation(&mut self, point: Point) -> Result<Option<f64>> {
+        let (south, west) = (point.y().floor(), point.x().floor());
+        let name = format!(
+            "{}{:02}{}{:03}.hgt",
+            if south < 0. { 'S' } else { 'N' },
+            south.abs(),
+            if west < 0. { 'W' } else { 'E' },
+            west.abs()
+        );
+        if !self.loaded.contains_key(&name) {
+            let tile_path = if self.path.is_dir() {
+                Some(self.path.join(&name))
+            } else {
+                self.path
+                    .file_name()
+                    .filter(|file_name| file_name.eq_ignore_ascii_case(&name))
+                    .map(|_| self.path.clone())
+            };
+            let tile = match tile_path.filter(|tile_path| tile_path.exists()) {
+                Some(tile_path) => Some(
+                    Tile::read(&tile_path)
+                        .with_context(|| format!("failed to read tile {}", tile_path.display()))?,
+                ),
+                None => None,
+            };
+            self.loaded.insert(name.clone(), tile);
+        }
+        Ok(self.loaded[&name]
+            .as_ref()
+            .and_then(|tile| tile.elevation(point.y() - south, point.x() - west)))
+    }
+}
+
+impl Tile {
+    fn read(path: &Path) -> Result<Tile> {
+        let bytes = fs::read(path)?;
+        let size = ((bytes.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != bytes.len() {
+            bail!("{} bytes isn't the size of an srtm tile", bytes.len());
+        }
+        Ok(Tile {
+            size,
+            samples: bytes
+                .chunks_exact(2)
+                .map(|sample| i16::from_be_bytes([sample[0], sample[1]]))
+                .collect(),
+        })
+    }
+
+    /// Elevation `north` and `east` degrees from the south west corner,
+    /// interpolated between the four samples around it to a tenth of a meter,
+    /// unless any of them are void.
+    fn elevation(&self, north: f64, east: f64) -> Option<f64> {
+        let last = (self.size - 1) as f64;
+        let row = ((1. - north) * last).clamp(0., last);
+        let column = (east * last).clamp(0., last);
+        let (top, left) = (row.floor() as usize, column.floor() as usize);
+        let (bottom, right) = ((top + 1).min(self.size - 1), (left + 1).min(self.size - 1));
+        let sample = |row: usize, column: usize| {
+            let sample = self.samples[row * self.size + column];
+            (sample != VOID).then_some(sample as f64)
+        };
+
+        let (down, across) = (row - top as f64, column - left as f64);
+        let upper = sample(top, left)? * (1. - across) + sample(top, right)? * across;
+        let lower = sample(bottom, left)? * (1. - across) + sample(bottom, right)? * across;
+        Some(((upper * (1. - down) + lower * down) * 10.).round() / 10.)
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::error::Error;
+use std::io;
+
+use gpx::errors::GpxError;
+use thiserror::Error;
+
+/// Ways that splitting a route can fail.
+#[derive(Debug, Error)]
+pub enum SplitError {
+    #[error("gpx file has no track to split")]
+    MissingTrack,
+    #[error("gpx track has no segment to split")]
+    MissingSegment,
+    /// The distance between two waypoints couldn't be measured, which happens
+    /// for points on opposite sides of the earth.
+    #[error("failed to measure the distance between two waypoints: {0}")]
+    DistanceComputation(String),
+    #[error(
+        "waypoint at {latitude}, {longitude} has no timestamp, which is required to split by time"
+    )]
+    MissingTime { latitude: f64, longitude: f64 },
+    /// An option, like those of a `SplitterBuilder`, that doesn't make sense.
+    #[error("{0}")]
+    InvalidOption(String),
+    /// An error from a `SplitStrategy` outside of this crate.
+    #[error("split strategy failed")]
+    Strategy(#[source] Box<dyn Error + Send + Sync>),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("failed to parse gpx file")]
+    Parse(#[from] GpxError),
+}
+
+impl SplitError {
+    /// The message of the error followed by each of its causes, for showing
+    /// where there's only room for a single string.
+    pub fn describe(&self) -> String {
+        let mut message = self.to_string();
+        let mut source = self.source();
+        while let Some(cause) = source {
+            message.push_str(&format!(": {cause}"));
+            source = cause.source();
+        }
+        message
+    }
+}
+
+pub type Result<T> = std::result::Result<T, SplitError>;
@@ -0,0 +1,515 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use anyhow::Result;
+use gpx::Gpx;
+use gpx::Waypoint;
+use time::OffsetDateTime;
+use xml::reader::XmlEvent;
+use xml::EventReader;
+
+use crate::metadata::declare_namespaces;
+use crate::metadata::root_namespaces;
+
+/// Local names of extension elements, compared without case, that mark the
+/// point where a new lap starts or the recording was paused. Garmin and Wahoo
+/// exports don't agree on a name, so a few common ones are accepted.
+const LAP_MARKERS: [&str; 4] = ["lap", "lapstart", "lap_start", "pause"];
+
+/// Namespace of Garmin's GPX extensions.
+const GARMIN_NAMESPACE: &str = "http://www.garmin.com/xmlschemas/GpxExtensions/v3";
+
+/// Display colors from Garmin's extensions, in the order they're cycled
+/// through. They're picked to be easy to tell apart from each other on a map.
+pub const DISPLAY_COLORS: [&str; 8] = [
+    "Red",
+    "Blue",
+    "DarkGreen",
+    "Magenta",
+    "DarkYellow",
+    "Cyan",
+    "DarkRed",
+    "DarkBlue",
+];
+
+/// Adds a Garmin display color extension to the first track in `xml`, or the
+/// first route if there aren't any tracks. It has to have been written by the
+/// `gpx` crate. Extensions go right before the track's segments or the route's
+/// points.
+pub fn add_display_color(xml: &str, color: &str) -> String {
+    let (position, extension) = match xml.find("<trkseg").or_else(|| xml.find("</trk>")) {
+        Some(position) => (position, "TrackExtension"),
+        None => match xml.find("<rtept").or_else(|| xml.find("</rte>")) {
+            Some(position) => (position, "RouteExtension"),
+            None => return xml.to_owned(),
+        },
+    };
+    let line_start = xml[..position].rfind('\n').map_or(0, |index| index + 1);
+    let indent = &xml[line_start..position];
+
+    let mut colored = String::with_capacity(xml.len() + 256);
+    colored.push_str(&xml[..line_start]);
+    for line in [
+        "<extensions>".to_owned(),
+        format!("  <gpxx:{extension}>"),
+        format!("    <gpxx:DisplayColor>{color}</gpxx:DisplayColor>"),
+        format!("  </gpxx:{extension}>"),
+        "</extensions>".to_owned(),
+    ] {
+        colored.push_str(indent);
+        colored.push_str(&line);
+        colored.push('\n');
+    }
+    colored.push_str(&xml[line_start..]);
+
+    // the prefix has to be declared on the root element
+    match colored.find("<gpx ") {
+        Some(root) => colored.insert_str(
+            root + "<gpx".len(),
+            &format!(" xmlns:gpxx=\"{GARMIN_NAMESPACE}\""),
+        ),
+        None => return xml.to_owned(),
+    }
+    colored
+}
+
+/// Indices of the points in the first segment of the first track that are the
+/// last point before a lap or pause marker. The `gpx` crate skips over
+/// `<extensions>` elements entirely, so this reads the XML again separately.
+pub fn lap_indices<R: Read>(reader: R) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+
+    let mut tracks = 0;
+    let mut segments = 0;
+    let mut points = 0;
+    let mut in_point = false;
+
+    for event in EventReader::new(reader) {
+        match event? {
+            XmlEvent::StartElement { name, .. } => match name.local_name.as_str() {
+                "trk" => {
+                    tracks += 1;
+                    segments = 0;
+                }
+                "trkseg" => segments += 1,
+                "trkpt" if tracks == 1 && segments == 1 => {
+                    points += 1;
+                    in_point = true;
+                }
+                local_name
+                    if in_point
+                        && points > 1
+                        && LAP_MARKERS
+                            .iter()
+                            .any(|marker| marker.eq_ignore_ascii_case(local_name)) =>
+                {
+                    // the marker is on the first point of the new lap, so the cut
+                    // goes on the point before it
+                    let index = points - 2;
+                    if indices.last() != Some(&index) {
+                        indices.push(index);
+                    }
+                }
+                _ => {}
+            },
+            XmlEvent::EndElement { name } if name.local_name == "trkpt" => in_point = false,
+            _ => {}
+        }
+    }
+
+    Ok(indices)
+}
+
+/// The `<extensions>` of the track and route points in a GPX file, exactly as
+/// they were written, like Garmin's heart rate, cadence, and temperature. The
+/// `gpx` crate skips over them when it reads a file, so they're found in its
+/// text and put back into the files that are written. Points are matched up
+/// by their position and time, so points that are moved or added don't have
+/// any, and points given new times have to be `retime`d.
+pub struct PointExtensions {
+    extensions: HashMap<PointKey, String>,
+    /// Namespaces declared on the root element, which the extensions may use.
+    namespaces: Vec<(String, String)>,
+}
+
+type PointKey = (u64, u64, Option<OffsetDateTime>);
+
+impl PointExtensions {
+    /// Finds the extensions of the points of `gpx` in `xml`, the text it was
+    /// read from, if any of them have extensions.
+    pub fn find(xml: &str, gpx: &Gpx) -> Option<PointExtensions> {
+        if !xml.contains("<extensions") {
+            return None;
+        }
+
+        let mut extensions = HashMap::new();
+        for (name, points) in points(gpx) {
+            let tags = start_tags(xml, name);
+            // something like a commented out point would throw off matching
+            // the rest of them up, so it's safer to leave them all out
+            if tags.len() != points.len() {
+                return None;
+            }
+            for (tag, waypoint) in tags.into_iter().zip(points) {
+                let Some(body_end) = tag.body_end(xml, name) else {
+                    continue;
+                };
+                let body = &xml[tag.end..body_end];
+                let (Some(start), Some(end)) =
+                    (body.find("<extensions"), body.rfind("</extensions>"))
+                else {
+                    continue;
+                };
+                let start = tag.end + start;
+                let end = tag.end + end + "</extensions>".len();
+                extensions
+                    .entry(key(waypoint))
+                    .or_insert_with(|| unindent(&xml[start..end], indent(xml, start)));
+            }
+        }
+
+        (!extensions.is_empty()).then(|| PointExtensions {
+            extensions,
+            namespaces: root_namespaces(xml),
+        })
+    }
+
+    /// Adds the extensions from `other`, for points that don't already have
+    /// them.
+    pub fn extend(&mut self, other: PointExtensions) {
+        for (key, extensions) in other.extensions {
+            self.extensions.entry(key).or_insert(extensions);
+        }
+        for namespace in other.namespaces {
+            if !self
+                .namespaces
+                .iter()
+                .any(|(prefix, _)| *prefix == namespace.0)
+            {
+                self.namespaces.push(namespace);
+            }
+        }
+    }
+
+    /// Keeps the extensions of the points in `before` for the same points in
+    /// `after`, once they've been given new times.
+    pub fn retime(&mut self, before: &[Waypoint], after: &[Waypoint]) {
+        for (before, after) in before.iter().zip(after) {
+            if let Some(extensions) = self.extensions.get(&key(before)).cloned() {
+                self.extensions.insert(key(after), extensions);
+            }
+        }
+    }
+
+    /// Puts the extensions of the points of `gpx` into `xml`, which has to
+    /// have been written from it by the `gpx` crate.
+    pub fn add(&self, xml: &str, gpx: &Gpx) -> String {
+        let mut insertions = Vec::new();
+        for (name, points) in points(gpx) {
+            for (tag, waypoint) in start_tags(xml, name).into_iter().zip(points) {
+                let Some(extensions) = self.extensions.get(&key(waypoint)) else {
+                    continue;
+                };
+                let indent = indent(xml, tag.start);
+                let mut lines = String::new();
+                for line in extensions.lines() {
+                    lines.push_str(&format!("{indent}  {line}\n"));
+                }
+                match tag.body_end(xml, name) {
+                    // the extensions go on their own lines before the end tag
+                    Some(body_end) => {
+                        let line_start = xml[..body_end].rfind('\n').map_or(0, |index| index + 1);
+                        insertions.push((line_start, line_start, lines));
+                    }
+                    // an empty element has to be opened up to fit them in
+                    None => {
+                        let slash = xml[..tag.end].rfind('/').unwrap_or(tag.end - 1);
+                        let trimmed = xml[..slash].trim_end().len();
+                        insertions.push((trimmed, tag.end, format!(">\n{lines}{indent}</{name}>")));
+                    }
+                }
+            }
+        }
+        if insertions.is_empty() {
+            return xml.to_owned();
+        }
+
+        insertions.sort_by_key(|(start, _, _)| *start);
+        let mut added = String::with_capacity(xml.len() + insertions.len() * 256);
+        let mut position = 0;
+        for (start, end, text) in insertions {
+            added.push_str(&xml[position..start]);
+            added.push_str(&text);
+            position = end;
+        }
+        added.push_str(&xml[position..]);
+        declare_namespaces(&added, &self.namespaces)
+    }
+}
+
+/// The track points and route points of `gpx`, in the order they're written,
+/// with the names of their elements.
+fn points(gpx: &Gpx) -> [(&'static str, Vec<&Waypoint>); 2] {
+    [
+        (
+            "trkpt",
+            gpx.tracks
+                .iter()
+                .flat_map(|track| &track.segments)
+                .flat_map(|segment| &segment.points)
+                .collect(),
+        ),
+        (
+            "rtept",
+            gpx.routes.iter().flat_map(|route| &route.points).collect(),
+        ),
+    ]
+}
+
+fn key(waypoint: &Waypoint) -> PointKey {
+    let point = waypoint.point();
+    (
+        point.x().to_bits(),
+        point.y().to_bits(),
+        waypoint.time.map(OffsetDateTime::from),
+    )
+}
+
+/// A start tag in the text of a file.
+struct StartTag {
+    start: usize,
+    /// Position just after the tag's `>`.
+    end: usize,
+}
+
+impl StartTag {
+    /// Where the element's content ends at its end tag, unless it's empty,
+    /// like `<trkpt lat="1" lon="2"/>`.
+    fn body_end(&self, xml: &str, name: &str) -> Option<usize> {
+        if xml[..self.end].ends_with("/>") {
+            return None;
+        }
+        Some(self.end + xml[self.end..].find(&format!("</{name}>"))?)
+    }
+}
+
+/// The start tags of the elements named `name` in `xml`.
+fn start_tags(xml: &str, name: &str) -> Vec<StartTag> {
+    let open = format!("<{name}");
+    xml.match_indices(&open)
+        .filter_map(|(start, _)| {
+            let rest = &xml[start + open.len()..];
+            let next = rest.chars().next()?;
+            if !(next == '>' || next == '/' || next.is_whitespace()) {
+                return None;
+            }
+            Some(StartTag {
+                start,
+                end: start + open.len() + rest.find('>')? + 1,
+            })
+        })
+        .collect()
+}
+
+/// The whitespace before `position` on its line, if there's nothing else
+/// before it.
+fn indent(xml: &str, position: usize) -> &str {
+    let line_start = xml[..position].rfind('\n').map_or(0, |index| index + 1);
+    let indent = &xml[line_start..position];
+    if indent.trim().is_empty() {
+        indent
+    } else {
+        ""
+    }
+}
+
+/// `text` with `indent` taken off the start of each of its lines after the
+/// first, so that it can be indented again wherever it goes.
+fn unindent(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| line.strip_prefix(indent).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use gpxsplit::split_all;
+    use gpxsplit::SplitOptions;
+
+    use super::*;
+
+    /// Like a Strava export, with Garmin's track point extensions spread over
+    /// several lines.
+    fn strava() -> String {
+        let mut points = String::new();
+        for index in 0..5 {
+            points.push_str(&format!(
+                r#"   <trkpt lat="45.{index}" lon="-110.{index}">
+    <ele>{}</ele>
+    <time>2025-06-13T13:0{index}:00Z</time>
+    <extensions>
+     <gpxtpx:TrackPointExtension>
+      <gpxtpx:atemp>2{index}</gpxtpx:atemp>
+      <gpxtpx:hr>12{index}</gpxtpx:hr>
+      <gpxtpx:cad>8{index}</gpxtpx:cad>
+     </gpxtpx:TrackPointExtension>
+    </extensions>
+   </trkpt>
+"#,
+                1000 + index
+            ));
+        }
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="StravaGPX" version="1.1" xmlns="http://www.topografix.com/GPX/1/1" xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+ <trk>
+  <name>Morning Ride</name>
+  <trkseg>
+{points}  </trkseg>
+ </trk>
+</gpx>
+"#
+        )
+    }
+
+    /// Like a Wahoo export, with extensions in a namespace nothing else knows
+    /// about, and every other point written as an empty element.
+    fn wahoo() -> String {
+        let mut points = String::new();
+        for index in 0..6 {
+            if index % 2 == 0 {
+                points.push_str(&format!(
+                    "<trkpt lat=\"39.{index}\" lon=\"-105.{index}\"><extensions><wahoo:hr>13{index}</wahoo:hr><wahoo:power>2{index}0</wahoo:power></extensions></trkpt>\n"
+                ));
+            } else {
+                points.push_str(&format!(
+                    "<trkpt lat=\"39.{index}\" lon=\"-105.{index}\"/>\n"
+                ));
+            }
+        }
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="Wahoo ELEMNT" version="1.1" xmlns="http://www.topografix.com/GPX/1/1" xmlns:wahoo="https://example.com/wahoo/v1">
+<trk><trkseg>
+{points}</trkseg></trk>
+</gpx>
+"#
+        )
+    }
+
+    /// Splits `xml` into parts of `max_points`, reversing it first if asked,
+    /// and writes them out with their extensions put back.
+    fn split_with_extensions(xml: &str, max_points: usize, reverse: bool) -> Vec<String> {
+        let mut gpx = gpx::read(xml.as_bytes()).unwrap();
+        let extensions = PointExtensions::find(xml, &gpx).unwrap();
+        if reverse {
+            crate::reverse(&mut gpx);
+        }
+        let options = SplitOptions {
+            max_points: Some(max_points),
+            ..SplitOptions::default()
+        };
+        split_all(gpx, options)
+            .unwrap()
+            .iter()
+            .map(|part| {
+                let written = String::from_utf8(crate::encode(part).unwrap()).unwrap();
+                extensions.add(&written, part)
+            })
+            .collect()
+    }
+
+    /// The text of each element with the local name `name` in `xml`, which
+    /// has to be well-formed with all of its prefixes declared.
+    fn values(xml: &str, name: &str) -> Vec<String> {
+        let mut values = Vec::new();
+        let mut inside = false;
+        for event in EventReader::new(xml.as_bytes()) {
+            match event.unwrap() {
+                XmlEvent::StartElement { name: element, .. } => inside = element.local_name == name,
+                XmlEvent::Characters(text) if inside => values.push(text),
+                XmlEvent::EndElement { .. } => inside = false,
+                _ => {}
+            }
+        }
+        values
+    }
+
+    /// Checks that every point in the parts has the same extensions it had in
+    /// `xml`.
+    fn assert_extensions_kept(xml: &str, parts: &[String]) {
+        let original = PointExtensions::find(xml, &gpx::read(xml.as_bytes()).unwrap()).unwrap();
+        for part in parts {
+            let gpx = gpx::read(part.as_bytes()).unwrap();
+            let found = PointExtensions::find(part, &gpx).unwrap();
+            for (key, extensions) in &found.extensions {
+                assert_eq!(original.extensions.get(key), Some(extensions));
+            }
+        }
+    }
+
+    #[test]
+    fn strava_extensions_survive_splitting() {
+        let xml = strava();
+        let parts = split_with_extensions(&xml, 3, false);
+        assert_eq!(parts.len(), 2);
+        for part in &parts {
+            assert!(part.contains(
+                r#"xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1""#
+            ));
+        }
+        assert_eq!(values(&parts[0], "hr"), ["120", "121", "122"]);
+        assert_eq!(values(&parts[1], "hr"), ["122", "123", "124"]);
+        assert_eq!(values(&parts[0], "cad"), ["80", "81", "82"]);
+        assert_eq!(values(&parts[1], "atemp"), ["22", "23", "24"]);
+        assert_extensions_kept(&xml, &parts);
+    }
+
+    #[test]
+    fn wahoo_extensions_survive_splitting() {
+        let xml = wahoo();
+        let parts = split_with_extensions(&xml, 4, false);
+        assert_eq!(parts.len(), 2);
+        for part in &parts {
+            assert!(part.contains(r#"xmlns:wahoo="https://example.com/wahoo/v1""#));
+        }
+        assert_eq!(values(&parts[0], "hr"), ["130", "132"]);
+        assert_eq!(values(&parts[1], "hr"), ["134"]);
+        assert_eq!(values(&parts[1], "power"), ["240"]);
+        // the points without extensions are still written without any
+        let gpx = gpx::read(parts[1].as_bytes()).unwrap();
+        let found = PointExtensions::find(&parts[1], &gpx).unwrap();
+        assert_eq!(found.extensions.len(), 1);
+        assert_extensions_kept(&xml, &parts);
+    }
+
+    #[test]
+    fn extensions_follow_reversed_points() {
+        let xml = strava();
+        let parts = split_with_extensions(&xml, 3, true);
+        assert_eq!(values(&parts[0], "hr"), ["124", "123", "122"]);
+        assert_eq!(values(&parts[1], "hr"), ["122", "121", "120"]);
+        assert_extensions_kept(&xml, &parts);
+
+        let xml = wahoo();
+        let parts = split_with_extensions(&xml, 4, true);
+        assert_eq!(values(&parts[0], "hr"), ["134", "132"]);
+        assert_eq!(values(&parts[1], "hr"), ["132", "130"]);
+        assert_extensions_kept(&xml, &parts);
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A C interface with the `ffi` feature, for apps in other languages, which
+//! `include/gpxsplit.h` declares.
+//!
+//! Functions that can fail return a negative number, and
+//! `gpxsplit_last_error` then describes what went wrong.
+
+use std::cell::RefCell;
+use std::ffi::c_char;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::path::Path;
+use std::ptr;
+use std::slice;
+
+use crate::error::Result;
+use crate::split_all;
+use crate::split_gpx_bytes;
+use crate::splitter::simple_options;
+use crate::write_part_to_writer;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// The contents of a GPX file for a part, from `gpxsplit_split_bytes`.
+#[repr(C)]
+pub struct GpxsplitPart {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+/// Splits the GPX file at `in_path` into files of `km` kilometers and at most
+/// `max_points` points, either of which can be zero to not use it. The files
+/// are numbered after the input file, like `route_01.gpx`, and written to
+/// `out_dir`, or next to the input file if it's null. Existing files are
+/// overwritten. Returns the number of files.
+///
+/// # Safety
+///
+/// `in_path` and `out_dir`, if it isn't null, have to be C strings.
+#[no_mangle]
+pub unsafe extern "C" fn gpxsplit_split_file(
+    in_path: *const c_char,
+    km: f64,
+    max_points: u32,
+    out_dir: *const c_char,
+) -> i32 {
+    let in_path = Path::new(CStr::from_ptr(in_path).to_str().unwrap_or_default());
+    let out_dir = if out_dir.is_null() {
+        in_path.parent().unwrap_or(Path::new(""))
+    } else {
+        Path::new(CStr::from_ptr(out_dir).to_str().unwrap_or_default())
+    };
+    report(split_file(in_path, km, max_points, out_dir))
+}
+
+/// Splits the GPX file in the `len` bytes at `input`, with `km` and
+/// `max_points` like `gpxsplit_split_file`. The parts are put in a new array
+/// at `parts`, which has to be freed with `gpxsplit_free_parts`. Returns the
+/// number of parts.
+///
+/// # Safety
+///
+/// `input` has to point to `len` readable bytes, and `parts` to a writable
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn gpxsplit_split_bytes(
+    input: *const u8,
+    len: usize,
+    km: f64,
+    max_points: u32,
+    parts: *mut *mut GpxsplitPart,
+) -> i32 {
+    *parts = ptr::null_mut();
+    let input = slice::from_raw_parts(input, len);
+    let encoded = simple_options(km, max_points)
+        .and_then(|options| split_gpx_bytes(input, options))
+        .map(|encoded| {
+            let array: Box<[GpxsplitPart]> = encoded
+                .into_iter()
+                .map(|bytes| {
+                    let bytes = Box::leak(bytes.into_boxed_slice());
+                    GpxsplitPart {
+                        data: bytes.as_mut_ptr(),
+                        len: bytes.len(),
+                    }
+                })
+                .collect();
+            let count = array.len();
+            *parts = Box::leak(array).as_mut_ptr();
+            count
+        });
+    report(encoded)
+}
+
+/// Frees the `count` parts from `gpxsplit_split_bytes`.
+///
+/// # Safety
+///
+/// `parts` has to be from `gpxsplit_split_bytes`, with the number of parts
+/// it returned, and can't be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn gpxsplit_free_parts(parts: *mut GpxsplitPart, count: usize) {
+    if parts.is_null() {
+        return;
+    }
+    let array = Box::from_raw(ptr::slice_from_raw_parts_mut(parts, count));
+    for part in array.iter() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            part.data, part.len,
+        )));
+    }
+}
+
+/// Describes the last error on this thread, or null if there hasn't been one.
+/// The string is valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn gpxsplit_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+fn split_file(in_path: &Path, km: f64, max_points: u32, out_dir: &Path) -> Result<usize> {
+    let parts = split_all(
+        gpx::read(BufReader::new(File::open(in_path)?))?,
+        simple_options(km, max_points)?,
+    )?;
+    let stem = in_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let pad = parts.len().to_string().len().max(2);
+    for (index, part) in parts.iter().enumerate() {
+        let path = out_dir.join(format!("{stem}_{:0pad$}.gpx", index + 1));
+        write_part_to_writer(part, BufWriter::new(File::create(path)?))?;
+    }
+    Ok(parts.len())
+}
+
+/// Returns the number from `result`, or saves its error and returns -1.
+fn report(result: Result<usize>) -> i32 {
+    match result {
+        Ok(count) => count as i32,
+        Err(error) => {
+            LAST_ERROR.with(|last_error| {
+                *last_error.borrow_mut() = CString::new(error.describe()).ok();
+            });
+            -1
+        }
+    }
+}
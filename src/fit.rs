@@ -0,0 +1,534 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use anyhow::bail;
+use anyhow::Result;
+use geo_types::Point;
+use gpx::Gpx;
+use gpx::GpxVersion;
+use gpx::Track;
+use gpx::TrackSegment;
+use gpx::Waypoint;
+use time::OffsetDateTime;
+
+use crate::course::Course;
+use crate::course::CoursePointKind;
+
+/// Version of the FIT profile that the messages are written from.
+const PROFILE_VERSION: u16 = 2132;
+
+/// FIT timestamps count seconds from 1989-12-31, rather than from 1970.
+const FIT_EPOCH: i64 = 631065600;
+
+/// Sizes of the fixed length string fields, including the terminating zero.
+const COURSE_NAME_SIZE: u8 = 32;
+const COURSE_POINT_NAME_SIZE: u8 = 16;
+
+// global message numbers
+const FILE_ID: u16 = 0;
+const LAP: u16 = 19;
+const RECORD: u16 = 20;
+const EVENT: u16 = 21;
+const COURSE: u16 = 31;
+const COURSE_POINT: u16 = 32;
+
+// base types
+const ENUM: u8 = 0x00;
+const UINT16: u8 = 0x84;
+const SINT32: u8 = 0x85;
+const UINT32: u8 = 0x86;
+const STRING: u8 = 0x07;
+const UINT32Z: u8 = 0x8c;
+
+/// Writes `course` as a binary FIT course file, with a file id, the course
+/// itself, a lap covering all of it, a record for each point, and its course
+/// points, in the order that Garmin's course file guidelines use.
+pub fn write_fit<W: Write>(mut writer: W, course: &Course) -> io::Result<()> {
+    let mut data = Messages::default();
+    let start = course.track.first();
+    let end = course.track.last();
+    let start_time = start.map_or(0, |point| timestamp(point.time));
+    let end_time = end.map_or(0, |point| timestamp(point.time));
+
+    data.define(
+        0,
+        FILE_ID,
+        &[
+            (0, 1, ENUM),
+            (1, 2, UINT16),
+            (2, 2, UINT16),
+            (3, 4, UINT32Z),
+            (4, 4, UINT32),
+        ],
+    );
+    data.record(0);
+    data.u8(6); // course
+    data.u16(255); // development manufacturer
+    data.u16(0);
+    data.u32(1);
+    data.u32(start_time);
+
+    data.define(1, COURSE, &[(4, 1, ENUM), (5, COURSE_NAME_SIZE, STRING)]);
+    data.record(1);
+    data.u8(2); // cycling
+    data.string(&course.name, COURSE_NAME_SIZE);
+
+    data.define(
+        2,
+        LAP,
+        &[
+            (253, 4, UINT32),
+            (2, 4, UINT32),
+            (3, 4, SINT32),
+            (4, 4, SINT32),
+            (5, 4, SINT32),
+            (6, 4, SINT32),
+            (7, 4, UINT32),
+            (8, 4, UINT32),
+            (9, 4, UINT32),
+        ],
+    );
+    data.record(2);
+    data.u32(start_time);
+    data.u32(start_time);
+    data.i32(start.map_or(i32::MAX, |point| semicircles(point.latitude)));
+    data.i32(start.map_or(i32::MAX, |point| semicircles(point.longitude)));
+    data.i32(end.map_or(i32::MAX, |point| semicircles(point.latitude)));
+    data.i32(end.map_or(i32::MAX, |point| semicircles(point.longitude)));
+    data.u32((course.seconds() * 1000.) as u32);
+    data.u32((course.seconds() * 1000.) as u32);
+    data.u32((course.meters() * 100.) as u32);
+
+    // the timer starts at the beginning of the course and stops at the end
+    data.define(3, EVENT, &[(253, 4, UINT32), (0, 1, ENUM), (1, 1, ENUM)]);
+    data.record(3);
+    data.u32(start_time);
+    data.u8(0); // timer
+    data.u8(0); // start
+
+    data.define(
+        4,
+        RECORD,
+        &[
+            (253, 4, UINT32),
+            (0, 4, SINT32),
+            (1, 4, SINT32),
+            (2, 2, UINT16),
+            (5, 4, UINT32),
+        ],
+    );
+    for point in &course.track {
+        data.record(4);
+        data.u32(timestamp(point.time));
+        data.i32(semicircles(point.latitude));
+        data.i32(semicircles(point.longitude));
+        data.u16(point.elevation.map_or(u16::MAX, |elevation| {
+            ((elevation + 500.) * 5.)
+                .round()
+                .clamp(0., u16::MAX as f64 - 1.) as u16
+        }));
+        data.u32((point.meters * 100.).round() as u32);
+    }
+
+    data.record(3);
+    data.u32(end_time);
+    data.u8(0); // timer
+    data.u8(9); // stop disable all
+
+    data.define(
+        5,
+        COURSE_POINT,
+        &[
+            (254, 2, UINT16),
+            (1, 4, UINT32),
+            (2, 4, SINT32),
+            (3, 4, SINT32),
+            (4, 4, UINT32),
+            (5, 1, ENUM),
+            (6, COURSE_POINT_NAME_SIZE, STRING),
+        ],
+    );
+    for (index, course_point) in course.course_points.iter().enumerate() {
+        let point = &course.track[course_point.index];
+        data.record(5);
+        data.u16(index as u16);
+        data.u32(timestamp(point.time));
+        data.i32(semicircles(point.latitude));
+        data.i32(semicircles(point.longitude));
+        data.u32((point.meters * 100.).round() as u32);
+        data.u8(point_type(course_point.kind));
+        data.string(&course_point.name, COURSE_POINT_NAME_SIZE);
+    }
+
+    let data = data.bytes;
+    let data_size =
+        u32::try_from(data.len()).map_err(|_| io::Error::other("too much data for a fit file"))?;
+    let mut header = vec![14, 0x20];
+    header.extend(PROFILE_VERSION.to_le_bytes());
+    header.extend(data_size.to_le_bytes());
+    header.extend(b".FIT");
+    header.extend(crc(0, &header).to_le_bytes());
+
+    // the file's checksum covers the header too
+    let file_crc = crc(crc(0, &header), &data);
+    writer.write_all(&header)?;
+    writer.write_all(&data)?;
+    writer.write_all(&file_crc.to_le_bytes())
+}
+
+/// Whether `data` starts like a FIT file.
+pub fn is_fit(data: &[u8]) -> bool {
+    data.len() >= 12 && data[0] >= 12 && &data[8..12] == b".FIT"
+}
+
+/// Reads the records of a FIT activity or course into a GPX file with a
+/// single track, and any course points as waypoints. Records without a
+/// position, like those from before a device has a fix, are skipped.
+pub fn read_fit(data: &[u8]) -> Result<Gpx> {
+    if !is_fit(data) {
+        bail!("not a fit file");
+    }
+    let header_size = data[0] as usize;
+    let data_size = u32::from_le_bytes(data[4..8].try_into()?) as usize;
+    let Some(records) = data.get(header_size..header_size + data_size) else {
+        bail!("fit file is truncated");
+    };
+
+    let mut definitions: HashMap<u8, Definition> = HashMap::new();
+    let mut last_timestamp = 0u64;
+    let mut name = None;
+    let mut points = Vec::new();
+    let mut course_points = Vec::new();
+
+    let mut position = 0;
+    while position < records.len() {
+        let header = records[position];
+        position += 1;
+
+        if header & 0x80 == 0 && header & 0x40 != 0 {
+            let fixed = records.get(position..position + 5).ok_or_else(truncated)?;
+            let big_endian = fixed[1] == 1;
+            let global = if big_endian {
+                u16::from_be_bytes([fixed[2], fixed[3]])
+            } else {
+                u16::from_le_bytes([fixed[2], fixed[3]])
+            };
+            let field_count = fixed[4] as usize;
+            position += 5;
+
+            let mut fields = Vec::with_capacity(field_count);
+            for _ in 0..field_count {
+                let field = records.get(position..position + 3).ok_or_else(truncated)?;
+                fields.push((field[0], field[1] as usize));
+                position += 3;
+            }
+
+            // developer fields aren't read, but their sizes are needed to skip
+            // over them
+            let mut developer_size = 0;
+            if header & 0x20 != 0 {
+                let count = *records.get(position).ok_or_else(truncated)? as usize;
+                position += 1;
+                for _ in 0..count {
+                    developer_size += *records.get(position + 1).ok_or_else(truncated)? as usize;
+                    position += 3;
+                }
+            }
+
+            definitions.insert(
+                header & 0x0f,
+                Definition {
+                    global,
+                    big_endian,
+                    fields,
+                    developer_size,
+                },
+            );
+            continue;
+        }
+
+        // compressed timestamp headers replace the low bits of the last
+        // timestamp with an offset, instead of having a timestamp field
+        let (local, compressed_timestamp) = if header & 0x80 != 0 {
+            let offset = (header & 0x1f) as u64;
+            let mut timestamp = (last_timestamp & !0x1f) | offset;
+            if offset < last_timestamp & 0x1f {
+                timestamp += 0x20;
+            }
+            ((header >> 5) & 0x03, Some(timestamp))
+        } else {
+            (header & 0x0f, None)
+        };
+
+        let Some(definition) = definitions.get(&local) else {
+            bail!("fit message uses undefined local message type {local}");
+        };
+        let size = definition.size();
+        let message = records
+            .get(position..position + size)
+            .ok_or_else(truncated)?;
+        position += size;
+        let message = Message {
+            definition,
+            bytes: message,
+        };
+
+        if let Some(timestamp) = message.uint(253).or(compressed_timestamp) {
+            last_timestamp = timestamp;
+        }
+
+        match definition.global {
+            COURSE => name = message.string(5),
+            RECORD => {
+                let (Some(latitude), Some(longitude)) = (message.sint(0), message.sint(1)) else {
+                    continue;
+                };
+                let mut waypoint = Waypoint::new(Point::new(
+                    degrees(longitude as i32),
+                    degrees(latitude as i32),
+                ));
+                waypoint.elevation = message
+                    .uint(78)
+                    .or_else(|| message.uint(2))
+                    .map(|altitude| altitude as f64 / 5. - 500.);
+                if message.uint(253).is_some() || compressed_timestamp.is_some() {
+                    waypoint.time =
+                        OffsetDateTime::from_unix_timestamp(last_timestamp as i64 + FIT_EPOCH)
+                            .ok()
+                            .map(Into::into);
+                }
+                points.push(waypoint);
+            }
+            COURSE_POINT => {
+                let (Some(latitude), Some(longitude)) = (message.sint(2), message.sint(3)) else {
+                    continue;
+                };
+                let mut waypoint = Waypoint::new(Point::new(
+                    degrees(longitude as i32),
+                    degrees(latitude as i32),
+                ));
+                waypoint.name = message.string(6);
+                waypoint.type_ = message.uint(5).and_then(|kind| {
+                    COURSE_POINT_KINDS
+                        .iter()
+                        .find(|(value, _)| *value as u64 == kind)
+                        .map(|(_, kind)| kind.name().to_owned())
+                });
+                course_points.push(waypoint);
+            }
+            _ => {}
+        }
+    }
+
+    let mut track = Track::new();
+    track.name = name;
+    let mut segment = TrackSegment::new();
+    segment.points = points;
+    track.segments.push(segment);
+
+    Ok(Gpx {
+        version: GpxVersion::Gpx11,
+        waypoints: course_points,
+        tracks: vec![track],
+        ..Gpx::default()
+    })
+}
+
+fn truncated() -> anyhow::Error {
+    anyhow::anyhow!("fit file is truncated")
+}
+
+/// Converts the 32 bit fractions of a circle that FIT uses to degrees.
+fn degrees(semicircles: i32) -> f64 {
+    semicircles as f64 * (180. / 2f64.powi(31))
+}
+
+/// The layout of a local message type, from its definition message.
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    /// Number and size of each field.
+    fields: Vec<(u8, usize)>,
+    developer_size: usize,
+}
+
+impl Definition {
+    fn size(&self) -> usize {
+        self.fields.iter().map(|(_, size)| size).sum::<usize>() + self.developer_size
+    }
+}
+
+struct Message<'a> {
+    definition: &'a Definition,
+    bytes: &'a [u8],
+}
+
+impl Message<'_> {
+    fn field(&self, number: u8) -> Option<&[u8]> {
+        let mut offset = 0;
+        for (field, size) in &self.definition.fields {
+            if *field == number {
+                return Some(&self.bytes[offset..offset + size]);
+            }
+            offset += size;
+        }
+        None
+    }
+
+    /// An unsigned integer field, or `None` if it's missing or has the
+    /// invalid value of all ones.
+    fn uint(&self, number: u8) -> Option<u64> {
+        let bytes = self.field(number)?;
+        if bytes.is_empty() || bytes.len() > 8 || bytes.iter().all(|byte| *byte == 0xff) {
+            return None;
+        }
+        let mut value = 0u64;
+        for index in 0..bytes.len() {
+            let byte = if self.definition.big_endian {
+                bytes[index]
+            } else {
+                bytes[bytes.len() - 1 - index]
+            };
+            value = (value << 8) | byte as u64;
+        }
+        Some(value)
+    }
+
+    /// A signed 32 bit field, or `None` if it's missing or has the invalid
+    /// value of the largest positive number.
+    fn sint(&self, number: u8) -> Option<i64> {
+        let bytes: [u8; 4] = self.field(number)?.try_into().ok()?;
+        let value = if self.definition.big_endian {
+            i32::from_be_bytes(bytes)
+        } else {
+            i32::from_le_bytes(bytes)
+        };
+        (value != i32::MAX).then_some(value as i64)
+    }
+
+    fn string(&self, number: u8) -> Option<String> {
+        let bytes = self.field(number)?;
+        let end = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .unwrap_or(bytes.len());
+        let string = String::from_utf8_lossy(&bytes[..end]).into_owned();
+        (!string.is_empty()).then_some(string)
+    }
+}
+
+/// Values of the FIT course point types that have a matching kind.
+const COURSE_POINT_KINDS: [(u8, CoursePointKind); 7] = [
+    (0, CoursePointKind::Generic),
+    (1, CoursePointKind::Summit),
+    (2, CoursePointKind::Valley),
+    (3, CoursePointKind::Water),
+    (4, CoursePointKind::Food),
+    (5, CoursePointKind::Danger),
+    (9, CoursePointKind::FirstAid),
+];
+
+fn point_type(kind: CoursePointKind) -> u8 {
+    match kind {
+        CoursePointKind::Generic => 0,
+        CoursePointKind::Summit => 1,
+        CoursePointKind::Valley => 2,
+        CoursePointKind::Water => 3,
+        CoursePointKind::Food => 4,
+        CoursePointKind::Danger => 5,
+        CoursePointKind::FirstAid => 9,
+    }
+}
+
+fn timestamp(time: OffsetDateTime) -> u32 {
+    (time.unix_timestamp() - FIT_EPOCH).clamp(0, u32::MAX as i64 - 1) as u32
+}
+
+/// Converts degrees to the 32 bit fractions of a circle that FIT uses.
+fn semicircles(degrees: f64) -> i32 {
+    (degrees * (2f64.powi(31) / 180.)).round() as i32
+}
+
+/// The data records of a FIT file. Each message is written after a
+/// definition that gives its fields, numbered by the profile, with their sizes
+/// and base types.
+#[derive(Default)]
+struct Messages {
+    bytes: Vec<u8>,
+}
+
+impl Messages {
+    fn define(&mut self, local: u8, global: u16, fields: &[(u8, u8, u8)]) {
+        self.bytes.push(0x40 | local);
+        self.bytes.push(0); // reserved
+        self.bytes.push(0); // little endian
+        self.bytes.extend(global.to_le_bytes());
+        self.bytes.push(fields.len() as u8);
+        for (number, size, base_type) in fields {
+            self.bytes.extend([*number, *size, *base_type]);
+        }
+    }
+
+    fn record(&mut self, local: u8) {
+        self.bytes.push(local);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes.extend(value.to_le_bytes());
+    }
+
+    /// Writes `value` padded with zeros to `size` bytes, cutting it off at a
+    /// character boundary if it's too long.
+    fn string(&mut self, value: &str, size: u8) {
+        let mut length = value.len().min(size as usize - 1);
+        while !value.is_char_boundary(length) {
+            length -= 1;
+        }
+        self.bytes.extend(&value.as_bytes()[..length]);
+        self.bytes
+            .extend(std::iter::repeat_n(0, size as usize - length));
+    }
+}
+
+/// The CRC-16 checksum that FIT files use.
+fn crc(mut crc: u16, data: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xcc01, 0xd801, 0x1400, 0xf001, 0x3c00, 0x2800, 0xe401, 0xa001, 0x6c00, 0x7800,
+        0xb401, 0x5000, 0x9c01, 0x8801, 0x4400,
+    ];
+    for byte in data {
+        for nibble in [byte & 0xf, byte >> 4] {
+            let tmp = TABLE[(crc & 0xf) as usize];
+            crc = (crc >> 4) & 0x0fff;
+            crc = crc ^ tmp ^ TABLE[nibble as usize];
+        }
+    }
+    crc
+}
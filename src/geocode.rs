@@ -0,0 +1,187 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+use anyhow::Context;
+use anyhow::Result;
+use geoutils::Location;
+use gpx::Waypoint;
+
+use crate::json::Json;
+use crate::logging::info;
+
+/// Reverse geocoding service, which asks for no more than one request per
+/// second.
+const NOMINATIM_URL: &str = "https://nominatim.openstreetmap.org/reverse";
+
+/// Waypoints in the GPX file within this many meters are used as the name of
+/// a place when the geocoding service can't be reached.
+const LANDMARK_METERS: f64 = 5000.;
+
+/// Names places along the route with the Nominatim reverse geocoding service.
+/// Names are cached on disk by their coordinates, so running again over the
+/// same route doesn't need the network. If the service can't be reached, the
+/// closest named waypoint in the GPX file is used instead, or the coordinates
+/// if there isn't one nearby.
+pub struct Geocoder {
+    cache_path: Option<PathBuf>,
+    cache: RefCell<Vec<(String, Json)>>,
+    loaded: usize,
+    landmarks: Vec<(Location, String)>,
+    offline: Cell<bool>,
+    requested: Cell<bool>,
+}
+
+impl Geocoder {
+    /// Loads the cache, if there is one. `landmarks` are the named waypoints
+    /// to fall back to.
+    pub fn new(landmarks: Vec<(Location, String)>) -> Geocoder {
+        let cache_path = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .map(|cache| cache.join("gpxsplit").join("places.json"));
+
+        // a missing or broken cache just means everything is looked up again
+        let cache = cache_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|text| Json::parse(&text).ok())
+            .and_then(|json| match json {
+                Json::Object(entries) => Some(entries),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        Geocoder {
+            cache_path,
+            loaded: cache.len(),
+            cache: RefCell::new(cache),
+            landmarks,
+            offline: Cell::new(false),
+            requested: Cell::new(false),
+        }
+    }
+
+    /// Name of the place closest to `waypoint`.
+    pub fn place(&self, waypoint: &Waypoint) -> String {
+        let point = waypoint.point();
+        let key = format!("{:.3},{:.3}", point.y(), point.x());
+
+        let cached = self
+            .cache
+            .borrow()
+            .iter()
+            .find(|(cached_key, _)| *cached_key == key)
+            .and_then(|(_, name)| name.as_str().map(str::to_owned));
+        if let Some(name) = cached {
+            return name;
+        }
+
+        match self.request(point.y(), point.x()) {
+            Some(name) => {
+                self.cache
+                    .borrow_mut()
+                    .push((key, Json::String(name.clone())));
+                name
+            }
+            None => self.landmark(point.y(), point.x()).unwrap_or(key),
+        }
+    }
+
+    /// Writes any new names to the cache.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.cache_path else {
+            return Ok(());
+        };
+        if self.cache.borrow().len() == self.loaded {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let cache = Json::Object(self.cache.borrow().clone());
+        fs::write(path, cache.to_string())
+            .with_context(|| format!("failed to write geocoding cache {}", path.display()))
+    }
+
+    /// Looks up the name of a place with the geocoding service, using `curl`
+    /// so that this doesn't need an HTTP client of its own. After the service
+    /// can't be reached once, it isn't tried again, but a response without a
+    /// name only falls back for this place.
+    fn request(&self, latitude: f64, longitude: f64) -> Option<String> {
+        if self.offline.get() {
+            return None;
+        }
+        if self.requested.replace(true) {
+            sleep(Duration::from_secs(1));
+        }
+
+        let output = Command::new("curl")
+            .args(["--silent", "--fail", "--location", "--max-time", "10"])
+            .args(["--user-agent", "gpxsplit"])
+            .arg(format!(
+                "{NOMINATIM_URL}?format=jsonv2&zoom=10&lat={latitude}&lon={longitude}"
+            ))
+            .output();
+
+        let Some(output) = output.ok().filter(|output| output.status.success()) else {
+            info!("couldn't reach the geocoding service, naming places from waypoints instead");
+            self.offline.set(true);
+            return None;
+        };
+
+        // places out at sea or in the wilderness come back without a name
+        String::from_utf8(output.stdout)
+            .ok()
+            .and_then(|text| Json::parse(&text).ok())
+            .and_then(|json| place_name(&json))
+    }
+
+    fn landmark(&self, latitude: f64, longitude: f64) -> Option<String> {
+        let location = Location::new(latitude, longitude);
+        self.landmarks
+            .iter()
+            .map(|(landmark, name)| (location.haversine_distance_to(landmark).meters(), name))
+            .filter(|(meters, _)| *meters <= LANDMARK_METERS)
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, name)| name.clone())
+    }
+}
+
+/// The most specific settlement name in a Nominatim response.
+fn place_name(json: &Json) -> Option<String> {
+    let address = json.get("address");
+    [
+        "city",
+        "town",
+        "village",
+        "hamlet",
+        "municipality",
+        "county",
+    ]
+    .iter()
+    .find_map(|key| address?.get(key)?.as_str())
+    .or_else(|| json.get("name")?.as_str())
+    .filter(|name| !name.is_empty())
+    .map(str::to_owned)
+}
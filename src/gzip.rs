@@ -0,0 +1,608 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::zip::crc32;
+
+/// Distance back that matches can be found at, which is the most that
+/// deflate allows.
+const WINDOW: usize = 32768;
+
+/// Candidates checked for each match. More finds slightly longer matches, but
+/// GPX files repeat so much that the first few are almost always enough.
+const MAX_CHAIN: usize = 32;
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+const LENGTH_BASES: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA_BITS: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASES: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Compresses `data` into the gzip format. It's deflated as a single block
+/// with the fixed Huffman codes, which is simple and gets most of the benefit
+/// for text as repetitive as GPX.
+pub fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![
+        0x1f, 0x8b, // magic number
+        8,    // deflate
+        0,    // no flags
+        0, 0, 0, 0,   // no modification time
+        0,   // no extra flags
+        255, // unknown operating system
+    ];
+    output.extend(deflate(data));
+    output.extend(crc32(data).to_le_bytes());
+    output.extend((data.len() as u32).to_le_bytes());
+    output
+}
+
+/// Whether `data` starts like a gzip file.
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&[0x1f, 0x8b])
+}
+
+/// Decompresses gzip data, including files made of several gzip members
+/// one after another, which is how `cat` joins compressed files.
+pub fn gunzip(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    while !data.is_empty() {
+        if !is_gzip(data) || data.len() < 10 || data[2] != 8 {
+            bail!("not a gzip file");
+        }
+
+        // skip over the optional fields of the header
+        let flags = data[3];
+        let mut position = 10;
+        if flags & 4 != 0 {
+            let length = u16::from_le_bytes(
+                data.get(position..position + 2)
+                    .ok_or_else(truncated)?
+                    .try_into()?,
+            );
+            position += 2 + length as usize;
+        }
+        for flag in [8, 16] {
+            if flags & flag != 0 {
+                let end = data
+                    .get(position..)
+                    .and_then(|rest| rest.iter().position(|byte| *byte == 0))
+                    .ok_or_else(truncated)?;
+                position += end + 1;
+            }
+        }
+        if flags & 2 != 0 {
+            position += 2;
+        }
+
+        let start = output.len();
+        let mut bits = BitReader {
+            data: data.get(position..).ok_or_else(truncated)?,
+            position: 0,
+        };
+        inflate_blocks(&mut bits, &mut output)?;
+        position += bits.position.div_ceil(8);
+
+        let trailer = data.get(position..position + 8).ok_or_else(truncated)?;
+        let crc = u32::from_le_bytes(trailer[..4].try_into()?);
+        let size = u32::from_le_bytes(trailer[4..].try_into()?);
+        if crc != crc32(&output[start..]) || size != (output.len() - start) as u32 {
+            bail!("gzip file is corrupt");
+        }
+        data = &data[position + 8..];
+    }
+    Ok(output)
+}
+
+fn truncated() -> anyhow::Error {
+    anyhow!("gzip file is truncated")
+}
+
+/// Decompresses raw deflate data, without the gzip header and trailer, the
+/// way that zip archives store it.
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    inflate_blocks(&mut BitReader { data, position: 0 }, &mut output)?;
+    Ok(output)
+}
+
+fn inflate_blocks(bits: &mut BitReader, output: &mut Vec<u8>) -> Result<()> {
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => {
+                // stored without compression, starting at the next byte
+                bits.position = bits.position.div_ceil(8) * 8;
+                let length = bits.read(16)? as usize;
+                bits.read(16)?;
+                let start = bits.position / 8;
+                let stored = bits.data.get(start..start + length).ok_or_else(truncated)?;
+                output.extend(stored);
+                bits.position += length * 8;
+            }
+            1 => {
+                let mut lengths = [0; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths);
+                let distances = Huffman::new(&[5; 30]);
+                inflate_block(bits, output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(bits)?;
+                inflate_block(bits, output, &literals, &distances)?;
+            }
+            _ => bail!("invalid deflate block type"),
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads the Huffman codes at the start of a block with dynamic codes.
+fn dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman)> {
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+
+    let mut code_lengths = [0; 19];
+    for index in ORDER.iter().take(code_length_count) {
+        code_lengths[*index] = bits.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    // the literal and distance code lengths are compressed together
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_lengths.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths
+                    .last()
+                    .ok_or_else(|| anyhow!("invalid deflate code lengths"))?,
+                3 + bits.read(2)?,
+            ),
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        bail!("invalid deflate code lengths");
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    bits: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<()> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let code = symbol - 257;
+                let length =
+                    LENGTH_BASES[code] as usize + bits.read(LENGTH_EXTRA_BITS[code])? as usize;
+                let code = distances.decode(bits)? as usize;
+                if code >= DISTANCE_BASES.len() {
+                    bail!("invalid deflate distance");
+                }
+                let distance =
+                    DISTANCE_BASES[code] as usize + bits.read(DISTANCE_EXTRA_BITS[code])? as usize;
+                if distance > output.len() {
+                    bail!("invalid deflate distance");
+                }
+
+                // the match can overlap the bytes that it's copying
+                let start = output.len() - distance;
+                for index in start..start + length {
+                    output.push(output[index]);
+                }
+            }
+            _ => bail!("invalid deflate symbol"),
+        }
+    }
+}
+
+/// Reads bits starting from the least significant bit of each byte.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, count: u8) -> Result<u32> {
+        let mut value = 0;
+        for bit in 0..count {
+            let byte = self.data.get(self.position / 8).ok_or_else(truncated)?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// A canonical Huffman code, stored as the number of codes of each length
+/// and the symbols in the order of their codes.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16)
+            .filter(|symbol| lengths[*symbol as usize] != 0)
+            .collect();
+        symbols.sort_by_key(|symbol| lengths[*symbol as usize]);
+        Huffman { counts, symbols }
+    }
+
+    /// Reads a code one bit at a time, since codes are written starting from
+    /// their most significant bit.
+    fn decode(&self, bits: &mut BitReader) -> Result<u16> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+        for count in &self.counts[1..] {
+            code |= bits.read(1)? as i32;
+            let count = *count as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        bail!("invalid deflate code")
+    }
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter::default();
+    bits.write(1, 1); // final block
+    bits.write(1, 2); // fixed Huffman codes
+
+    // the most recent position of each three byte sequence, and for each
+    // position, the previous position with the same sequence. Only positions
+    // within the window can be matched, so `previous` wraps around instead of
+    // growing with the data.
+    let mut head = vec![usize::MAX; 1 << 15];
+    let mut previous = vec![usize::MAX; WINDOW];
+    let insert = |index: usize, head: &mut [usize], previous: &mut [usize]| {
+        if index + MIN_MATCH <= data.len() {
+            let hash = hash(data, index);
+            previous[index % WINDOW] = head[hash];
+            head[hash] = index;
+        }
+    };
+
+    let mut index = 0;
+    while index < data.len() {
+        let (length, distance) = longest_match(data, index, &head, &previous);
+        if length >= MIN_MATCH {
+            bits.write_length(length);
+            bits.write_distance(distance);
+            for position in index..index + length {
+                insert(position, &mut head, &mut previous);
+            }
+            index += length;
+        } else {
+            bits.write_literal(data[index] as u16);
+            insert(index, &mut head, &mut previous);
+            index += 1;
+        }
+    }
+
+    bits.write_literal(256); // end of block
+    bits.finish()
+}
+
+/// Length and distance of the longest earlier match for the bytes at `index`.
+fn longest_match(data: &[u8], index: usize, head: &[usize], previous: &[usize]) -> (usize, usize) {
+    if index + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+
+    let max_length = MAX_MATCH.min(data.len() - index);
+    let mut best = (0, 0);
+    let mut candidate = head[hash(data, index)];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || index - candidate > WINDOW {
+            break;
+        }
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[index..index + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best.0 {
+            best = (length, index - candidate);
+            if length == max_length {
+                break;
+            }
+        }
+        candidate = previous[candidate % WINDOW];
+    }
+    best
+}
+
+/// Hash of the three bytes at `index`, to index `head` with.
+fn hash(data: &[u8], index: usize) -> usize {
+    let key =
+        (data[index] as usize) << 16 | (data[index + 1] as usize) << 8 | data[index + 2] as usize;
+    (key.wrapping_mul(2654435761) >> 7) & ((1 << 15) - 1)
+}
+
+/// Writes bits starting from the least significant bit of each byte, the way
+/// that deflate packs them.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, count: u8) {
+        self.buffer |= value << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Writes a Huffman code, which goes most significant bit first.
+    fn write_code(&mut self, code: u32, count: u8) {
+        self.write(code.reverse_bits() >> (32 - count), count);
+    }
+
+    /// Writes a literal byte, an end of block, or a length symbol, using the
+    /// fixed literal and length codes.
+    fn write_literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn write_length(&mut self, length: usize) {
+        let code = LENGTH_BASES
+            .iter()
+            .rposition(|base| *base as usize <= length)
+            .unwrap();
+        self.write_literal(257 + code as u16);
+        self.write(
+            (length - LENGTH_BASES[code] as usize) as u32,
+            LENGTH_EXTRA_BITS[code],
+        );
+    }
+
+    fn write_distance(&mut self, distance: usize) {
+        let code = DISTANCE_BASES
+            .iter()
+            .rposition(|base| *base as usize <= distance)
+            .unwrap();
+        self.write_code(code as u32, 5);
+        self.write(
+            (distance - DISTANCE_BASES[code] as usize) as u32,
+            DISTANCE_EXTRA_BITS[code],
+        );
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `hello, gpxsplit\n` compressed by `gzip -n`.
+    const HELLO: [u8; 36] = [
+        31, 139, 8, 0, 0, 0, 0, 0, 0, 3, 203, 72, 205, 201, 201, 215, 81, 72, 47, 168, 40, 46, 200,
+        201, 44, 225, 2, 0, 4, 163, 237, 222, 16, 0, 0, 0,
+    ];
+
+    /// `stored without compression\n` deflated in a block without compression.
+    const STORED: [u8; 32] = [
+        1, 27, 0, 228, 255, 115, 116, 111, 114, 101, 100, 32, 119, 105, 116, 104, 111, 117, 116,
+        32, 99, 111, 109, 112, 114, 101, 115, 115, 105, 111, 110, 10,
+    ];
+
+    /// `<ele>1000</ele><ele>1001</ele>\n` deflated with the fixed codes.
+    const FIXED: [u8; 21] = [
+        179, 73, 205, 73, 181, 51, 52, 48, 48, 176, 209, 7, 177, 108, 160, 92, 67, 8, 151, 11, 0,
+    ];
+
+    /// `dynamic_input()` deflated with dynamic codes by zlib.
+    const DYNAMIC: [u8; 87] = [
+        109, 202, 57, 10, 128, 48, 20, 5, 192, 222, 83, 132, 244, 46, 63, 123, 32, 241, 46, 214, 6,
+        21, 201, 253, 81, 187, 39, 188, 114, 96, 74, 191, 247, 171, 171, 182, 245, 170, 157, 159,
+        22, 173, 218, 121, 84, 61, 138, 44, 47, 230, 117, 40, 255, 32, 24, 34, 9, 6, 131, 35, 193,
+        98, 16, 18, 28, 134, 68, 130, 199, 224, 73, 8, 24, 12, 9, 17, 67, 38, 33, 97, 8, 36, 100,
+        12, 246, 11, 15,
+    ];
+
+    /// `second member\n` compressed by Python's `gzip`, with the file name
+    /// `route.gpx` in the header.
+    const NAMED: [u8; 44] = [
+        31, 139, 8, 8, 0, 0, 0, 0, 2, 255, 114, 111, 117, 116, 101, 46, 103, 112, 120, 0, 43, 78,
+        77, 206, 207, 75, 81, 200, 77, 205, 77, 74, 45, 226, 2, 0, 54, 24, 75, 14, 14, 0, 0, 0,
+    ];
+
+    fn dynamic_input() -> Vec<u8> {
+        (0..10)
+            .flat_map(|index| {
+                format!(
+                    "<trkpt lat=\"45.{index}\" lon=\"-110.{}\"/>\n",
+                    index * 7 % 10
+                )
+                .into_bytes()
+            })
+            .collect()
+    }
+
+    /// Track points like a long route has, which repeat enough to need more
+    /// than the whole window.
+    fn track_points(count: usize) -> Vec<u8> {
+        let mut points = String::new();
+        for index in 0..count {
+            points.push_str(&format!(
+                "      <trkpt lat=\"{:.6}\" lon=\"{:.6}\">\n        <ele>{}</ele>\n      </trkpt>\n",
+                45. + index as f64 * 0.0001,
+                -110. - index as f64 * 0.0003,
+                1000 + index % 700,
+            ));
+        }
+        points.into_bytes()
+    }
+
+    /// Bytes that don't repeat, so there's little to match.
+    fn noise(count: usize) -> Vec<u8> {
+        let mut state = 12345u32;
+        (0..count)
+            .map(|_| {
+                state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trips() {
+        let inputs = [
+            Vec::new(),
+            b"a".to_vec(),
+            b"abcabcabcabcabcabcabcabc".to_vec(),
+            vec![0; 100_000],
+            noise(70_000),
+            track_points(5000),
+        ];
+        for input in inputs {
+            let compressed = gzip(&input);
+            assert!(is_gzip(&compressed));
+            assert_eq!(gunzip(&compressed).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn compresses_track_points() {
+        let input = track_points(5000);
+        assert!(gzip(&input).len() < input.len() / 3);
+    }
+
+    #[test]
+    fn writes_header_and_trailer() {
+        let compressed = gzip(b"hello");
+        assert_eq!(compressed[..4], [0x1f, 0x8b, 8, 0]);
+        let trailer = &compressed[compressed.len() - 8..];
+        assert_eq!(trailer[..4], crc32(b"hello").to_le_bytes());
+        assert_eq!(trailer[4..], 5u32.to_le_bytes());
+    }
+
+    #[test]
+    fn decompresses_gzip_output() {
+        assert_eq!(gunzip(&HELLO).unwrap(), b"hello, gpxsplit\n");
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        let mut corrupt = HELLO;
+        corrupt[30] ^= 1;
+        assert!(gunzip(&corrupt).is_err());
+        assert!(gunzip(&HELLO[..HELLO.len() - 4]).is_err());
+        assert!(gunzip(b"<gpx>").is_err());
+    }
+
+    #[test]
+    fn inflates_stored_blocks() {
+        assert_eq!(inflate(&STORED).unwrap(), b"stored without compression\n");
+    }
+
+    #[test]
+    fn inflates_fixed_codes() {
+        assert_eq!(
+            inflate(&FIXED).unwrap(),
+            b"<ele>1000</ele><ele>1001</ele>\n"
+        );
+    }
+
+    #[test]
+    fn inflates_dynamic_codes() {
+        assert_eq!(inflate(&DYNAMIC).unwrap(), dynamic_input());
+    }
+
+    #[test]
+    fn skips_file_names() {
+        assert_eq!(gunzip(&NAMED).unwrap(), b"second member\n");
+    }
+
+    #[test]
+    fn joins_members() {
+        let joined = [&HELLO[..], &NAMED, &gzip(b"third\n")].concat();
+        assert_eq!(
+            gunzip(&joined).unwrap(),
+            b"hello, gpxsplit\nsecond member\nthird\n"
+        );
+        // a member that's cut off is still an error, even after good ones
+        assert!(gunzip(&joined[..joined.len() - 1]).is_err());
+    }
+}
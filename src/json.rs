@@ -0,0 +1,360 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Result;
+
+/// Most arrays and objects that can be nested inside each other. The parser
+/// recurses for each one, so this keeps malformed or malicious files from
+/// overflowing the stack. Nothing gpxsplit reads comes close.
+const MAX_DEPTH: usize = 128;
+
+/// A parsed JSON value. Objects keep their keys in the order they were
+/// written.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(text: &str) -> Result<Json> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.chars.next() {
+            bail!("unexpected {c:?} after json value");
+        }
+        Ok(value)
+    }
+
+    /// Builds an object from `entries`, keeping them in order.
+    pub fn object<'a>(entries: impl IntoIterator<Item = (&'a str, Json)>) -> Json {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_owned(), value))
+                .collect(),
+        )
+    }
+
+    /// Looks up `key` if this is an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Writes the value with each entry of its arrays and objects on its own
+    /// indented line, for files that are meant to be read and edited.
+    pub fn to_pretty_string(&self) -> String {
+        let mut pretty = String::new();
+        self.write_pretty(&mut pretty, 0);
+        pretty.push('\n');
+        pretty
+    }
+
+    fn write_pretty(&self, pretty: &mut String, depth: usize) {
+        let indent = |pretty: &mut String, depth: usize| {
+            pretty.push('\n');
+            pretty.extend((0..depth).map(|_| "  "));
+        };
+        match self {
+            Json::Array(values) if !values.is_empty() => {
+                pretty.push('[');
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        pretty.push(',');
+                    }
+                    indent(pretty, depth + 1);
+                    value.write_pretty(pretty, depth + 1);
+                }
+                indent(pretty, depth);
+                pretty.push(']');
+            }
+            Json::Object(entries) if !entries.is_empty() => {
+                pretty.push('{');
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        pretty.push(',');
+                    }
+                    indent(pretty, depth + 1);
+                    pretty.push_str(&format!("{}: ", Json::String(key.clone())));
+                    value.write_pretty(pretty, depth + 1);
+                }
+                indent(pretty, depth);
+                pretty.push('}');
+            }
+            value => pretty.push_str(&value.to_string()),
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            // json has no infinity or nan, so those are written as null
+            Json::Number(number) if !number.is_finite() => write!(f, "null"),
+            Json::Number(number) => write!(f, "{number}"),
+            Json::String(string) => write_string(f, string),
+            Json::Array(values) => {
+                write!(f, "[")?;
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(entries) => {
+                write!(f, "{{")?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut Formatter, string: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in string.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// Arrays and objects that the parser is inside of.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn value(&mut self) -> Result<Json> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('{') => self.nested(Parser::object),
+            Some('[') => self.nested(Parser::array),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some(_) => self.number(),
+            None => bail!("unexpected end of json"),
+        }
+    }
+
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Json>) -> Result<Json> {
+        if self.depth == MAX_DEPTH {
+            bail!("json is nested more than {MAX_DEPTH} deep");
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Json> {
+        self.expect('{')?;
+        let mut entries = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(Json::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            entries.push((key, self.value()?));
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(entries)),
+                other => bail!("expected , or }} in json object but found {other:?}"),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                other => bail!("expected , or ] in json array but found {other:?}"),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        self.expect('"')?;
+        let mut string = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => string.push('"'),
+                    Some('\\') => string.push('\\'),
+                    Some('/') => string.push('/'),
+                    Some('b') => string.push('\u{8}'),
+                    Some('f') => string.push('\u{c}'),
+                    Some('n') => string.push('\n'),
+                    Some('r') => string.push('\r'),
+                    Some('t') => string.push('\t'),
+                    Some('u') => string.push(self.unicode_escape()?),
+                    other => bail!("invalid escape {other:?} in json string"),
+                },
+                Some(c) => string.push(c),
+                None => bail!("unterminated json string"),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| anyhow!("invalid unicode escape"));
+        }
+
+        // characters outside the basic multilingual plane are escaped as a pair
+        // of surrogates
+        self.expect('\\')?;
+        self.expect('u')?;
+        let low = self.hex4()?;
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff))
+            .ok_or_else(|| anyhow!("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32> {
+        let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+        u32::from_str_radix(&hex, 16).map_err(|_| anyhow!("invalid unicode escape {hex}"))
+    }
+
+    fn number(&mut self) -> Result<Json> {
+        let mut number = String::new();
+        while let Some(c) = self
+            .chars
+            .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            number.push(c);
+        }
+        number
+            .parse()
+            .map(Json::Number)
+            .map_err(|_| anyhow!("invalid json number {number:?}"))
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json> {
+        for expected in keyword.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            other => bail!("expected {expected:?} in json but found {other:?}"),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_nesting() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(Json::parse(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(Json::parse(&"{\"a\":".repeat(1_000_000)).is_err());
+        assert!(Json::parse(&"[".repeat(1_000_000)).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io::Read;
+use std::io::Write;
+
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use geo_types::Point;
+use gpx::Gpx;
+use gpx::GpxVersion;
+use gpx::Track;
+use gpx::TrackSegment;
+use gpx::Waypoint;
+use xml::reader::XmlEvent;
+use xml::EventReader;
+
+use crate::markup::XmlWriter;
+
+const NAMESPACE: &str = "http://www.opengis.net/kml/2.2";
+
+/// Line colors for each part, cycled through so that neighbouring parts look
+/// different. KML orders the channels alpha, blue, green, red.
+const COLORS: [&str; 6] = [
+    "ff0000ff", "ffff0000", "ff00aa00", "ffff00ff", "ff00aaff", "ffffaa00",
+];
+
+/// A part of the route, drawn as a single line.
+pub struct Line<'a> {
+    pub name: &'a str,
+    /// Position of the part in the whole route, which picks its color.
+    pub index: usize,
+    pub waypoints: &'a [Waypoint],
+}
+
+/// Writes a KML document named `name`, with a placemark for each of `lines`.
+pub fn write_kml<W: Write>(writer: W, name: &str, lines: &[Line]) -> Result<()> {
+    let mut writer = XmlWriter::new(writer, "kml", NAMESPACE)?;
+    writer.start("Document")?;
+    writer.text("name", name)?;
+
+    for line in lines {
+        writer.start("Placemark")?;
+        writer.text("name", line.name)?;
+        writer.start("Style")?;
+        writer.start("LineStyle")?;
+        writer.text("color", COLORS[line.index % COLORS.len()])?;
+        writer.text("width", "4")?;
+        writer.end()?;
+        writer.end()?;
+        writer.start("LineString")?;
+        writer.text("tessellate", "1")?;
+        let coordinates: Vec<String> = line
+            .waypoints
+            .iter()
+            .map(|waypoint| {
+                let point = waypoint.point();
+                match waypoint.elevation {
+                    Some(elevation) => format!("{},{},{elevation}", point.x(), point.y()),
+                    None => format!("{},{}", point.x(), point.y()),
+                }
+            })
+            .collect();
+        writer.text("coordinates", &coordinates.join(" "))?;
+        writer.end()?;
+        writer.end()?;
+    }
+
+    writer.end()?;
+    writer.finish()
+}
+
+/// Whether `data` looks like a KML file, from the name of its root element
+/// near the start.
+pub fn is_kml(data: &[u8]) -> bool {
+    let start = &data[..data.len().min(1024)];
+    String::from_utf8_lossy(start).contains("<kml")
+}
+
+/// Reads a KML file into a GPX file with a track for each placemark that has
+/// lines, and a waypoint for each placemark that's a single point.
+pub fn read_kml<R: Read>(reader: R) -> Result<Gpx> {
+    let mut gpx = Gpx {
+        version: GpxVersion::Gpx11,
+        ..Gpx::default()
+    };
+
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut name = None;
+    let mut segments = Vec::new();
+    let mut point = None;
+
+    for event in EventReader::new(reader) {
+        match event? {
+            XmlEvent::StartElement { name, .. } => {
+                path.push(name.local_name);
+                text.clear();
+            }
+            XmlEvent::Characters(characters) | XmlEvent::CData(characters) => {
+                text.push_str(&characters);
+            }
+            XmlEvent::EndElement { .. } => {
+                let Some(element) = path.pop() else {
+                    continue;
+                };
+                match (element.as_str(), path.last().map(String::as_str)) {
+                    ("name", Some("Placemark")) => name = Some(text.trim().to_owned()),
+                    ("coordinates", Some("LineString")) => {
+                        let mut segment = TrackSegment::new();
+                        segment.points = coordinates(&text)?;
+                        segments.push(segment);
+                    }
+                    ("coordinates", Some("Point")) => point = coordinates(&text)?.pop(),
+                    ("Placemark", _) => {
+                        if !segments.is_empty() {
+                            let mut track = Track::new();
+                            track.name = name.clone();
+                            track.segments = segments.split_off(0);
+                            gpx.tracks.push(track);
+                        }
+                        if let Some(mut point) = point.take() {
+                            point.name = name.clone();
+                            gpx.waypoints.push(point);
+                        }
+                        name = None;
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(gpx)
+}
+
+/// Parses the contents of a `coordinates` element, which is a list of
+/// longitude, latitude, and optional altitude tuples separated by spaces.
+fn coordinates(text: &str) -> Result<Vec<Waypoint>> {
+    text.split_whitespace()
+        .map(|tuple| {
+            let invalid = || format!("invalid coordinates {tuple} in kml file");
+            let mut values = tuple.split(',').map(str::parse::<f64>);
+            let (Some(longitude), Some(latitude)) = (values.next(), values.next()) else {
+                bail!(invalid());
+            };
+            let mut waypoint = Waypoint::new(Point::new(
+                longitude.with_context(invalid)?,
+                latitude.with_context(invalid)?,
+            ));
+            waypoint.elevation = values.next().transpose().with_context(invalid)?;
+            Ok(waypoint)
+        })
+        .collect()
+}
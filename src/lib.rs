@@ -0,0 +1,259 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Splits long GPX routes into shorter ones that navigation devices can
+//! handle, the same way as the `gpxsplit` command.
+
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod limit;
+mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+mod splitter;
+mod strategy;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use std::io::Read;
+use std::io::Write;
+use std::mem::take;
+
+use geo_types::coord;
+use geo_types::Rect;
+use gpx::Gpx;
+
+pub use error::Result;
+pub use error::SplitError;
+pub use limit::time;
+pub use limit::Backtrack;
+pub use limit::Limit;
+pub use limit::Measure;
+pub use limit::Overlap;
+pub use limit::Snap;
+pub use limit::SpeedModel;
+pub use limit::SplitOptions;
+pub use limit::SplitWaypoints;
+pub use limit::Subsequences;
+pub use progress::Progress;
+pub use progress::ProgressCallback;
+pub use progress::ProgressWriter;
+pub use progress::PROGRESS_POINTS;
+pub use splitter::is_positive;
+pub use splitter::Splitter;
+pub use splitter::SplitterBuilder;
+pub use strategy::CloneStrategy;
+pub use strategy::MaxDistance;
+pub use strategy::MaxDuration;
+pub use strategy::MaxPoints;
+pub use strategy::SplitStrategy;
+
+/// Splits the first segment of the first track of `gpx` with `options`,
+/// yielding a GPX file for each part of the route. Everything else in each
+/// file, like the waypoints and the name of the track, is the same as in
+/// `gpx`.
+pub fn split(mut gpx: Gpx, options: SplitOptions) -> impl Iterator<Item = Result<Gpx>> {
+    let (waypoints, missing) = match gpx.tracks.first_mut() {
+        None => (Vec::new(), Some(SplitError::MissingTrack)),
+        Some(track) => match track.segments.first_mut() {
+            None => (Vec::new(), Some(SplitError::MissingSegment)),
+            Some(segment) => (take(&mut segment.points), None),
+        },
+    };
+
+    missing
+        .map(Err)
+        .into_iter()
+        .chain(
+            Subsequences::new(waypoints, options).map(move |subsequence| {
+                let mut part = gpx.clone();
+                part.tracks[0].segments[0].points = subsequence?;
+                Ok(part)
+            }),
+        )
+}
+
+/// Splits `gpx` the same as `split`, returning all of the parts at once. Each
+/// part has its own copy of the metadata, with the bounds, if there are any,
+/// narrowed to the part's waypoints and route.
+pub fn split_all(gpx: Gpx, options: SplitOptions) -> Result<Vec<Gpx>> {
+    let mut parts = split(gpx, options).collect::<Result<Vec<Gpx>>>()?;
+    for part in &mut parts {
+        let bounds = bounds(part);
+        if let Some(metadata) = &mut part.metadata {
+            if metadata.bounds.is_some() {
+                metadata.bounds = bounds;
+            }
+        }
+    }
+    Ok(parts)
+}
+
+/// Reads a GPX file from `reader`, like an uploaded file's bytes, and splits
+/// it the same as `split_all`. Nothing is written to disk. This blocks while
+/// reading, so async code should read the whole upload first and pass a byte
+/// slice, or run this on a thread that's allowed to block.
+pub fn split_from_reader<R: Read>(reader: R, options: SplitOptions) -> Result<Vec<Gpx>> {
+    split_all(gpx::read(reader)?, options)
+}
+
+/// Writes a part from `split` or `split_all` to `writer` as a GPX file.
+pub fn write_part_to_writer<W: Write>(part: &Gpx, writer: W) -> Result<()> {
+    gpx::write(part, writer)?;
+    Ok(())
+}
+
+/// Splits the GPX file in `input`, returning the contents of a GPX file for
+/// each part.
+pub fn split_gpx_bytes(input: &[u8], options: SplitOptions) -> Result<Vec<Vec<u8>>> {
+    let parts = split_all(gpx::read(input)?, options)?;
+    parts
+        .iter()
+        .map(|part| {
+            let mut bytes = Vec::new();
+            write_part_to_writer(part, &mut bytes)?;
+            Ok(bytes)
+        })
+        .collect()
+}
+
+/// Smallest rectangle around all of the waypoints and track points in `gpx`.
+fn bounds(gpx: &Gpx) -> Option<Rect<f64>> {
+    let points = gpx
+        .tracks
+        .iter()
+        .flat_map(|track| &track.segments)
+        .flat_map(|segment| &segment.points)
+        .chain(&gpx.waypoints)
+        .map(|waypoint| waypoint.point());
+    points.fold(None, |bounds, point| {
+        let point = coord! { x: point.x(), y: point.y() };
+        Some(match bounds {
+            Some(bounds) => Rect::new(
+                coord! { x: bounds.min().x.min(point.x), y: bounds.min().y.min(point.y) },
+                coord! { x: bounds.max().x.max(point.x), y: bounds.max().y.max(point.y) },
+            ),
+            None => Rect::new(point, point),
+        })
+    })
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use geo_types::Point;
+    use gpx::GpxVersion;
+    use gpx::Metadata;
+    use gpx::Track;
+    use gpx::TrackSegment;
+    use gpx::Waypoint;
+    use time::Duration;
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    /// Waypoints heading north, about 1.1 km and a minute apart.
+    pub(crate) fn waypoints(count: usize) -> Vec<Waypoint> {
+        (0..count)
+            .map(|index| {
+                let mut waypoint = Waypoint::new(Point::new(-110., 45. + index as f64 * 0.01));
+                let time = OffsetDateTime::UNIX_EPOCH + Duration::minutes(index as i64);
+                waypoint.time = Some(time.into());
+                waypoint
+            })
+            .collect()
+    }
+
+    /// A GPX file with a single segment of `waypoints`, with bounds in its
+    /// metadata.
+    pub(crate) fn route(waypoints: Vec<Waypoint>) -> Gpx {
+        let mut segment = TrackSegment::new();
+        segment.points = waypoints;
+        let mut track = Track::new();
+        track.name = Some("Route".to_owned());
+        track.segments.push(segment);
+        let mut gpx = Gpx {
+            version: GpxVersion::Gpx11,
+            tracks: vec![track],
+            ..Gpx::default()
+        };
+        gpx.metadata = Some(Metadata {
+            bounds: bounds(&gpx),
+            ..Metadata::default()
+        });
+        gpx
+    }
+
+    fn max_points(points: usize) -> SplitOptions {
+        Splitter::builder()
+            .max_points(points)
+            .build()
+            .unwrap()
+            .options()
+            .clone()
+    }
+
+    #[test]
+    fn splits_a_single_segment() {
+        let parts = split_all(route(waypoints(5)), max_points(3)).unwrap();
+        assert_eq!(parts.len(), 2);
+        for (part, expected) in parts.iter().zip([0..3, 2..5]) {
+            assert_eq!(part.tracks.len(), 1);
+            assert_eq!(part.tracks[0].name.as_deref(), Some("Route"));
+            assert_eq!(part.tracks[0].segments[0].points, waypoints(5)[expected]);
+        }
+
+        // each part's bounds only cover its own points
+        let bounds = parts[1].metadata.as_ref().unwrap().bounds.unwrap();
+        assert!((bounds.min().y - 45.02).abs() < 1e-9);
+        assert!((bounds.max().y - 45.04).abs() < 1e-9);
+    }
+
+    #[test]
+    fn splits_a_route_within_the_limits_into_one_part() {
+        let parts = split_all(route(waypoints(3)), max_points(3)).unwrap();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].tracks[0].segments[0].points, waypoints(3));
+    }
+
+    #[test]
+    fn splits_an_empty_segment_into_nothing() {
+        assert!(split_all(route(Vec::new()), max_points(3))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn needs_a_track_and_a_segment() {
+        assert!(matches!(
+            split_all(Gpx::default(), max_points(3)),
+            Err(SplitError::MissingTrack)
+        ));
+        let mut gpx = route(Vec::new());
+        gpx.tracks[0].segments.clear();
+        assert!(matches!(
+            split_all(gpx, max_points(3)),
+            Err(SplitError::MissingSegment)
+        ));
+    }
+
+    #[test]
+    fn splits_bytes() {
+        let mut input = Vec::new();
+        write_part_to_writer(&route(waypoints(5)), &mut input).unwrap();
+        let parts = split_gpx_bytes(&input, max_points(3)).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert!(split_gpx_bytes(b"<gpx", max_points(3)).is_err());
+    }
+}
@@ -0,0 +1,781 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::collections::VecDeque;
+use std::mem::take;
+use std::ops::AddAssign;
+
+use geoutils::Location;
+use gpx::Waypoint;
+use time::OffsetDateTime;
+
+use crate::error::Result;
+use crate::error::SplitError;
+use crate::progress::Progress;
+use crate::progress::ProgressCallback;
+use crate::progress::PROGRESS_POINTS;
+use crate::strategy::SplitStrategy;
+
+macro_rules! ok_or_bail {
+    ($expr:expr) => {
+        match $expr {
+            Ok(val) => val,
+            Err(err) => return Some(Err(err)),
+        }
+    };
+}
+
+/// How to split a route into subsequences, which `Subsequences` describes in
+/// more detail. Options that don't make sense, like a `max_points` of zero,
+/// make `Subsequences` fail with `SplitError::InvalidOption`.
+#[derive(Clone)]
+pub struct SplitOptions {
+    /// Each subsequence is cut off once any of these is reached.
+    pub limits: Vec<Limit>,
+    /// Most waypoints in a subsequence, including those carried over.
+    pub max_points: Option<usize>,
+    pub measure: Measure,
+    /// Needed for `Limit::EstimatedSeconds`.
+    pub speed_model: Option<SpeedModel>,
+    /// Distances from the start of the route to cut after, in order.
+    pub cut_meters: Vec<f64>,
+    /// Indices of waypoints to cut after, in order.
+    pub cut_indices: Vec<usize>,
+    /// Indices of waypoints after which nothing is carried over to the next
+    /// subsequence, in order.
+    pub detached_indices: Vec<usize>,
+    /// Whether each waypoint is partway up a climb, where subsequences aren't
+    /// cut.
+    pub climbing: Vec<bool>,
+    pub snap: Option<Snap>,
+    pub backtrack: Option<Backtrack>,
+    pub overlap: Overlap,
+    /// Whether legs between the waypoints carried over from the previous
+    /// subsequence don't count towards the limits.
+    pub exclude_overlap: bool,
+    /// Other ways of deciding where to cut, which are treated like `limits`.
+    pub strategies: Vec<Box<dyn SplitStrategy>>,
+    /// Called as waypoints are read and each subsequence is finished.
+    pub progress: Option<ProgressCallback>,
+}
+
+impl SplitOptions {
+    /// Checks that the options can be split with, with an error for the first
+    /// one that doesn't make sense.
+    pub fn validate(&self) -> Result<()> {
+        let min_points = self.overlap.min_points();
+        if self
+            .max_points
+            .is_some_and(|max_points| max_points < min_points)
+        {
+            return Err(SplitError::InvalidOption(format!(
+                "max_points must be at least {min_points} to fit the overlap"
+            )));
+        }
+        if let Some(snap) = &self.snap {
+            if !(snap.meters.is_finite() && snap.meters >= 0.) {
+                return Err(SplitError::InvalidOption(
+                    "snap meters must not be negative".to_owned(),
+                ));
+            }
+        }
+        if let Some(backtrack) = &self.backtrack {
+            if !(backtrack.meters.is_finite() && backtrack.meters >= 0.) {
+                return Err(SplitError::InvalidOption(
+                    "backtrack meters must not be negative".to_owned(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for SplitOptions {
+    /// Options that don't split the route at all, and carry over a single
+    /// waypoint once something is added that does.
+    fn default() -> SplitOptions {
+        SplitOptions {
+            limits: Vec::new(),
+            max_points: None,
+            measure: Measure::Horizontal,
+            speed_model: None,
+            cut_meters: Vec::new(),
+            cut_indices: Vec::new(),
+            detached_indices: Vec::new(),
+            climbing: Vec::new(),
+            snap: None,
+            backtrack: None,
+            overlap: Overlap::Points(1),
+            exclude_overlap: false,
+            strategies: Vec::new(),
+            progress: None,
+        }
+    }
+}
+
+/// Iterator of waypoints that reads from an underlying iterator and yields
+/// subsequences of waypoints, each one running until any of the `limits` has
+/// been reached or the `max_points` count has been filled. Waypoints are read
+/// as they're needed, so they can come from anywhere, and each subsequence can
+/// be written out before the next one is read. Make one with
+/// `Subsequences::new` or `SplitWaypoints::subsequences`, with the options in
+/// a `SplitOptions`.
+///
+/// Subsequences are also cut off after the first waypoint past each distance
+/// in `cut_meters`, which are measured from the start of the route rather than
+/// the start of the subsequence, and after each index in `cut_indices`. The
+/// subsequence after an index in `detached_indices` doesn't include the last
+/// waypoints from the previous subsequence, otherwise the last waypoints are
+/// carried over to the start of the next one according to `overlap`. When
+/// nothing is carried over, the last waypoint is kept in `dropped` so that the
+/// leg from it is still measured along the route. Legs between the carried
+/// waypoints count towards the limits unless `exclude_overlap` is set.
+///
+/// If a subsequence would be cut off at a waypoint marked in `climbing`, it
+/// continues until the next waypoint that isn't. The `max_points` count is a
+/// hard limit that isn't deferred like this, since devices can't handle more.
+/// Cuts from `limits` can also be moved to a nearby landmark by `snap`, which
+/// may rewind some waypoints to be read again for the next subsequence, or
+/// moved back to a junction by `backtrack`.
+#[derive(Clone)]
+pub struct Subsequences<Waypoints> {
+    waypoints: Waypoints,
+    measure: Measure,
+    limits: Vec<Limit>,
+    max_points: Option<usize>,
+    last_elevation: Option<f64>,
+    speed_model: Option<SpeedModel>,
+    cut_meters: VecDeque<f64>,
+    route_meters: f64,
+    cut_indices: VecDeque<usize>,
+    detached_indices: VecDeque<usize>,
+    climbing: Vec<bool>,
+    snap: Option<Snap>,
+    backtrack: Option<Backtrack>,
+    rewound: Vec<Waypoint>,
+    next_index: usize,
+    overlap: Overlap,
+    exclude_overlap: bool,
+    strategies: Vec<Box<dyn SplitStrategy>>,
+    progress: Option<ProgressCallback>,
+    parts: usize,
+    carried: Vec<Waypoint>,
+    dropped: Option<Waypoint>,
+    /// Set if the options don't make sense, to fail with the message once and
+    /// then end.
+    invalid: Option<Option<String>>,
+}
+
+/// Splits any iterator of waypoints into subsequences.
+pub trait SplitWaypoints: Iterator<Item = Waypoint> + Sized {
+    fn subsequences(self, options: SplitOptions) -> Subsequences<Self> {
+        Subsequences::new(self, options)
+    }
+}
+
+impl<Waypoints: Iterator<Item = Waypoint>> SplitWaypoints for Waypoints {}
+
+/// Moves cuts caused by `limits` to nearby landmarks. Once a limit is reached,
+/// the subsequence is cut off at the waypoint within `meters` along the route
+/// that's closest to a landmark, according to `landmark_meters` for each
+/// waypoint in the route. Waypoints past the end of `landmark_meters` are
+/// treated as being far from any landmark.
+#[derive(Clone)]
+pub struct Snap {
+    pub meters: f64,
+    pub landmark_meters: Vec<f64>,
+}
+
+/// How much of the end of each subsequence is repeated at the start of the
+/// next one.
+#[derive(Clone, Copy)]
+pub enum Overlap {
+    None,
+    Points(usize),
+    /// As many of the last waypoints as it takes to cover this distance.
+    Meters(f64),
+}
+
+impl Overlap {
+    /// Fewest waypoints a subsequence can have while still reaching a new
+    /// waypoint after the overlap.
+    pub fn min_points(self) -> usize {
+        match self {
+            Overlap::None => 1,
+            Overlap::Points(points) => points + 1,
+            Overlap::Meters(_) => 2,
+        }
+    }
+}
+
+/// Moves cuts caused by `limits` or `max_points` back to the most recent
+/// waypoint marked in `junctions`, as long as it's within `meters` along the
+/// route. Waypoints past the end of `junctions` aren't junctions.
+#[derive(Clone)]
+pub struct Backtrack {
+    pub meters: f64,
+    pub junctions: Vec<bool>,
+}
+
+/// A limit on the size of each subsequence, which is reached once the total
+/// for the subsequence goes over it.
+#[derive(Clone, Copy)]
+pub enum Limit {
+    Meters(f64),
+    /// Time between the timestamps of the waypoints, which they all need.
+    Seconds(f64),
+    /// Meters climbed.
+    Gain(f64),
+    /// Time to ride the subsequence according to `SplitOptions::speed_model`.
+    EstimatedSeconds(f64),
+}
+
+impl Limit {
+    fn reached(self, totals: &Totals) -> bool {
+        match self {
+            Limit::Meters(meters) => totals.meters > meters,
+            Limit::Seconds(seconds) => totals.seconds > seconds,
+            Limit::Gain(gain) => totals.gain > gain,
+            Limit::EstimatedSeconds(seconds) => totals.estimated_seconds > seconds,
+        }
+    }
+}
+
+/// Measurements of a single leg between two waypoints, or the sum of all of
+/// the legs in a subsequence.
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    meters: f64,
+    seconds: f64,
+    gain: f64,
+    estimated_seconds: f64,
+}
+
+impl AddAssign for Totals {
+    fn add_assign(&mut self, leg: Totals) {
+        self.meters += leg.meters;
+        self.seconds += leg.seconds;
+        self.gain += leg.gain;
+        self.estimated_seconds += leg.estimated_seconds;
+    }
+}
+
+impl<Waypoints: Iterator<Item = Waypoint>> Iterator for Subsequences<Waypoints> {
+    type Item = Result<Vec<Waypoint>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(invalid) = &mut self.invalid {
+            return invalid
+                .take()
+                .map(|message| Err(SplitError::InvalidOption(message)));
+        }
+        let first = self.next_waypoint()?;
+
+        let start_elevation = self.last_elevation;
+        let mut totals = Totals::default();
+        let mut strategy_cut = false;
+        for strategy in &mut self.strategies {
+            strategy.start();
+        }
+
+        // include the last waypoints from the previous segment so that we don't lose
+        // navigation between those points. Carried waypoints are never cut at, so
+        // their distances along the route don't matter
+        let mut accumulated_waypoints = take(&mut self.carried);
+        let first_position = accumulated_waypoints.len();
+        let mut accumulated_route_meters = vec![self.route_meters; first_position];
+        if !self.exclude_overlap {
+            for pair in accumulated_waypoints.windows(2) {
+                totals += ok_or_bail!(self.carried_leg(&pair[0], &pair[1]));
+                strategy_cut |= ok_or_bail!(self.strategy_cut(&pair[0], &pair[1]));
+            }
+        }
+
+        match (accumulated_waypoints.last(), self.dropped.take()) {
+            (Some(prev_last), _) => {
+                totals += ok_or_bail!(self.leg(prev_last, &first));
+                strategy_cut |= ok_or_bail!(self.strategy_cut(prev_last, &first));
+            }
+            // the leg between disjoint subsequences is still part of the route, but
+            // isn't part of either subsequence
+            (None, Some(dropped)) => {
+                ok_or_bail!(self.leg(&dropped, &first));
+            }
+            // only applies to the first file, or after a detached index
+            (None, None) => {
+                self.climb(&first);
+            }
+        }
+        accumulated_waypoints.push(first);
+        accumulated_route_meters.push(self.route_meters);
+
+        let mut cut_pending = false;
+        let mut explicit_cut = false;
+        let mut exhausted = false;
+        let mut snap_from: Option<f64> = None;
+
+        while self
+            .max_points
+            .is_none_or(|max_points| accumulated_waypoints.len() < max_points)
+        {
+            let Some(waypoint) = self.next_waypoint() else {
+                exhausted = true;
+                break;
+            };
+            let prev = accumulated_waypoints.last().unwrap();
+
+            totals += ok_or_bail!(self.leg(prev, &waypoint));
+            strategy_cut |= ok_or_bail!(self.strategy_cut(prev, &waypoint));
+            accumulated_waypoints.push(waypoint);
+            accumulated_route_meters.push(self.route_meters);
+
+            let limit_reached =
+                strategy_cut || self.limits.iter().any(|limit| limit.reached(&totals));
+            if limit_reached && self.snap.is_some() {
+                snap_from.get_or_insert(self.route_meters);
+            }
+
+            explicit_cut |= self.passed_cut() || self.reached_cut_index();
+            cut_pending |= explicit_cut || (limit_reached && self.snap.is_none());
+
+            if cut_pending && !self.is_climbing() {
+                break;
+            }
+
+            // keep reading until the end of the window around the limit, so that the
+            // closest waypoint to a landmark can be picked out of all of it
+            if let (Some(snap), Some(snap_from)) = (&self.snap, snap_from) {
+                if self.route_meters > snap_from + snap.meters {
+                    break;
+                }
+            }
+        }
+
+        // cuts that aren't from the limits are in specific places, so they aren't
+        // moved to a landmark
+        if let (Some(snap_from), false) = (snap_from, cut_pending) {
+            self.snap_to_landmark(
+                &mut accumulated_waypoints,
+                &accumulated_route_meters,
+                first_position,
+                snap_from,
+                start_elevation,
+            );
+        } else if !explicit_cut && !exhausted {
+            self.backtrack_to_junction(
+                &mut accumulated_waypoints,
+                &accumulated_route_meters,
+                first_position,
+                start_elevation,
+            );
+        }
+
+        if self.reached_detached_index() {
+            self.carried = Vec::new();
+        } else if let Overlap::None = self.overlap {
+            self.dropped = accumulated_waypoints.last().cloned();
+        } else {
+            self.carried = ok_or_bail!(self.carry(&accumulated_waypoints));
+        }
+
+        self.report(Progress::PartFinished {
+            index: self.parts,
+            points: accumulated_waypoints.len(),
+        });
+        self.parts += 1;
+        Some(Ok(accumulated_waypoints))
+    }
+}
+
+impl<Waypoints: Iterator<Item = Waypoint>> Subsequences<Waypoints> {
+    /// Splits `waypoints`, which is a route in order, with `options`.
+    pub fn new(
+        waypoints: impl IntoIterator<IntoIter = Waypoints>,
+        options: SplitOptions,
+    ) -> Subsequences<Waypoints> {
+        let invalid = options
+            .validate()
+            .err()
+            .map(|error| Some(error.to_string()));
+        Subsequences {
+            waypoints: waypoints.into_iter(),
+            measure: options.measure,
+            limits: options.limits,
+            max_points: options.max_points,
+            last_elevation: None,
+            speed_model: options.speed_model,
+            cut_meters: options.cut_meters.into(),
+            route_meters: 0.,
+            cut_indices: options.cut_indices.into(),
+            detached_indices: options.detached_indices.into(),
+            climbing: options.climbing,
+            snap: options.snap,
+            backtrack: options.backtrack,
+            rewound: Vec::new(),
+            next_index: 0,
+            overlap: options.overlap,
+            exclude_overlap: options.exclude_overlap,
+            strategies: options.strategies,
+            progress: options.progress,
+            parts: 0,
+            carried: Vec::new(),
+            dropped: None,
+            invalid,
+        }
+    }
+
+    /// Index in the route of the waypoint after the last one read, so the
+    /// last subsequence starts at this minus its length.
+    pub fn next_index(&self) -> usize {
+        self.next_index
+    }
+
+    fn next_waypoint(&mut self) -> Option<Waypoint> {
+        let waypoint = self.rewound.pop().or_else(|| self.waypoints.next())?;
+        self.next_index += 1;
+        if self.next_index.is_multiple_of(PROGRESS_POINTS) {
+            self.report(Progress::PointsRead(self.next_index));
+        }
+        Some(waypoint)
+    }
+
+    fn report(&self, progress: Progress) {
+        if let Some(callback) = &self.progress {
+            callback(progress);
+        }
+    }
+
+    /// Cuts `accumulated_waypoints` off at the waypoint closest to a landmark
+    /// within the snap window around `snap_from`, rewinding the waypoints after
+    /// it so that they're read again for the next subsequence. Waypoints before
+    /// `first_position` were carried over from the previous subsequence.
+    fn snap_to_landmark(
+        &mut self,
+        accumulated_waypoints: &mut Vec<Waypoint>,
+        accumulated_route_meters: &[f64],
+        first_position: usize,
+        snap_from: f64,
+        start_elevation: Option<f64>,
+    ) {
+        let Some(snap) = &self.snap else {
+            return;
+        };
+
+        let first_index = self.next_index - accumulated_waypoints.len();
+        let best = (first_position.max(1)..accumulated_waypoints.len())
+            .filter(|position| {
+                (accumulated_route_meters[*position] - snap_from).abs() <= snap.meters
+            })
+            .min_by(|a, b| {
+                let landmark_meters = |position: usize| {
+                    snap.landmark_meters
+                        .get(first_index + position)
+                        .copied()
+                        .unwrap_or(f64::INFINITY)
+                };
+                landmark_meters(*a).total_cmp(&landmark_meters(*b))
+            });
+
+        if let Some(best) = best {
+            self.rewind_after(
+                accumulated_waypoints,
+                accumulated_route_meters,
+                best,
+                start_elevation,
+            );
+        }
+    }
+
+    /// Cuts `accumulated_waypoints` off at the most recent junction within the
+    /// backtrack window, rewinding the waypoints after it so that they're read
+    /// again for the next subsequence. Waypoints before `first_position` were
+    /// carried over from the previous subsequence.
+    fn backtrack_to_junction(
+        &mut self,
+        accumulated_waypoints: &mut Vec<Waypoint>,
+        accumulated_route_meters: &[f64],
+        first_position: usize,
+        start_elevation: Option<f64>,
+    ) {
+        let Some(backtrack) = &self.backtrack else {
+            return;
+        };
+
+        let first_index = self.next_index - accumulated_waypoints.len();
+        let cut_meters = self.route_meters;
+        let junction = (first_position.max(1)..accumulated_waypoints.len())
+            .rev()
+            .take_while(|position| {
+                cut_meters - accumulated_route_meters[*position] <= backtrack.meters
+            })
+            .find(|position| {
+                backtrack
+                    .junctions
+                    .get(first_index + position)
+                    .copied()
+                    .unwrap_or_default()
+                    && !self
+                        .climbing
+                        .get(first_index + position)
+                        .copied()
+                        .unwrap_or_default()
+            });
+
+        if let Some(junction) = junction {
+            self.rewind_after(
+                accumulated_waypoints,
+                accumulated_route_meters,
+                junction,
+                start_elevation,
+            );
+        }
+    }
+
+    /// Cuts `accumulated_waypoints` off after `position`, pushing the rest back
+    /// to be read again and restoring the distance and elevation to what they
+    /// were at that waypoint.
+    fn rewind_after(
+        &mut self,
+        accumulated_waypoints: &mut Vec<Waypoint>,
+        accumulated_route_meters: &[f64],
+        position: usize,
+        start_elevation: Option<f64>,
+    ) {
+        let rewound = accumulated_waypoints.split_off(position + 1);
+        self.next_index -= rewound.len();
+        self.route_meters = accumulated_route_meters[position];
+        self.last_elevation = accumulated_waypoints
+            .iter()
+            .rev()
+            .find_map(|waypoint| waypoint.elevation)
+            .or(start_elevation);
+        self.rewound.extend(rewound.into_iter().rev());
+    }
+
+    /// Whether the most recent waypoint is partway up a climb.
+    fn is_climbing(&self) -> bool {
+        self.climbing
+            .get(self.next_index - 1)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Measures the leg from `prev` to `next`, which is the next waypoint in the
+    /// route.
+    fn leg(&mut self, prev: &Waypoint, next: &Waypoint) -> Result<Totals> {
+        let meters = self.measure.distance(prev, next)?;
+        let gain = self.climb(next);
+        self.route_meters += meters;
+
+        Ok(Totals {
+            meters,
+            seconds: self.elapsed(prev, next)?,
+            gain,
+            estimated_seconds: self.estimate(meters, gain),
+        })
+    }
+
+    /// Whether any of the strategies says to cut after the leg from `prev` to
+    /// `next`. Every strategy is told about the leg, even once one of them
+    /// says to cut.
+    fn strategy_cut(&mut self, prev: &Waypoint, next: &Waypoint) -> Result<bool> {
+        let mut cut = false;
+        for strategy in &mut self.strategies {
+            cut |= strategy.should_cut(prev, next)?;
+        }
+        Ok(cut)
+    }
+
+    /// The last waypoints of `accumulated_waypoints` to carry over to the next
+    /// subsequence. This always leaves room under `max_points` for at least
+    /// one new waypoint.
+    fn carry(&self, accumulated_waypoints: &[Waypoint]) -> Result<Vec<Waypoint>> {
+        let len = accumulated_waypoints.len();
+        let mut count = match self.overlap {
+            Overlap::None => 0,
+            Overlap::Points(points) => points,
+            Overlap::Meters(meters) => {
+                let mut count = 1;
+                let mut total = 0.;
+                while count < len && total < meters {
+                    total += self.measure.distance(
+                        &accumulated_waypoints[len - count - 1],
+                        &accumulated_waypoints[len - count],
+                    )?;
+                    count += 1;
+                }
+                count
+            }
+        };
+        if let Some(max_points) = self.max_points {
+            count = count.min(max_points - 1);
+        }
+
+        Ok(accumulated_waypoints[len - count.min(len)..].to_vec())
+    }
+
+    /// Measures the leg between two waypoints carried over from the previous
+    /// subsequence, which have already been counted along the route.
+    fn carried_leg(&self, prev: &Waypoint, next: &Waypoint) -> Result<Totals> {
+        let meters = self.measure.distance(prev, next)?;
+        let gain = match (prev.elevation, next.elevation) {
+            (Some(prev), Some(next)) => (next - prev).max(0.),
+            _ => 0.,
+        };
+
+        Ok(Totals {
+            meters,
+            seconds: self.elapsed(prev, next)?,
+            gain,
+            estimated_seconds: self.estimate(meters, gain),
+        })
+    }
+
+    /// Estimated seconds to ride `meters` while climbing `climb` meters, or
+    /// zero if there's no speed model.
+    fn estimate(&self, meters: f64, climb: f64) -> f64 {
+        self.speed_model
+            .map_or(0., |speed_model| speed_model.seconds(meters, climb))
+    }
+
+    /// Meters climbed to reach `waypoint` from the last waypoint with an
+    /// elevation. Missing elevations are skipped over rather than treated as
+    /// zero, so a gap in the data doesn't add a huge climb afterwards.
+    fn climb(&mut self, waypoint: &Waypoint) -> f64 {
+        let Some(elevation) = waypoint.elevation else {
+            return 0.;
+        };
+
+        let climb = self
+            .last_elevation
+            .map_or(0., |last_elevation| (elevation - last_elevation).max(0.));
+        self.last_elevation = Some(elevation);
+        climb
+    }
+
+    /// Whether the most recent waypoint is at or past the next index in
+    /// `cut_indices`, removing all indices that have been reached.
+    fn reached_cut_index(&mut self) -> bool {
+        let mut reached = false;
+        while self
+            .cut_indices
+            .front()
+            .is_some_and(|cut_index| *cut_index < self.next_index)
+        {
+            self.cut_indices.pop_front();
+            reached = true;
+        }
+        reached
+    }
+
+    /// Whether the most recent waypoint is at the next index in
+    /// `detached_indices`, removing all indices that have been reached.
+    fn reached_detached_index(&mut self) -> bool {
+        let last_index = self.next_index - 1;
+        while self
+            .detached_indices
+            .front()
+            .is_some_and(|detached_index| *detached_index < last_index)
+        {
+            self.detached_indices.pop_front();
+        }
+        self.detached_indices.front() == Some(&last_index)
+    }
+
+    /// Whether the route has passed the next distance in `cut_meters`. All
+    /// distances that have been passed are removed, so that a single long leg
+    /// can't produce a run of single-point subsequences.
+    fn passed_cut(&mut self) -> bool {
+        let mut passed = false;
+        while self
+            .cut_meters
+            .front()
+            .is_some_and(|cut_meters| self.route_meters >= *cut_meters)
+        {
+            self.cut_meters.pop_front();
+            passed = true;
+        }
+        passed
+    }
+
+    /// Seconds between two waypoints, if splitting by time. Otherwise this is
+    /// always zero, so that files without timestamps can still be split by
+    /// distance.
+    fn elapsed(&self, a: &Waypoint, b: &Waypoint) -> Result<f64> {
+        if !self
+            .limits
+            .iter()
+            .any(|limit| matches!(limit, Limit::Seconds(_)))
+        {
+            return Ok(0.);
+        }
+
+        Ok((time(b)? - time(a)?).as_seconds_f64())
+    }
+}
+
+/// Estimates riding time from distance and climbing, with a flat speed and a
+/// penalty for each meter climbed.
+#[derive(Clone, Copy)]
+pub struct SpeedModel {
+    pub meters_per_second: f64,
+    pub seconds_per_meter_climbed: f64,
+}
+
+impl SpeedModel {
+    /// Estimated seconds to ride `meters` while climbing `climb` meters.
+    pub fn seconds(&self, meters: f64, climb: f64) -> f64 {
+        meters / self.meters_per_second + climb * self.seconds_per_meter_climbed
+    }
+}
+
+/// How the distance between two waypoints is measured when splitting.
+#[derive(Clone, Copy)]
+pub enum Measure {
+    Horizontal,
+    /// Includes the change in elevation, if both waypoints have one.
+    Slope,
+}
+
+impl Measure {
+    pub fn distance(self, a: &Waypoint, b: &Waypoint) -> Result<f64> {
+        let horizontal = distance(a, b)?;
+        match (self, a.elevation, b.elevation) {
+            (Measure::Slope, Some(a), Some(b)) => Ok(horizontal.hypot(b - a)),
+            _ => Ok(horizontal),
+        }
+    }
+}
+
+fn distance(a: &Waypoint, b: &Waypoint) -> Result<f64> {
+    let location = |waypoint: &Waypoint| {
+        let point = waypoint.point();
+        Location::new(point.y(), point.x())
+    };
+    location(a)
+        .distance_to(&location(b))
+        .map(|distance| distance.meters())
+        .map_err(|err| SplitError::DistanceComputation(err.to_string()))
+}
+
+/// Time of `waypoint`, which is an error if it doesn't have one.
+pub fn time(waypoint: &Waypoint) -> Result<OffsetDateTime> {
+    waypoint.time.map(OffsetDateTime::from).ok_or_else(|| {
+        let point = waypoint.point();
+        SplitError::MissingTime {
+            latitude: point.y(),
+            longitude: point.x(),
+        }
+    })
+}
@@ -0,0 +1,76 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Messages on stderr about what's being done, with as much detail as `-v`
+//! and `--quiet` ask for. Errors are always printed, so they don't go
+//! through here.
+
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+
+/// How much to print, from least to most.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Nothing, with `--quiet`.
+    Quiet,
+    /// Warnings and things that were done besides writing the files, like
+    /// removing stale ones.
+    Info,
+    /// What was read and written, and how each route was split, with `-v`.
+    Verbose,
+    /// Where each file starts and ends and why it was cut there, with `-vv`.
+    Debug,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Whether messages at `level` are printed.
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Prints a message unless `--quiet` is used.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::Level::Info) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Prints a message with `-v`.
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::Level::Verbose) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Prints a message with `-vv`.
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::logging::enabled($crate::logging::Level::Debug) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use debug;
+pub(crate) use info;
+pub(crate) use verbose;
@@ -12,168 +12,4142 @@
 // ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
 // IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
 
+mod borders;
+mod course;
+mod crossings;
+mod csv;
+mod elevation;
+mod extensions;
+mod fit;
+mod geocode;
+mod gzip;
+mod json;
+mod kml;
+mod logging;
+mod markup;
+mod metadata;
+mod pattern;
+mod plan;
+mod progress_bar;
+mod repair;
+mod resample;
+mod simplify;
+mod stream;
+mod svg;
+mod tcx;
+mod template;
+mod validate;
+mod zip;
+
+use std::fs;
 use std::fs::File;
+use std::io;
+use std::io::BufRead;
 use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
 use std::mem::take;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::rc::Rc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 
 use anyhow::anyhow;
+use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
+use borders::region_change_indices;
+use borders::Regions;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::ArgAction;
+use clap::ArgGroup;
+use clap::CommandFactory;
+use clap::FromArgMatches;
 use clap::Parser;
+use clap::ValueEnum;
+use course::Course;
+use course::CoursePoint;
+use course::CoursePointKind;
+use course::TrackPoint;
+use crossings::crossing_indices;
+use csv::write_csv;
+use elevation::smooth_elevations;
+use elevation::ElevationSource;
+use extensions::add_display_color;
+use extensions::lap_indices;
+use extensions::PointExtensions;
+use extensions::DISPLAY_COLORS;
+use fit::is_fit;
+use fit::read_fit;
+use fit::write_fit;
+use geo_types::Point;
+use geo_types::Rect;
+use geocode::Geocoder;
 use geoutils::Location;
 use gpx::Gpx;
+use gpx::Link;
+use gpx::Metadata;
+use gpx::Route;
+use gpx::Time;
 use gpx::Track;
 use gpx::TrackSegment;
 use gpx::Waypoint;
-
-macro_rules! ok_or_bail {
-    ($expr:expr) => {
-        match $expr {
-            Ok(val) => val,
-            Err(err) => return Some(Err(err)),
-        }
-    };
-}
+use gpxsplit::is_positive;
+use gpxsplit::time;
+use gpxsplit::Backtrack;
+use gpxsplit::Limit;
+use gpxsplit::Measure;
+use gpxsplit::Overlap;
+use gpxsplit::Progress;
+use gpxsplit::ProgressCallback;
+use gpxsplit::Snap;
+use gpxsplit::SpeedModel;
+use gpxsplit::SplitOptions;
+use gpxsplit::Subsequences;
+use gzip::gunzip;
+use gzip::gzip;
+use gzip::is_gzip;
+use json::Json;
+use kml::is_kml;
+use kml::read_kml;
+use kml::write_kml;
+use kml::Line;
+use logging::debug;
+use logging::info;
+use logging::verbose;
+use logging::Level;
+use metadata::RawMetadata;
+use pattern::Pattern;
+use plan::Cut;
+use plan::Plan;
+use plan::RoutePlan;
+use progress_bar::ProgressBar;
+use progress_bar::ReadProgress;
+use repair::repair;
+use resample::resample;
+use simplify::simplify;
+use stream::split_streaming;
+use stream::StreamLimits;
+use svg::write_map;
+use svg::write_profile;
+use svg::Profile;
+use tcx::is_tcx;
+use tcx::read_tcx;
+use tcx::write_tcx;
+use template::Template;
+use template::Value;
+use time::format_description;
+use time::Date;
+use time::OffsetDateTime;
+use time::PrimitiveDateTime;
+use time::UtcOffset;
+use validate::count;
+use validate::describe;
+use validate::validate;
+use validate::Problem;
+use zip::crc32;
+use zip::is_zip;
+use zip::read_zip_file;
+use zip::write_zip;
 
 /// Splits a long GPX file into separate files that won't overload the
 /// directions calculations on a Wahoo or other navigation device.
 ///
-/// Note this is only tested on the 2025 Tour Divide GPX file, and it assumes
-/// a single track and a single segment within that track.
-///
-/// If that isn't the case, the program will either exit with an error or just
-/// not divide the file up, depending on how the file differs. I didn't know
-/// that much about the structure and elements of GPX files and just poked
-/// around with a debugger after loading the 2025 Tour Divide file to figure
-/// out how to achieve this for that file.
+/// Only the first segment of the first track is split by default, with a
+/// note when there are more. `--tracks` and `--segments` can instead merge
+/// the others into it, split each of them on its own, or stop with an error,
+/// and `--per-track` and `--per-segment` write each of them to its own file
+/// without splitting. Files that only have routes are split like tracks.
 #[derive(Parser)]
+#[command(
+    after_help = "Use `gpxsplit validate FILE...` to check files for problems without \
+    splitting them."
+)]
+#[command(group(ArgGroup::new("criteria").multiple(true).args([
+    "km_per_file_option",
+    "max_points",
+    "max_bytes",
+    "hours_per_file",
+    "parts",
+    "at",
+    "at_percent",
+    "split_near",
+    "split_at_waypoint",
+    "gain_per_file",
+    "per_day",
+    "split_on_gap",
+    "split_on_jump",
+    "split_at_towns",
+    "split_where_crosses",
+    "grid_degrees",
+    "est_hours_per_file",
+    "split_at_borders",
+    "split_at_turnaround",
+    "split_on_lap",
+])))]
 struct Arguments {
-    /// GPX file to split into smaller files. Resulting files will be written to
-    /// the same directory, with numbers appended to the component of the
-    /// filename before the file extension.
-    gpx: PathBuf,
+    /// GPX files to split into smaller files, or FIT or TCX activities or
+    /// courses, or KML or KMZ routes. Resulting files will be written to the
+    /// same directory, with numbers appended to the component of the filename
+    /// before the file extension. File names can have `*` and `?` wildcards,
+    /// for shells that don't expand them. Use `-` to read from standard input,
+    /// which needs `--output-dir` and `--basename`. An `http://` or `https://`
+    /// URL is downloaded with `curl`, and the resulting files are written to
+    /// the current directory unless `--output-dir` is used. The last of them
+    /// can instead be the number of kilometers to include in each file, the
+    /// same as `--km-per-file`.
+    #[arg(required_unless_present = "recursive")]
+    gpx: Vec<PathBuf>,
+
+    /// Split every GPX file in this directory and the directories in it. The
+    /// files are written next to each GPX file, or with `--output-dir`, to
+    /// the same directories under the output directory. Files that look like
+    /// the parts from an earlier run are left alone.
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["basename", "zip", "overview"])]
+    recursive: Option<PathBuf>,
+
+    /// Join the input files end to end into a single route before splitting
+    /// it, such as to split a route that's in a file for each day in a
+    /// different way. The files are named after the first one.
+    #[arg(long, conflicts_with_all = ["recursive", "split_on_lap"])]
+    concat: bool,
+
+    /// Repair GPX files that can't be read because of small mistakes instead
+    /// of refusing them, such as by leaving out points with invalid positions
+    /// and removing unknown or invalid elements from points. What was repaired
+    /// is printed.
+    #[arg(long)]
+    lenient: bool,
+
+    /// Split the route in the opposite direction, to ride a published route
+    /// backwards without exporting it again from a route planner. The first
+    /// file starts at the end of the route. Times stay with their points, so
+    /// they run backwards unless they're replaced with `--synthesize-time` or
+    /// left out with `--strip-time`, and the options that split by time can't
+    /// be used.
+    #[arg(
+        long,
+        conflicts_with_all = ["hours_per_file", "per_day", "split_on_gap", "split_on_lap"]
+    )]
+    reverse: bool,
+
+    /// Leave out points at the same position as the point before them, or
+    /// closer to it than a distance given like `--dedupe=1m`. Route planners
+    /// often add them at every control, which uses up the points that devices
+    /// can handle and confuses some devices' distance display. Without this,
+    /// the summary and the report show how many each file has.
+    #[arg(
+        long,
+        value_name = "TOLERANCE",
+        value_parser = parse_distance,
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with = "split_on_lap"
+    )]
+    dedupe: Option<Option<f64>>,
+
+    /// Leave out points that are within this distance of the line between
+    /// the points kept on either side of them, like `5m`, with the
+    /// Douglas-Peucker algorithm. Routes from planners often have far more
+    /// points than they need, and can lose most of them without visibly
+    /// changing, so that they fit in fewer files on devices with a limit on
+    /// points. The route is simplified before it's split.
+    #[arg(
+        long,
+        value_name = "DISTANCE",
+        value_parser = parse_distance,
+        conflicts_with = "split_on_lap"
+    )]
+    simplify: Option<f64>,
+
+    /// Replace the points with points this distance apart along the route,
+    /// like `50m`, with elevations and times worked out from the points on
+    /// either side of them. This shrinks dense tracks, and fills in sparse
+    /// routes from planners, which can confuse turn guidance. Bends shorter
+    /// than the distance are cut off. The route is resampled before it's
+    /// split.
+    #[arg(
+        long,
+        value_name = "DISTANCE",
+        value_parser = parse_distance,
+        conflicts_with_all = ["simplify", "split_on_lap"]
+    )]
+    resample: Option<f64>,
+
+    /// Look up elevations for the points that don't have one, from SRTM
+    /// `.hgt` tiles like `--fill-elevation=srtm/`, or from an elevation
+    /// service like Open Topo Data or Open-Elevation given by its URL. Without
+    /// a source, Open Topo Data's 90 meter SRTM data is used, which takes a
+    /// second for every 100 points. Elevations from services are cached, so
+    /// splitting the same route again doesn't look them up again.
+    #[arg(
+        long,
+        value_name = "SOURCE",
+        num_args = 0..=1,
+        require_equals = true
+    )]
+    fill_elevation: Option<Option<String>>,
+
+    /// Smooth out the elevations with the median of this many points around
+    /// each one, like `5`, before the route is split. Noise in GPS elevations
+    /// adds up to far more climbing than there really is, which this takes
+    /// out of the limits, the summary, and the files.
+    #[arg(long, value_name = "POINTS", value_parser = clap::value_parser!(u64).range(2..))]
+    smooth_elevation: Option<u64>,
+
+    /// Replace the times of the points with times from riding the route at
+    /// `--speed`, plus any climbing at `--climbing-per-hour`, starting at
+    /// `--start`. Some devices need times on the points to race a virtual
+    /// partner along a course. The times are given before the route is split,
+    /// so each file carries on from where the one before it ends.
+    #[arg(long, requires = "start", conflicts_with = "strip_time")]
+    synthesize_time: bool,
+
+    /// Time to start the route at with `--synthesize-time`, like
+    /// `2025-06-13T07:00` or `2025-06-13T07:00-06:00`. Defaults to UTC without
+    /// an offset.
+    #[arg(
+        long,
+        requires = "synthesize_time",
+        value_parser = parse_start_time
+    )]
+    start: Option<OffsetDateTime>,
+
+    /// Read the file and write the resulting files a point at a time instead
+    /// of holding the whole route in memory, for routes with millions of
+    /// points. Only the first segment of the first track is split, by
+    /// `--km-per-file` or `--max-points`, and the file can't be compressed or
+    /// in another format. Most other options can't be used with it.
+    #[arg(long, conflicts_with = "concat")]
+    stream: bool,
+
+    /// With `--concat`, fail if a file starts further than this distance from
+    /// where the file before it ends, like `1km`, which usually means that the
+    /// files are out of order.
+    #[arg(long, requires = "concat", value_parser = parse_distance)]
+    max_concat_gap: Option<f64>,
+
+    /// Directory to write the resulting files to instead of the directory of
+    /// the GPX file. It's created if it doesn't exist yet.
+    #[arg(long, required_if_eq("gpx", "-"))]
+    output_dir: Option<PathBuf>,
+
+    /// Name to number the resulting files after, instead of the GPX file's
+    /// name without its extension.
+    #[arg(long, required_if_eq("gpx", "-"))]
+    basename: Option<String>,
+
+    /// Template for the names of the resulting files, like
+    /// `{basename}_day{index:02}_{start_km}-{end_km}km`. The placeholders are
+    /// `{basename}`, `{index}`, `{total}` number of files, `{start_km}` and
+    /// `{end_km}` along the route, the `{date}` of the first point, and the
+    /// `{track}` name. `.gpx` is added if it's missing. Defaults to
+    /// `{basename}_{index}`.
+    #[arg(long)]
+    name_template: Option<Template>,
+
+    /// Number of digits to pad `{index}` to with zeros. By default it's padded
+    /// to the number of digits in the number of files, and at least two, so
+    /// that the files sort in order.
+    #[arg(long)]
+    pad: Option<usize>,
+
+    /// Template for the name of the track inside each file, which is what
+    /// most devices show, like `Tour Divide – Day {index} ({km} km, {gain:,}
+    /// m)`. It has the same placeholders as `--name-template`, plus the `{km}`
+    /// and meters of climbing `{gain}` in the file. `{gain:,}` separates the
+    /// thousands with commas. Defaults to the file name.
+    #[arg(long)]
+    track_name_template: Option<Template>,
+
+    /// Describe the track in each file with its part number, distance,
+    /// climbing and descent, and where it starts and ends, for route planners
+    /// and devices that show descriptions.
+    #[arg(long)]
+    describe: bool,
+
+    /// Give the track or route in each file a different display color, using
+    /// the Garmin extension that Garmin devices and many viewers honor, so
+    /// that neighbouring files look different when they're loaded together.
+    #[arg(long)]
+    cycle_colors: bool,
+
+    /// Link each file's metadata to the previous and next files, so that the
+    /// sequence can be followed and checked for missing files.
+    #[arg(long)]
+    link_parts: bool,
+
+    /// Copy the input's metadata into each file exactly as it's written,
+    /// instead of rewriting it, which drops anything that isn't understood,
+    /// such as extensions.
+    #[arg(long, conflicts_with_all = ["link_parts", "strip_metadata"])]
+    verbatim_metadata: bool,
+
+    /// Leave the input's metadata out of each file, along with the bounds of
+    /// the file's route that are otherwise added to it.
+    #[arg(long)]
+    strip_metadata: bool,
+
+    /// Leave the times out of each file. Some devices treat a track with
+    /// times as an activity that was already ridden instead of a course to
+    /// follow, and the times show when the route was ridden. TCX and FIT
+    /// courses need times, so they're estimated from the distance instead.
+    #[arg(long)]
+    strip_time: bool,
+
+    /// Leave extensions out of each file, like the heart rate, cadence, power,
+    /// and temperature that devices record with every point, which can be
+    /// most of a file and aren't needed to follow it as a course.
+    #[arg(long, conflicts_with = "verbatim_metadata")]
+    strip_extensions: bool,
+
+    /// Creator to name in each file, instead of the input's. Some services
+    /// treat files differently depending on which app created them.
+    #[arg(long)]
+    creator: Option<String>,
+
+    /// Add the range of kilometers along the route that each file covers to
+    /// its name, like `divide_03_0520-0780km.gpx`, to tell at a glance which
+    /// file covers which stretch of the route.
+    #[arg(long, conflicts_with = "name_template")]
+    km_in_name: bool,
+
+    /// Look up the names of the places where each file starts and ends, and
+    /// add them to its name, like `divide_04_Pinedale-to-Rawlins.gpx`. They're
+    /// also available as `{start_place}` and `{end_place}` in a name template.
+    /// Places are looked up online with OpenStreetMap and cached. Without a
+    /// connection, the closest waypoint in the GPX file is used instead.
+    #[arg(long)]
+    geocode_names: bool,
+
+    /// Overwrite files left over from a previous run.
+    #[arg(long)]
+    force: bool,
+
+    /// Remove numbered files left over from a previous run that this run
+    /// doesn't replace, such as when the route is split into fewer files than
+    /// before. Only files named exactly like this run's files, like
+    /// `route_07.gpx`, are removed. Without this, there's a warning about them.
+    #[arg(long)]
+    remove_stale: bool,
+
+    /// Work out the split without writing anything, and print the files that
+    /// would be written with their number of points and distance.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Work out the split without writing anything, and print where each
+    /// route would be cut: the index of the point the file ends at, counting
+    /// from zero, how many kilometers along the route it is, its coordinates,
+    /// and the closest named waypoint in the GPX file.
+    #[arg(
+        long,
+        conflicts_with_all = ["dry_run", "save_plan", "report", "per_segment", "per_track"],
+    )]
+    plan: bool,
+
+    /// Work out the split without writing the files, and write where each
+    /// route is cut to this JSON file instead: the index of the point that
+    /// each file ends at, counting from zero, and how many kilometers along
+    /// the route it is. The plan can be edited and then split with
+    /// `--apply-plan`.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["dry_run", "per_segment", "per_track", "apply_plan"],
+    )]
+    save_plan: Option<PathBuf>,
+
+    /// Split the route where a plan from `--save-plan` says to, instead of
+    /// working out where to cut it. To move a cut, change the index of the
+    /// point where the file ends, or remove the index and change its
+    /// kilometers. The overlap options still apply.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["criteria", "per_segment", "per_track"],
+    )]
+    apply_plan: Option<PathBuf>,
+
+    /// Zip archive to write all of the resulting files into, instead of
+    /// writing them as separate files.
+    #[arg(long)]
+    zip: Option<PathBuf>,
+
+    /// Turn the waypoints within this distance of each file's route into course
+    /// points, like `100m`, so that devices alert about them along the way.
+    /// In GPX files, only these waypoints are kept, with the kind of course
+    /// point as their type. TCX and FIT files use 50m without this.
+    #[arg(long, value_parser = parse_distance)]
+    course_points: Option<f64>,
+
+    /// Write each file's points as a route instead of a track, which some
+    /// devices navigate differently, such as with turn prompts. GPX files
+    /// that only have routes are split and written as routes without this.
+    #[arg(long)]
+    as_route: bool,
+
+    /// Format to write the files in.
+    #[arg(long, value_enum, default_value_t = Format::Gpx)]
+    format: Format,
+
+    /// Single file to write all of the parts into, one after another, instead
+    /// of writing them as separate files. Only KML and GeoJSON support this,
+    /// with a GeoJSON feature collection.
+    #[arg(long, conflicts_with = "zip")]
+    overview: Option<PathBuf>,
+
+    /// JSON file to write a report of the files to after writing them, for
+    /// scripts: the input file, the command line arguments, and each file's
+    /// name, number of points and duplicate points, distance, climbing and
+    /// descent, estimated hours with `--estimate`, bounds, and CRC-32
+    /// checksum. With `--zip`, the names are those in the archive.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["dry_run", "overview", "save_plan"],
+    )]
+    report: Option<PathBuf>,
+
+    /// Read the files back after writing them, and check that together they
+    /// have every point of the route, in order and unchanged, apart from the
+    /// points repeated from the file before, and the same total distance.
+    /// Fails if anything is missing or different.
+    #[arg(
+        long,
+        conflicts_with_all = ["zip", "overview", "dry_run", "plan", "save_plan", "per_segment", "per_track"],
+    )]
+    verify: bool,
+
+    /// Write an SVG image of each file's elevation profile next to it, with
+    /// markers every few kilometers and the distances along the route where
+    /// the file starts and ends, for printing a card for each day.
+    #[arg(long, conflicts_with_all = ["dry_run", "plan", "save_plan"])]
+    profiles: bool,
+
+    /// SVG image to draw the whole route in, with each file in a different
+    /// color and a circle at each cut, to check where the cuts land before
+    /// loading the files anywhere. Also written with `--dry-run`.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["plan", "save_plan"])]
+    preview: Option<PathBuf>,
+
+    /// Only print errors, without a progress bar while long routes are split,
+    /// the table of files that were written, or warnings.
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Print more about what's being done: what was read and written with
+    /// `-v`, and also where each file starts and ends and why it was cut
+    /// there with `-vv`.
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Compress each file with gzip, adding `.gz` to its name. Long routes
+    /// compress to about a tenth of their size, and some apps can load them
+    /// directly.
+    #[arg(long, conflicts_with = "zip")]
+    gzip: bool,
+
+    /// Number of kilometers given after the input files, which is taken out of
+    /// `gpx` after parsing.
+    #[arg(skip)]
+    km_per_file: Option<f64>,
 
     /// Number of kilometers to include in each file. The file will be cut off
     /// after the next point that exceeds this number, so each file will be
     /// a bit longer than this number. Each succeeding file will include the
     /// final point from the preceeding file, so that the route is not missing
     /// the directions between those two points.
-    km_per_file: f64,
+    #[arg(long = "km-per-file", value_name = "KM_PER_FILE", value_parser = parse_positive)]
+    km_per_file_option: Option<f64>,
+
+    /// Maximum number of points to include in each file, including the point
+    /// carried over from the preceeding file. Some devices can't handle
+    /// courses with too many points, regardless of their length. If this is
+    /// used alongside a distance, a file is cut off as soon as either limit is
+    /// reached.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(2..))]
+    max_points: Option<u64>,
+
+    /// Maximum size of each written file, like `1M` or `500K`, for devices
+    /// with a limit on file size. The number of points that fit is estimated
+    /// from the size of the whole route, then any file that still comes out
+    /// too large is split again with fewer points.
+    #[arg(long, value_parser = parse_bytes)]
+    max_bytes: Option<usize>,
+
+    /// Number of points from the end of each file to repeat at the start of
+    /// the next one. Some devices need a few points of context to rejoin
+    /// navigation smoothly at the start of a course.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    overlap_points: u64,
+
+    /// Kilometers from the end of each file to repeat at the start of the next
+    /// one, instead of a number of points. The overlap is however many of the
+    /// last points it takes to cover this distance.
+    #[arg(long, conflicts_with = "overlap_points", value_parser = parse_positive)]
+    overlap_km: Option<f64>,
+
+    /// Don't repeat any points from the end of each file at the start of the
+    /// next one, so that the files are strictly disjoint. The leg between the
+    /// files isn't counted towards either of them.
+    #[arg(long, conflicts_with_all = ["overlap_points", "overlap_km"])]
+    no_overlap: bool,
+
+    /// Don't count the legs between points repeated from the previous file
+    /// towards the limits, so that every file covers the full distance, time,
+    /// or climbing of new route. The leg from the last repeated point to the
+    /// first new point is new route, so it's always counted.
+    #[arg(long, conflicts_with = "no_overlap")]
+    exclude_overlap: bool,
+
+    /// Number of hours of recorded time to include in each file, measured
+    /// from the timestamps of the points. Like the distance, the file is cut
+    /// off after the next point that exceeds this number. Every point must
+    /// have a timestamp for this to work.
+    #[arg(long, value_parser = parse_positive)]
+    hours_per_file: Option<f64>,
+
+    /// Number of files to split the route into. The total length of the route
+    /// is measured first, then each file is cut off after the first point
+    /// past its share of that length, so that the files are roughly the same
+    /// length.
+    #[arg(
+        long,
+        conflicts_with_all = ["km_per_file_option", "hours_per_file"],
+        value_parser = clap::value_parser!(u64).range(1..),
+    )]
+    parts: Option<u64>,
+
+    /// Comma-separated kilometer marks, measured from the start of the route,
+    /// to split at. Each file is cut off after the first point past its mark,
+    /// which is useful when the days of a trip have already been planned.
+    /// Marks past the end of the route are an error.
+    #[arg(long, value_delimiter = ',', conflicts_with = "parts", value_parser = parse_positive)]
+    at: Vec<f64>,
+
+    /// Comma-separated percentages of the total length of the route to split
+    /// at, like `25,50,75` to split it into quarters.
+    #[arg(long, value_delimiter = ',', conflicts_with = "parts", value_parser = parse_percent)]
+    at_percent: Vec<f64>,
+
+    /// Latitude and longitude, separated by a comma, to split at. The file is
+    /// cut off at the point on the route closest to this location, such as a
+    /// campsite or town that has already been picked out. Can be passed more
+    /// than once.
+    #[arg(long, value_parser = parse_location)]
+    split_near: Vec<Location>,
+
+    /// Pattern matching the names of waypoints in the GPX file, such as
+    /// resupply towns, to split at. The file is cut off at the point on the
+    /// route closest to each matching waypoint. Supports `.`, `*`, `+`, `?`,
+    /// `^`, and `$` like a regular expression.
+    #[arg(long)]
+    split_at_waypoint: Option<Pattern>,
+
+    /// Meters of climbing to include in each file, which is a better measure
+    /// of effort than distance on mountainous routes. Climbing is measured
+    /// from the elevation of each point, and points without an elevation are
+    /// skipped over.
+    #[arg(long, value_parser = parse_positive)]
+    gain_per_file: Option<f64>,
+
+    /// Start a new file whenever the date of the points' timestamps changes,
+    /// which splits a recorded multi-day trip into one file per day. Every
+    /// point must have a timestamp for this to work.
+    #[arg(long)]
+    per_day: bool,
+
+    /// UTC offset, like `-06:00`, to use when finding the date of each point
+    /// for `--per-day`. Named time zones aren't supported, so this needs to be
+    /// the offset in effect during the trip. Defaults to UTC.
+    #[arg(
+        long,
+        requires = "per_day",
+        allow_hyphen_values = true,
+        value_parser = parse_utc_offset,
+    )]
+    timezone: Option<UtcOffset>,
+
+    /// Start a new file wherever the time between two consecutive points is
+    /// longer than this duration, like `2h` or `45m`, which separates the days
+    /// of a recorded trip where the GPS was turned off overnight.
+    #[arg(long, value_parser = parse_duration)]
+    split_on_gap: Option<f64>,
+
+    /// Start a new file wherever two consecutive points are further apart
+    /// than this distance, like `5km` or `800m`, such as a ferry crossing or a
+    /// bad GPS fix.
+    #[arg(long, value_parser = parse_distance)]
+    split_on_jump: Option<f64>,
+
+    /// Don't carry the last point over to the next file when splitting on a
+    /// jump, so that neither file includes the jump itself.
+    #[arg(long, requires = "split_on_jump")]
+    drop_jumps: bool,
+
+    /// GPX file of towns, or any other places with services, as waypoints.
+    /// Once `--min-km` has passed since the last split, the file is cut off at
+    /// the point closest to the next town along the route, so that each file
+    /// ends somewhere useful.
+    #[arg(long, requires = "min_km")]
+    split_at_towns: Option<PathBuf>,
+
+    /// Minimum number of kilometers between splits at towns.
+    #[arg(long, requires = "split_at_towns")]
+    min_km: Option<f64>,
+
+    /// How close a town has to be to the route to split at it, like `2km`.
+    #[arg(long, default_value = "2km", value_parser = parse_distance)]
+    town_radius: f64,
+
+    /// Another GPX file, such as one with bailout roads, whose tracks and
+    /// routes the route is split at wherever it crosses them.
+    #[arg(long)]
+    split_where_crosses: Option<PathBuf>,
+
+    /// Size in degrees of a latitude and longitude grid, like `1` for 1°×1°
+    /// tiles, to split on for offline map preparation. Each file is a
+    /// contiguous run of points inside a single tile, without the point
+    /// carried over from the preceeding file.
+    #[arg(long, value_parser = parse_positive)]
+    grid_degrees: Option<f64>,
+
+    /// GeoJSON file of polygons, such as countries or states, to split at the
+    /// borders of. A new file starts whenever the route crosses from one
+    /// polygon feature into another.
+    #[arg(long)]
+    split_at_borders: Option<PathBuf>,
+
+    /// Split an out-and-back route at its turnaround, the point furthest from
+    /// the start, into an out file and a back file. Routes that end more than
+    /// halfway out to that point aren't out-and-back, so they aren't split.
+    #[arg(long)]
+    split_at_turnaround: bool,
+
+    /// Start a new file at each lap or pause marker in the extensions of the
+    /// points, which some Garmin and Wahoo devices record.
+    #[arg(long)]
+    split_on_lap: bool,
+
+    /// Number of hours of estimated riding time to include in each file, for
+    /// routes without timestamps. The time for each leg is estimated from its
+    /// distance at `--speed`, plus any climbing at `--climbing-per-hour`.
+    #[arg(long, requires = "speed", value_parser = parse_positive)]
+    est_hours_per_file: Option<f64>,
+
+    /// Average speed on flat ground in kilometers per hour, used to estimate
+    /// riding time.
+    #[arg(long, value_parser = parse_positive)]
+    speed: Option<f64>,
+
+    /// Meters of climbing that add an hour to the estimated riding time, like
+    /// `600`. Without this, the estimate only uses the distance.
+    #[arg(long, requires = "speed", value_parser = parse_positive)]
+    climbing_per_hour: Option<f64>,
+
+    /// Estimate the riding time of each file from its distance at `--speed`
+    /// plus its climbing at `--climbing-per-hour`, like an hour for every 300
+    /// m with `--climbing-per-hour 300`, and show it in the summary, the dry
+    /// run, and the report.
+    #[arg(long, requires = "speed")]
+    estimate: bool,
+
+    /// Never end a file partway up a climb. When a file would be cut off on a
+    /// climb, it continues to the top instead, unless that would go over
+    /// `--max-points`.
+    #[arg(long, visible_alias = "split-after-climb")]
+    no_split_in_climb: bool,
+
+    /// Average grade, in percent, that counts as a climb.
+    #[arg(long, default_value_t = 4.)]
+    climb_grade: f64,
+
+    /// Minimum length of a climb, like `1km`.
+    #[arg(long, default_value = "1km", value_parser = parse_distance)]
+    climb_length: f64,
+
+    /// Change in elevation that has to build up before it counts towards the
+    /// climbing and descent reported for each file, like `5m`. Smaller ups
+    /// and downs, which are often noise in GPS or barometric elevations, are
+    /// ignored.
+    #[arg(long, value_parser = parse_distance)]
+    elevation_threshold: Option<f64>,
+
+    /// Measure distances along the slope, including the change in elevation
+    /// between points, instead of only horizontally. On very steep routes the
+    /// horizontal distance is noticeably shorter than the distance travelled.
+    #[arg(long = "3d-distance")]
+    three_d_distance: bool,
+
+    /// Distance, like `5km`, to look around each cut from a limit for a
+    /// point close to a waypoint in the GPX file. The file is cut off at the
+    /// point closest to a waypoint within that distance along the route
+    /// instead, so that splits land at meaningful landmarks.
+    #[arg(long, value_parser = parse_distance)]
+    snap_to_waypoints: Option<f64>,
+
+    /// Distance, like `2km`, to backtrack from each cut from a limit to the
+    /// most recent junction, so that files end at a turn or a waypoint in the
+    /// GPX file instead of partway along a straight road.
+    #[arg(long, value_parser = parse_distance, conflicts_with = "snap_to_waypoints")]
+    backtrack_to_junction: Option<f64>,
+
+    /// Change in heading, in degrees, that counts as a junction.
+    #[arg(long, default_value_t = 45.)]
+    junction_angle: f64,
+
+    /// How close a point has to be to a waypoint in the GPX file to count as
+    /// a junction, like `50m`.
+    #[arg(long, default_value = "50m", value_parser = parse_distance)]
+    junction_radius: f64,
+
+    /// Instead of splitting the route, write every segment of every track in
+    /// the GPX file to its own numbered file, in order. Recorded tracks often
+    /// have a new segment after each pause.
+    #[arg(long, conflicts_with = "criteria")]
+    per_segment: bool,
+
+    /// Instead of splitting the route, write every track in the GPX file to
+    /// its own file, named after the track. Route planners often export the
+    /// main route and its alternates as separate tracks.
+    #[arg(long, conflicts_with_all = ["criteria", "per_segment"])]
+    per_track: bool,
+
+    /// What to do with the tracks after the first, when the GPX file has more
+    /// than one. Only the first is split by default.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Multiple::First,
+        conflicts_with_all = ["per_segment", "per_track"],
+    )]
+    tracks: Multiple,
+
+    /// What to do with the segments after the first, when a track has more
+    /// than one, such as after each pause in a recorded ride. Only the first
+    /// is split by default.
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Multiple::First,
+        conflicts_with_all = ["per_segment", "per_track"],
+    )]
+    segments: Multiple,
 }
 
-fn main() -> Result<()> {
-    let arguments = Arguments::parse();
+/// Checks files for problems that would stop them from being split properly,
+/// without splitting them. Fails if any of them have errors, rather than only
+/// warnings.
+#[derive(Parser)]
+#[command(name = "gpxsplit validate", bin_name = "gpxsplit validate")]
+struct ValidateArguments {
+    /// Files to check, in any of the formats that can be split.
+    #[arg(required = true)]
+    gpx: Vec<PathBuf>,
 
-    let file = File::open(&arguments.gpx)?;
-    let reader = BufReader::new(file);
-    let mut gpx = gpx::read(reader)?;
+    /// Distance between consecutive points that's reported as a gap, like
+    /// `5km`.
+    #[arg(long, default_value = "5km", value_parser = parse_distance)]
+    max_gap: f64,
+}
 
-    let waypoints = take(&mut get_segment(&mut gpx)?.points).into_iter();
-    let meters_per_file = arguments.km_per_file * 1000.;
+impl Arguments {
+    fn speed_model(&self) -> Option<SpeedModel> {
+        self.speed.map(|speed| SpeedModel {
+            meters_per_second: speed / 3.6,
+            seconds_per_meter_climbed: self
+                .climbing_per_hour
+                .map_or(0., |climbing_per_hour| 3600. / climbing_per_hour),
+        })
+    }
 
-    let basename = arguments
-        .gpx
-        .with_extension("")
-        .file_name()
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_owned();
+    /// The speed model to give points times with, which is `--speed` if
+    /// there is one, and otherwise a steady pace on a bike.
+    fn time_speed_model(&self) -> SpeedModel {
+        self.speed_model().unwrap_or(SpeedModel {
+            meters_per_second: COURSE_SPEED / 3.6,
+            seconds_per_meter_climbed: 0.,
+        })
+    }
 
-    let subsequences = LimitDistance {
-        waypoints,
-        meters_per_file,
-        prev_last: None,
-    };
+    fn log_level(&self) -> Level {
+        match self.verbose {
+            _ if self.quiet => Level::Quiet,
+            0 => Level::Info,
+            1 => Level::Verbose,
+            _ => Level::Debug,
+        }
+    }
+
+    fn elevation_threshold(&self) -> f64 {
+        self.elevation_threshold.unwrap_or_default()
+    }
+
+    fn measure(&self) -> Measure {
+        if self.three_d_distance {
+            Measure::Slope
+        } else {
+            Measure::Horizontal
+        }
+    }
+}
 
-    for (index, subsequence) in subsequences.enumerate() {
-        let name = format!("{}_{:02}.gpx", basename, index + 1);
-        let output = arguments.gpx.with_file_name(&name);
+fn main() -> Result<()> {
+    // a file named validate can still be split
+    let first = std::env::args_os().nth(1);
+    if first.is_some_and(|first| first == "validate" && !Path::new(&first).exists()) {
+        return validate_files(&ValidateArguments::parse_from(std::env::args_os().skip(1)));
+    }
+    let arguments = parse_arguments();
+    logging::set_level(arguments.log_level());
 
-        // update the GPX with the current set of waypoints, then write it to a numbered file
-        get_track(&mut gpx)?.name = Some(name);
-        get_segment(&mut gpx)?.points = subsequence?;
+    let mut inputs = Vec::new();
+    for path in &arguments.gpx {
+        if is_url(path) {
+            // there's no directory of the input file to write to
+            inputs.push(InputFile {
+                path: path.clone(),
+                output_dir: Some(arguments.output_dir.clone().unwrap_or_default()),
+                appended: Vec::new(),
+            });
+            continue;
+        }
+        inputs.extend(expand_wildcards(path)?.into_iter().map(|path| InputFile {
+            path,
+            output_dir: arguments.output_dir.clone(),
+            appended: Vec::new(),
+        }));
+    }
+    if arguments.concat {
+        let rest = inputs.split_off(1);
+        inputs[0].appended = rest.into_iter().map(|input_file| input_file.path).collect();
+    }
+    if let Some(directory) = &arguments.recursive {
+        let mut paths = Vec::new();
+        find_gpx_files(directory, arguments.output_dir.as_deref(), &mut paths)?;
+        if paths.is_empty() {
+            bail!("no gpx files found in {}", directory.display());
+        }
+        paths.sort();
+        inputs.extend(paths.into_iter().map(|path| {
+            // mirror the directories under the input directory
+            let output_dir = arguments.output_dir.as_ref().map(|output_dir| {
+                let relative = path
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(directory).ok());
+                output_dir.join(relative.unwrap_or(Path::new("")))
+            });
+            InputFile {
+                path,
+                output_dir,
+                appended: Vec::new(),
+            }
+        }));
+    }
 
-        let file = File::create_new(&output)
-            .with_context(|| format!("failed to create file {}", output.display()))?;
-        gpx::write(&gpx, file)?;
+    if inputs.len() > 1 {
+        for (used, option) in [
+            (arguments.basename.is_some(), "--basename"),
+            (arguments.zip.is_some(), "--zip"),
+            (arguments.overview.is_some(), "--overview"),
+            (arguments.report.is_some(), "--report"),
+            (arguments.preview.is_some(), "--preview"),
+            (arguments.save_plan.is_some(), "--save-plan"),
+            (arguments.apply_plan.is_some(), "--apply-plan"),
+        ] {
+            if used {
+                bail!("{option} can only be used with a single input file");
+            }
+        }
+    }
+    if let [input_file] = inputs.as_slice() {
+        return split_file(&arguments, input_file);
     }
 
+    // keep going after a file fails, so that one bad file doesn't stop the
+    // rest of the batch
+    let mut failures = 0;
+    for input_file in &inputs {
+        if let Err(error) = split_file(&arguments, input_file) {
+            eprintln!("{}: {error:#}", input_file.path.display());
+            failures += 1;
+        }
+    }
+    if failures > 0 {
+        bail!("failed to split {failures} of {} files", inputs.len());
+    }
     Ok(())
 }
 
-fn get_track(gpx: &mut Gpx) -> Result<&mut Track> {
-    Ok(gpx
-        .tracks
-        .get_mut(0)
-        .ok_or_else(|| anyhow!("gpx file missing track 0"))?)
+/// Checks each of the files for `gpxsplit validate`, printing the problems with
+/// each one, and fails if any of them have errors.
+fn validate_files(arguments: &ValidateArguments) -> Result<()> {
+    let mut paths = Vec::new();
+    for path in &arguments.gpx {
+        if is_url(path) {
+            paths.push(path.clone());
+        } else {
+            paths.extend(expand_wildcards(path)?);
+        }
+    }
+
+    let mut failures = 0;
+    for path in &paths {
+        let mut problems = Vec::new();
+        let mut summary = String::new();
+        match read_bytes(path) {
+            Ok(mut input) => {
+                // the problems that --lenient would repair stop the file from
+                // being read at all
+                if is_gpx(&input) {
+                    if let Ok((repaired, repairs)) = repair(&input) {
+                        problems.extend(repairs.into_iter().map(|description| {
+                            Problem::error(format!(
+                                "can only be read with --lenient, which {description}"
+                            ))
+                        }));
+                        input = repaired;
+                    }
+                }
+                match parse_input(&input, None) {
+                    Ok(gpx) => {
+                        summary = format!(": {}", describe(&gpx));
+                        problems.extend(validate(&gpx, arguments.max_gap));
+                    }
+                    Err(error) => {
+                        problems.push(Problem::error(format!("{error:#}")));
+                    }
+                }
+            }
+            Err(error) => problems.push(Problem::error(format!("{error:#}"))),
+        }
+
+        println!("{}{summary}", path.display());
+        for problem in &problems {
+            let severity = if problem.error { "error" } else { "warning" };
+            println!("  {severity}: {}", problem.description);
+        }
+        if problems.is_empty() {
+            println!("  no problems found");
+        }
+        if problems.iter().any(|problem| problem.error) {
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        bail!("{failures} of {} files have errors", paths.len());
+    }
+    Ok(())
 }
 
-fn get_segment(gpx: &mut Gpx) -> Result<&mut TrackSegment> {
-    Ok(get_track(gpx)?
-        .segments
-        .get_mut(0)
-        .ok_or_else(|| anyhow!("gpx track 0 missing segment 0"))?)
+/// Parses the command line, taking the number of kilometers per file off the
+/// end of the input files if it's there. It can't be a positional argument of
+/// its own, since it comes after any number of files.
+fn parse_arguments() -> Arguments {
+    let mut command = Arguments::command();
+    let matches = command.get_matches_mut();
+    let mut arguments = Arguments::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    // with --recursive, the number can be the only positional argument
+    let minimum = if arguments.recursive.is_some() { 1 } else { 2 };
+    if let (Some(last), true) = (arguments.gpx.last(), arguments.gpx.len() >= minimum) {
+        let km_per_file = last.to_str().and_then(|last| last.parse().ok());
+        if let (Some(km_per_file), false) = (km_per_file, last.exists()) {
+            if let Err(error) = parse_positive(&last.to_string_lossy()) {
+                command
+                    .error(
+                        ErrorKind::ValueValidation,
+                        format!("invalid value for KM_PER_FILE: {error}"),
+                    )
+                    .exit();
+            }
+            arguments.gpx.pop();
+            arguments.km_per_file = Some(km_per_file);
+        }
+    }
+
+    if arguments.km_per_file.is_some() {
+        for (used, option) in [
+            (arguments.km_per_file_option.is_some(), "--km-per-file"),
+            (arguments.parts.is_some(), "--parts"),
+            (arguments.apply_plan.is_some(), "--apply-plan"),
+        ] {
+            if used {
+                command
+                    .error(
+                        ErrorKind::ArgumentConflict,
+                        format!("KM_PER_FILE cannot be used with {option}"),
+                    )
+                    .exit();
+            }
+        }
+    } else if !matches.contains_id("criteria")
+        && !arguments.per_segment
+        && !arguments.per_track
+        && arguments.apply_plan.is_none()
+    {
+        command
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "KM_PER_FILE or another way to split the file is required",
+            )
+            .exit();
+    }
+
+    if arguments.verify && arguments.format != Format::Gpx {
+        command
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--verify only supports --format gpx",
+            )
+            .exit();
+    }
+
+    if arguments.stream {
+        let conflict = command.get_arguments().find_map(|argument| {
+            let id = argument.get_id().as_str();
+            let used = matches.value_source(id) == Some(ValueSource::CommandLine);
+            argument
+                .get_long()
+                .filter(|_| used && !STREAM_OPTIONS.contains(&id))
+        });
+        if let Some(long) = conflict {
+            command
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    format!("--stream cannot be used with --{long}"),
+                )
+                .exit();
+        }
+    }
+    arguments
 }
 
-/// Iterator of waypoints that reads from an underlying iterator and yields
-/// subsequences of waypoints, each one running until the `meters_per_file`
-/// distance has been reached.
-struct LimitDistance<Waypoints> {
-    waypoints: Waypoints,
-    meters_per_file: f64,
-    prev_last: Option<Waypoint>,
+/// Options that can be used with `--stream`.
+const STREAM_OPTIONS: [&str; 13] = [
+    "gpx",
+    "stream",
+    "km_per_file_option",
+    "max_points",
+    "three_d_distance",
+    "output_dir",
+    "basename",
+    "pad",
+    "force",
+    "remove_stale",
+    "strip_extensions",
+    "quiet",
+    "verbose",
+];
+
+/// A file to split, and where to write the resulting files.
+struct InputFile {
+    path: PathBuf,
+    /// Directory to write the resulting files to instead of the directory of
+    /// the input file.
+    output_dir: Option<PathBuf>,
+    /// Files to join onto the end of this one with `--concat`.
+    appended: Vec<PathBuf>,
 }
 
-impl<Waypoints: Iterator<Item = Waypoint>> Iterator for LimitDistance<Waypoints> {
-    type Item = Result<Vec<Waypoint>>;
+/// Splits the file at `path`, or standard input if it's `-`, and writes the
+/// resulting files.
+fn split_file(arguments: &Arguments, input_file: &InputFile) -> Result<()> {
+    if arguments.stream {
+        return split_file_streaming(arguments, input_file);
+    }
+    let path = &input_file.path;
+    let progress = ProgressBar::new(arguments.quiet);
+    let input = read_input(arguments, path)?;
+    let mut gpx = parse_input(&input, Some(&progress))?;
+    verbose!("read {}: {}", path.display(), describe(&gpx));
+    let mut extensions = point_extensions(arguments, &input, &gpx);
+    let from_routes = routes_to_tracks(&mut gpx);
+    for appended in &input_file.appended {
+        let appended_input = read_input(arguments, appended)?;
+        let mut other = parse_input(&appended_input, None)?;
+        if let Some(other_extensions) = point_extensions(arguments, &appended_input, &other) {
+            match &mut extensions {
+                Some(extensions) => extensions.extend(other_extensions),
+                None => extensions = Some(other_extensions),
+            }
+        }
+        routes_to_tracks(&mut other);
+        append_route(arguments, &mut gpx, other)
+            .with_context(|| format!("failed to join {}", appended.display()))?;
+    }
+    if arguments.reverse {
+        reverse(&mut gpx);
+    }
+    if let Some(tolerance) = arguments.dedupe {
+        for segment in gpx.tracks.iter_mut().flat_map(|track| &mut track.segments) {
+            let before = segment.points.len();
+            dedupe(&mut segment.points, tolerance);
+            verbose!(
+                "removed {} from a segment",
+                count(before - segment.points.len(), "duplicate point")
+            );
+        }
+    }
+    if let Some(tolerance) = arguments.simplify {
+        for segment in gpx.tracks.iter_mut().flat_map(|track| &mut track.segments) {
+            let before = segment.points.len();
+            simplify(&mut segment.points, tolerance);
+            verbose!(
+                "simplified a segment from {before} to {}",
+                count(segment.points.len(), "point")
+            );
+        }
+    }
+    if let Some(source) = &arguments.fill_elevation {
+        let mut source = ElevationSource::new(source.as_deref());
+        for segment in gpx.tracks.iter_mut().flat_map(|track| &mut track.segments) {
+            let filled = source.fill(&mut segment.points)?;
+            verbose!("filled in {} in a segment", count(filled, "elevation"));
+        }
+        source.save()?;
+    }
+    if let Some(spacing) = arguments.resample {
+        for segment in gpx.tracks.iter_mut().flat_map(|track| &mut track.segments) {
+            let before = segment.points.len();
+            segment.points = resample(&segment.points, spacing);
+            verbose!(
+                "resampled a segment from {before} to {}",
+                count(segment.points.len(), "point")
+            );
+        }
+    }
+    if let Some(window) = arguments.smooth_elevation {
+        for segment in gpx.tracks.iter_mut().flat_map(|track| &mut track.segments) {
+            smooth_elevations(&mut segment.points, window as usize);
+        }
+    }
+    if let Some(mut start) = arguments.start {
+        for segment in gpx.tracks.iter_mut().flat_map(|track| &mut track.segments) {
+            let meters = cumulative_meters(&segment.points, arguments.measure())?;
+            let times = estimated_times(
+                &segment.points,
+                &meters,
+                arguments.time_speed_model(),
+                start,
+            );
+            let before = extensions.as_ref().map(|_| segment.points.clone());
+            for (waypoint, time) in segment.points.iter_mut().zip(times) {
+                waypoint.time = Some(time.into());
+                start = time;
+            }
+            if let (Some(extensions), Some(before)) = (&mut extensions, before) {
+                extensions.retime(&before, &segment.points);
+            }
+        }
+    }
+
+    if arguments.strip_metadata {
+        gpx.metadata = None;
+    }
+    if let Some(creator) = &arguments.creator {
+        gpx.creator = Some(creator.clone());
+    }
+    let metadata = if arguments.verbatim_metadata {
+        RawMetadata::find(&String::from_utf8_lossy(&input)).map(Rc::new)
+    } else {
+        None
+    };
+    let extensions = extensions.map(Rc::new);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let first = self.waypoints.next()?;
+    let basename = basename(arguments, path);
+    create_output_dir(arguments, input_file)?;
 
-        let mut accumulated_meters: f64;
-        let mut accumulated_waypoints: Vec<Waypoint>;
+    let geocoder = arguments.geocode_names.then(|| {
+        Geocoder::new(
+            gpx.waypoints
+                .iter()
+                .filter_map(|waypoint| Some((location(waypoint), waypoint.name.clone()?)))
+                .collect(),
+        )
+    });
+    let geocoder = geocoder.as_ref();
+    let landmarks = if arguments.plan {
+        gpx.waypoints.clone()
+    } else {
+        Vec::new()
+    };
 
-        // include the last waypoint from the previous segment so that we don't lose
-        // navigation between those two points
-        match self.prev_last.take() {
-            Some(prev_last) => {
-                accumulated_meters = ok_or_bail!(distance(&prev_last, &first));
-                accumulated_waypoints = vec![prev_last, first];
+    let plan = match &arguments.apply_plan {
+        Some(plan) => Some(
+            fs::read_to_string(plan)
+                .map_err(anyhow::Error::from)
+                .and_then(|plan| Plan::parse(&plan))
+                .with_context(|| format!("failed to read plan {}", plan.display()))?,
+        ),
+        None => None,
+    };
+    let mut route_plans = Vec::new();
+    // the points of each route before it's split, for --verify
+    let mut originals = Vec::new();
+    let mut parts = if arguments.per_segment {
+        split_per_segment(arguments, gpx, &basename, geocoder)?
+    } else if arguments.per_track {
+        split_per_track(arguments, gpx, &basename, geocoder)?
+    } else {
+        let routes = select_routes(arguments, gpx)?;
+        if let Some(plan) = plan
+            .as_ref()
+            .filter(|plan| plan.routes.len() != routes.len())
+        {
+            bail!(
+                "plan is for {} routes, but {} routes are being split",
+                plan.routes.len(),
+                routes.len()
+            );
+        }
+        let numbered = routes.len() > 1;
+        let mut parts = Vec::new();
+        for (number, gpx) in routes.into_iter().enumerate() {
+            let basename = if numbered {
+                format!("{basename}_{}", number + 1)
+            } else {
+                basename.clone()
+            };
+            if arguments.verify {
+                let mut original = gpx.clone();
+                if arguments.strip_time {
+                    strip_times(&mut original);
+                }
+                originals.push(get_segment(&mut original)?.points.clone());
             }
-            None => {
-                // only applies to the first file
-                accumulated_meters = 0.;
-                accumulated_waypoints = vec![first];
+            let route_plan = plan.as_ref().map(|plan| &plan.routes[number]);
+            let (route_parts, route_plan) = split(
+                arguments, gpx, &input, &basename, geocoder, route_plan, &progress,
+            )?;
+            parts.extend(route_parts);
+            route_plans.push(route_plan);
+        }
+        parts
+    };
+    progress.clear();
+
+    if let Some(save_plan) = &arguments.save_plan {
+        let plan = Plan {
+            input: path.display().to_string(),
+            routes: route_plans,
+        };
+        return fs::write(save_plan, plan.to_json().to_pretty_string())
+            .with_context(|| format!("failed to write plan {}", save_plan.display()));
+    }
+    if arguments.plan {
+        print_plan(&route_plans, &landmarks);
+        return Ok(());
+    }
+    for part in &mut parts {
+        part.metadata.clone_from(&metadata);
+        part.extensions.clone_from(&extensions);
+        part.as_route = from_routes;
+        if arguments.strip_time {
+            if part.extensions.is_some() {
+                part.original_times = Some(
+                    points_mut(&mut part.gpx)
+                        .map(|waypoint| waypoint.time)
+                        .collect(),
+                );
             }
+            strip_times(&mut part.gpx);
         }
+    }
+
+    if let Some(preview) = &arguments.preview {
+        write_preview(arguments, preview, &basename, &parts)?;
+    }
+    if arguments.dry_run {
+        return print_dry_run(arguments, input_file, &parts);
+    }
+
+    if let Some(zip) = &arguments.zip {
+        write_zip_file(arguments, zip, &parts)?;
+    } else if let Some(overview) = &arguments.overview {
+        write_overview(arguments, overview, &basename, &parts)?;
+    } else {
+        let written = write_files(arguments, input_file, &parts, &progress)?;
+        progress.clear();
+        let pad = arguments
+            .pad
+            .unwrap_or_else(|| written.len().to_string().len().max(2));
+        remove_stale_files(arguments, path, &basename, pad, &written)?;
+        if arguments.verify {
+            verify(arguments, &written, &originals, &route_plans)?;
+        }
+    }
+    print_summary(arguments, &parts)?;
+    if arguments.profiles {
+        write_profiles(arguments, input_file, &parts)?;
+    }
+    if let Some(report) = &arguments.report {
+        write_report(arguments, input_file, &parts, report)?;
+    }
+
+    match geocoder {
+        Some(geocoder) => geocoder.save(),
+        None => Ok(()),
+    }
+}
 
-        while let Some(waypoint) = self.waypoints.next() {
-            let prev = accumulated_waypoints.last().unwrap();
+/// Splits the file at `path` with `--stream`, writing each resulting file as
+/// its points are read.
+fn split_file_streaming(arguments: &Arguments, input_file: &InputFile) -> Result<()> {
+    let path = &input_file.path;
+    let progress = ProgressBar::new(arguments.quiet);
+    let mut download = None;
+    let mut reader: BufReader<Box<dyn Read>> = if path == Path::new("-") {
+        BufReader::new(Box::new(io::stdin()))
+    } else if is_url(path) {
+        let mut child = curl(path)
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("failed to run curl")?;
+        let stdout = child.stdout.take().unwrap();
+        download = Some(child);
+        BufReader::new(Box::new(stdout))
+    } else {
+        let file =
+            File::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let size = file.metadata()?.len() as usize;
+        BufReader::new(Box::new(ReadProgress::new(
+            file,
+            size,
+            Arc::clone(&progress),
+        )))
+    };
+    let start = reader
+        .fill_buf()
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    if start.is_empty() {
+        bail!("failed to read {}", path.display());
+    }
+    if is_gzip(start) || !is_gpx(start) {
+        bail!("--stream can only read uncompressed gpx files");
+    }
 
-            accumulated_meters += ok_or_bail!(distance(prev, &waypoint));
-            accumulated_waypoints.push(waypoint);
+    let basename = basename(arguments, path);
+    create_output_dir(arguments, input_file)?;
 
-            if accumulated_meters > self.meters_per_file {
-                break;
+    let limits = StreamLimits {
+        meters: arguments
+            .km_per_file
+            .or(arguments.km_per_file_option)
+            .map(|km_per_file| km_per_file * 1000.),
+        max_points: arguments.max_points.map(|max| max as usize),
+    };
+    let measure = arguments.measure();
+    // the number of files isn't known until the end, so the numbers are
+    // always padded the same
+    let pad = arguments.pad.unwrap_or(2);
+    let mut written = Vec::new();
+    split_streaming(
+        reader,
+        &limits,
+        arguments.strip_extensions,
+        |a, b| Ok(measure.distance(a, b)?),
+        |index| {
+            let output = output_path(input_file, &format!("{basename}_{:0pad$}.gpx", index + 1));
+            if !arguments.force && output.exists() {
+                bail!(
+                    "{} already exists, use --force to overwrite it",
+                    output.display()
+                );
             }
+            let file = create_output(arguments, &output, "file")?;
+            written.push(output);
+            Ok(BufWriter::new(file))
+        },
+    )
+    .with_context(|| format!("failed to split {}", path.display()))?;
+    progress.clear();
+
+    if let Some(mut download) = download {
+        // the rest of the file after the first segment isn't read
+        download.kill().ok();
+        download.wait()?;
+    }
+    remove_stale_files(arguments, path, &basename, pad, &written)
+}
+
+/// The name of the input file without its extensions, or `--basename`, which
+/// the resulting files are named after.
+fn basename(arguments: &Arguments, path: &Path) -> String {
+    // the name in a URL ends before its query
+    let url_path;
+    let path = match path.to_str() {
+        Some(url) if is_url(path) => {
+            url_path = PathBuf::from(url.split(['?', '#']).next().unwrap());
+            &url_path
+        }
+        _ => path,
+    };
+    match &arguments.basename {
+        Some(basename) => basename.clone(),
+        None => match path.extension() {
+            // both extensions of a compressed file like `route.gpx.gz`
+            Some(extension) if extension == "gz" => path.with_extension(""),
+            _ => path.to_owned(),
+        }
+        .with_extension("")
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned(),
+    }
+}
+
+fn create_output_dir(arguments: &Arguments, input_file: &InputFile) -> Result<()> {
+    let writing = !arguments.dry_run && !arguments.plan && arguments.save_plan.is_none();
+    if let (Some(output_dir), true) = (&input_file.output_dir, writing) {
+        fs::create_dir_all(output_dir).with_context(|| {
+            format!("failed to create output directory {}", output_dir.display())
+        })?;
+    }
+    Ok(())
+}
+
+/// Reverses the order of the tracks in `gpx`, and of the segments and points
+/// in each of them.
+fn reverse(gpx: &mut Gpx) {
+    gpx.tracks.reverse();
+    for track in &mut gpx.tracks {
+        track.segments.reverse();
+        for segment in &mut track.segments {
+            segment.points.reverse();
         }
+    }
+}
+
+/// Removes the times from all of the points in `gpx`, and from its metadata.
+fn strip_times(gpx: &mut Gpx) {
+    if let Some(metadata) = &mut gpx.metadata {
+        metadata.time = None;
+    }
+    for waypoint in gpx.waypoints.iter_mut() {
+        waypoint.time = None;
+    }
+    for waypoint in points_mut(gpx) {
+        waypoint.time = None;
+    }
+}
+
+/// The track points and then the route points in `gpx`.
+fn points_mut(gpx: &mut Gpx) -> impl Iterator<Item = &mut Waypoint> {
+    let tracks = gpx.tracks.iter_mut().flat_map(|track| &mut track.segments);
+    tracks
+        .flat_map(|segment| &mut segment.points)
+        .chain(gpx.routes.iter_mut().flat_map(|route| &mut route.points))
+}
 
-        self.prev_last = accumulated_waypoints.last().cloned();
+/// Turns the routes in `gpx` into tracks if it doesn't have any tracks, which
+/// is how some route planners export, so that they're split the same way.
+/// Returns whether it did.
+fn routes_to_tracks(gpx: &mut Gpx) -> bool {
+    if !gpx.tracks.is_empty() || gpx.routes.is_empty() {
+        return false;
+    }
+    gpx.tracks = take(&mut gpx.routes)
+        .into_iter()
+        .map(|route| Track {
+            name: route.name,
+            comment: route.comment,
+            description: route.description,
+            source: route.source,
+            links: route.links,
+            type_: route.type_,
+            segments: vec![TrackSegment {
+                points: route.points,
+            }],
+            ..Track::default()
+        })
+        .collect();
+    true
+}
 
-        Some(Ok(accumulated_waypoints))
+/// Joins the route in `other` onto the end of the route in `gpx`, along with
+/// its waypoints.
+fn append_route(arguments: &Arguments, gpx: &mut Gpx, mut other: Gpx) -> Result<()> {
+    let points = take(&mut get_segment(&mut other)?.points);
+    let segment = get_segment(gpx)?;
+
+    let mut points = points.into_iter().peekable();
+    if let (Some(last), Some(first)) = (segment.points.last(), points.peek()) {
+        let gap = location(last)
+            .haversine_distance_to(&location(first))
+            .meters();
+        if let Some(max_gap) = arguments.max_concat_gap {
+            if gap > max_gap {
+                bail!(
+                    "it starts {:.1} km from the end of the file before it",
+                    gap / 1000.
+                );
+            }
+        }
+        // files for consecutive days often both have the point they meet at
+        if last.point() == first.point() {
+            points.next();
+        }
     }
+    segment.points.extend(points);
+    gpx.waypoints.append(&mut other.waypoints);
+    Ok(())
 }
 
-fn distance(a: &Waypoint, b: &Waypoint) -> Result<f64> {
-    location(a)
-        .distance_to(&location(b))
-        .map(|distance| distance.meters())
-        .map_err(|err| anyhow!("{}", err))
+/// Finds the files that match `path` if its file name has `*` or `?`
+/// wildcards, and otherwise returns it as it is.
+fn expand_wildcards(path: &Path) -> Result<Vec<PathBuf>> {
+    let Some(pattern) = path.file_name().and_then(|name| name.to_str()) else {
+        return Ok(vec![path.to_owned()]);
+    };
+    if !pattern.contains(['*', '?']) || path.exists() {
+        return Ok(vec![path.to_owned()]);
+    }
+
+    let directory = match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    };
+    let pattern: Vec<char> = pattern.chars().collect();
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(directory)
+        .with_context(|| format!("failed to read directory {}", directory.display()))?
+    {
+        let entry = entry?;
+        let name: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+        if wildcard_matches(&pattern, &name) && entry.file_type()?.is_file() {
+            paths.push(path.with_file_name(entry.file_name()));
+        }
+    }
+
+    if paths.is_empty() {
+        bail!("no files match {}", path.display());
+    }
+    paths.sort();
+    Ok(paths)
 }
 
-fn location(waypoint: &Waypoint) -> Location {
-    let point = waypoint.point();
-    Location::new(point.y(), point.x())
+/// Adds the GPX files in `directory` and the directories in it to `paths`,
+/// except for the output directory and files that are parts of another file.
+fn find_gpx_files(
+    directory: &Path,
+    output_dir: Option<&Path>,
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(directory)
+        .with_context(|| format!("failed to read directory {}", directory.display()))?
+    {
+        let path = entry?.path();
+        if path.is_dir() {
+            let is_output = output_dir.is_some_and(|output_dir| {
+                fs::canonicalize(&path).ok() == fs::canonicalize(output_dir).ok()
+            });
+            if !is_output {
+                find_gpx_files(&path, output_dir, paths)?;
+            }
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gpx"))
+        {
+            files.push(path);
+        }
+    }
+
+    // parts from an earlier run are named like `route_01.gpx`, next to
+    // `route.gpx`
+    let stems: Vec<String> = files
+        .iter()
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_owned()))
+        .collect();
+    paths.extend(files.into_iter().filter(|path| {
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            return true;
+        };
+        !stems.iter().any(|other| {
+            stem.strip_prefix(other.as_str())
+                .and_then(|rest| rest.strip_prefix('_'))
+                .is_some_and(|number| {
+                    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+                })
+        })
+    }));
+    Ok(())
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any number of
+/// characters and `?` matches any one character.
+fn wildcard_matches(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard_matches(&pattern[1..], name)
+                || (!name.is_empty() && wildcard_matches(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => wildcard_matches(&pattern[1..], &name[1..]),
+        (Some(expected), Some(c)) if expected == c => wildcard_matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Reads all of the file at `path` with `read_bytes`, repairing it with
+/// `--lenient`.
+fn read_input(arguments: &Arguments, path: &Path) -> Result<Vec<u8>> {
+    let mut input = read_bytes(path)?;
+    if arguments.lenient && is_gpx(&input) {
+        let (repaired, repairs) =
+            repair(&input).with_context(|| format!("failed to repair {}", path.display()))?;
+        for description in repairs {
+            info!("{}: {description}", path.display());
+        }
+        input = repaired;
+    }
+    Ok(input)
+}
+
+/// Reads all of the file at `path`, downloading it if it's a URL, or standard
+/// input if it's `-`, decompressing it if it's compressed with gzip.
+fn read_bytes(path: &Path) -> Result<Vec<u8>> {
+    let mut input = Vec::new();
+    if is_url(path) {
+        let output = curl(path).output().context("failed to run curl")?;
+        if !output.status.success() {
+            bail!(
+                "failed to download {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        input = output.stdout;
+    } else if path == Path::new("-") {
+        io::stdin()
+            .read_to_end(&mut input)
+            .context("failed to read standard input")?;
+    } else {
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut input))
+            .with_context(|| format!("failed to read {}", path.display()))?;
+    }
+
+    if is_gzip(&input) {
+        input =
+            gunzip(&input).with_context(|| format!("failed to decompress {}", path.display()))?;
+    }
+    Ok(input)
+}
+
+/// Whether an input file is a URL to download rather than a path.
+fn is_url(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.starts_with("http://") || path.starts_with("https://"))
+}
+
+/// A `curl` command that downloads `url` to its standard output, following
+/// redirects and decompressing the response if the server compressed it.
+fn curl(url: &Path) -> Command {
+    let mut command = Command::new("curl");
+    command
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--location",
+            "--compressed",
+        ])
+        .args(["--user-agent", "gpxsplit"])
+        .arg(url);
+    command
+}
+
+/// Extensions of the points in `gpx`, which was read from `input`, to write
+/// back out unless there's `--strip-extensions`.
+fn point_extensions(arguments: &Arguments, input: &[u8], gpx: &Gpx) -> Option<PointExtensions> {
+    if arguments.strip_extensions || !is_gpx(input) {
+        return None;
+    }
+    PointExtensions::find(&String::from_utf8_lossy(input), gpx)
+}
+
+/// Whether the input is a GPX file rather than one of the other formats that
+/// can be read.
+fn is_gpx(input: &[u8]) -> bool {
+    !(is_fit(input) || is_tcx(input) || is_zip(input) || is_kml(input))
+}
+
+/// Parses the input as whichever of the supported formats it is.
+fn parse_input(input: &[u8], progress: Option<&Arc<ProgressBar>>) -> Result<Gpx> {
+    if is_fit(input) {
+        read_fit(input).context("failed to read fit file")
+    } else if is_tcx(input) {
+        read_tcx(input).context("failed to read tcx file")
+    } else if is_zip(input) {
+        let kml = read_zip_file(input, |name| name.to_lowercase().ends_with(".kml"))
+            .context("failed to read kmz file")?
+            .ok_or_else(|| anyhow!("kmz file has no kml document"))?;
+        read_kml(kml.as_slice()).context("failed to read kml file")
+    } else if is_kml(input) {
+        read_kml(input).context("failed to read kml file")
+    } else if let Some(progress) = progress {
+        Ok(gpx::read(ReadProgress::new(
+            input,
+            input.len(),
+            Arc::clone(progress),
+        ))?)
+    } else {
+        Ok(gpx::read(input)?)
+    }
+}
+
+/// Picks the routes to split with `--tracks` and `--segments`, returning a GPX
+/// file for each route to split on its own. Each file has a single track
+/// with a single segment.
+fn select_routes(arguments: &Arguments, mut gpx: Gpx) -> Result<Vec<Gpx>> {
+    let count = gpx.tracks.len();
+    if count == 0 {
+        return Ok(vec![gpx]);
+    }
+    if arguments.split_on_lap && arguments.tracks != Multiple::First && count > 1 {
+        bail!("--split-on-lap only supports the first track");
+    }
+    if arguments.tracks == Multiple::Merge && arguments.segments == Multiple::Each {
+        bail!("--tracks merge can't be used with --segments each");
+    }
+    if count > 1 {
+        match arguments.tracks {
+            Multiple::First => {
+                info!(
+                    "only splitting the first of {count} tracks, use --tracks to choose what \
+                     to do with the others"
+                );
+                gpx.tracks.truncate(1);
+            }
+            Multiple::Error => bail!(
+                "gpx file has {count} tracks, use --tracks to choose what to do with the ones \
+                 after the first"
+            ),
+            Multiple::Merge | Multiple::Each => {}
+        }
+    }
+
+    // the routes in each track, after picking from its segments
+    let mut routes = Vec::new();
+    for track in take(&mut gpx.tracks) {
+        routes.extend(select_segments(arguments, track)?);
+    }
+
+    if arguments.tracks == Multiple::Merge {
+        let rest = routes.split_off(1);
+        if let Some(segment) = routes[0].segments.first_mut() {
+            for route in rest {
+                segment.points.extend(
+                    route
+                        .segments
+                        .into_iter()
+                        .flat_map(|segment| segment.points),
+                );
+            }
+        }
+    }
+
+    Ok(routes
+        .into_iter()
+        .map(|track| Gpx {
+            tracks: vec![track],
+            ..gpx.clone()
+        })
+        .collect())
+}
+
+/// Picks the segments of `track` to split with `--segments`, returning a track
+/// with a single segment for each route to split on its own.
+fn select_segments(arguments: &Arguments, mut track: Track) -> Result<Vec<Track>> {
+    let count = track.segments.len();
+    if count <= 1 {
+        return Ok(vec![track]);
+    }
+    if arguments.split_on_lap && arguments.segments != Multiple::First {
+        bail!("--split-on-lap only supports the first segment");
+    }
+
+    match arguments.segments {
+        Multiple::First => {
+            info!(
+                "only splitting the first of {count} segments, use --segments to choose \
+                 what to do with the others"
+            );
+            track.segments.truncate(1);
+            Ok(vec![track])
+        }
+        Multiple::Merge => {
+            let rest = track.segments.split_off(1);
+            let points = &mut track.segments[0].points;
+            for segment in rest {
+                points.extend(segment.points);
+            }
+            Ok(vec![track])
+        }
+        Multiple::Each => Ok(take(&mut track.segments)
+            .into_iter()
+            .map(|segment| Track {
+                segments: vec![segment],
+                ..track.clone()
+            })
+            .collect()),
+        Multiple::Error => bail!(
+            "gpx track has {count} segments, use --segments to choose what to do with the \
+             ones after the first"
+        ),
+    }
+}
+
+/// Splits the first segment of the first track into numbered files using the
+/// criteria in `arguments`, or where `plan` says to, returning the name and
+/// contents of each file and where the route was cut.
+fn split(
+    arguments: &Arguments,
+    mut gpx: Gpx,
+    input: &[u8],
+    basename: &str,
+    geocoder: Option<&Geocoder>,
+    plan: Option<&RoutePlan>,
+    progress: &Arc<ProgressBar>,
+) -> Result<(Vec<Part>, RoutePlan)> {
+    let waypoints = take(&mut get_segment(&mut gpx)?.points);
+    let measure = arguments.measure();
+    let route_meters = cumulative_meters(&waypoints, measure)?;
+    let track_name = get_track(&mut gpx)?.name.clone();
+
+    let mut limits = Vec::new();
+    if let Some(km_per_file) = arguments.km_per_file.or(arguments.km_per_file_option) {
+        limits.push(Limit::Meters(km_per_file * 1000.));
+    }
+    if let Some(hours_per_file) = arguments.hours_per_file {
+        limits.push(Limit::Seconds(hours_per_file * 3600.));
+    }
+    if let Some(gain_per_file) = arguments.gain_per_file {
+        limits.push(Limit::Gain(gain_per_file));
+    }
+    if let Some(est_hours_per_file) = arguments.est_hours_per_file {
+        limits.push(Limit::EstimatedSeconds(est_hours_per_file * 3600.));
+    }
+
+    let mut cut_meters: Vec<f64> = arguments.at.iter().map(|km| km * 1000.).collect();
+    if !arguments.at.is_empty() {
+        let total_meters = route_length(&waypoints, measure)?;
+        if let Some(km) = arguments.at.iter().find(|km| **km * 1000. >= total_meters) {
+            bail!(
+                "can't split at {km} km, which is past the end of the route at {:.1} km",
+                total_meters / 1000.
+            );
+        }
+    }
+    if arguments.parts.is_some() || !arguments.at_percent.is_empty() {
+        let total_meters = route_length(&waypoints, measure)?;
+        if let Some(parts) = arguments.parts {
+            cut_meters.extend((1..parts).map(|part| total_meters * part as f64 / parts as f64));
+        }
+        cut_meters.extend(
+            arguments
+                .at_percent
+                .iter()
+                .map(|percent| total_meters * percent / 100.),
+        );
+    }
+
+    let mut split_locations = arguments.split_near.clone();
+    if let Some(pattern) = &arguments.split_at_waypoint {
+        let matching: Vec<Location> = gpx
+            .waypoints
+            .iter()
+            .filter(|waypoint| {
+                waypoint
+                    .name
+                    .as_deref()
+                    .is_some_and(|name| pattern.is_match(name))
+            })
+            .map(location)
+            .collect();
+        if matching.is_empty() {
+            bail!("no waypoints in gpx file match the name pattern");
+        }
+        split_locations.extend(matching);
+    }
+
+    let mut cut_indices: Vec<usize> = split_locations
+        .iter()
+        .filter_map(|location| nearest_index(&waypoints, location))
+        .collect();
+    if arguments.per_day {
+        let offset = arguments.timezone.unwrap_or(UtcOffset::UTC);
+        cut_indices.extend(date_change_indices(&waypoints, offset)?);
+    }
+    if let Some(gap_seconds) = arguments.split_on_gap {
+        cut_indices.extend(gap_indices(&waypoints, gap_seconds)?);
+    }
+    if let Some(towns) = &arguments.split_at_towns {
+        let file = File::open(towns)
+            .with_context(|| format!("failed to open towns file {}", towns.display()))?;
+        let towns = gpx::read(BufReader::new(file))?.waypoints;
+        let min_meters = arguments.min_km.unwrap_or_default() * 1000.;
+        cut_indices.extend(town_indices(
+            &waypoints,
+            &towns,
+            min_meters,
+            arguments.town_radius,
+        )?);
+    }
+    if let Some(other) = &arguments.split_where_crosses {
+        let file = File::open(other)
+            .with_context(|| format!("failed to open file {}", other.display()))?;
+        let other = gpx::read(BufReader::new(file))?;
+        let lines: Vec<Vec<Waypoint>> = other
+            .tracks
+            .into_iter()
+            .flat_map(|track| track.segments)
+            .map(|segment| segment.points)
+            .chain(other.routes.into_iter().map(|route| route.points))
+            .collect();
+        cut_indices.extend(crossing_indices(&waypoints, &lines));
+    }
+    let mut detached_indices = Vec::new();
+    if let Some(jump_meters) = arguments.split_on_jump {
+        let indices = jump_indices(&waypoints, jump_meters)?;
+        if arguments.drop_jumps {
+            detached_indices.extend(&indices);
+        }
+        cut_indices.extend(indices);
+    }
+    if let Some(borders) = &arguments.split_at_borders {
+        let geojson = fs::read_to_string(borders)
+            .with_context(|| format!("failed to read geojson file {}", borders.display()))?;
+        let regions = Regions::from_geojson(&geojson)
+            .with_context(|| format!("failed to parse geojson file {}", borders.display()))?;
+        cut_indices.extend(region_change_indices(&waypoints, &regions));
+    }
+    if arguments.split_on_lap {
+        if !is_gpx(input) {
+            bail!("--split-on-lap only supports gpx input");
+        }
+        cut_indices.extend(lap_indices(input)?);
+    }
+    if arguments.split_at_turnaround {
+        match turnaround_index(&waypoints) {
+            Some(index) => cut_indices.push(index),
+            None => info!("the route doesn't come back toward its start, so there's no turnaround to split at"),
+        }
+    }
+    if let Some(grid_degrees) = arguments.grid_degrees {
+        let indices = tile_change_indices(&waypoints, grid_degrees);
+        detached_indices.extend(&indices);
+        cut_indices.extend(indices);
+    }
+    if let Some(plan) = plan {
+        if let Some(points) = plan.points.filter(|points| *points != waypoints.len()) {
+            bail!(
+                "plan is for a route with {points} points, but this one has {}",
+                waypoints.len()
+            );
+        }
+        for cut in &plan.cuts {
+            match cut.index {
+                Some(index) if index == 0 || index + 1 >= waypoints.len() => bail!(
+                    "plan cuts the route at point {index}, which has to be between 1 and {}",
+                    waypoints.len().saturating_sub(2)
+                ),
+                Some(index) => {
+                    cut_indices.push(index);
+                    if cut.detached {
+                        detached_indices.push(index);
+                    }
+                }
+                None => cut_meters.extend(cut.km.map(|km| km * 1000.)),
+            }
+        }
+    }
+    cut_meters.sort_by(f64::total_cmp);
+    cut_indices.sort();
+    cut_indices.dedup();
+    detached_indices.sort();
+    detached_indices.dedup();
+    cut_indices.retain(|index| *index > 0 && index + 1 < waypoints.len());
+
+    let snap = match arguments.snap_to_waypoints {
+        Some(meters) => {
+            if gpx.waypoints.is_empty() {
+                bail!("gpx file has no waypoints to snap to");
+            }
+            let landmarks: Vec<Location> = gpx.waypoints.iter().map(location).collect();
+            Some(Snap {
+                meters,
+                landmark_meters: waypoints
+                    .iter()
+                    .map(|waypoint| nearest_meters(&landmarks, &location(waypoint)))
+                    .collect(),
+            })
+        }
+        None => None,
+    };
+
+    let backtrack = match arguments.backtrack_to_junction {
+        Some(meters) => {
+            let landmarks: Vec<Location> = gpx.waypoints.iter().map(location).collect();
+            Some(Backtrack {
+                meters,
+                junctions: junction_waypoints(
+                    &waypoints,
+                    &landmarks,
+                    arguments.junction_angle,
+                    arguments.junction_radius,
+                ),
+            })
+        }
+        None => None,
+    };
+
+    let climbing = if arguments.no_split_in_climb {
+        climbing_waypoints(
+            &waypoints,
+            arguments.climb_grade / 100.,
+            arguments.climb_length,
+        )?
+    } else {
+        Vec::new()
+    };
+
+    let mut max_points = arguments.max_points.map(|max| max as usize);
+    if let Some(max_bytes) = arguments.max_bytes {
+        let fitting = points_under_bytes(&mut gpx, &waypoints, basename, max_bytes)?;
+        max_points = Some(max_points.map_or(fitting, |max_points| max_points.min(fitting)));
+    }
+    let overlap = match arguments.overlap_km {
+        _ if arguments.no_overlap => Overlap::None,
+        Some(overlap_km) => Overlap::Meters(overlap_km * 1000.),
+        None => Overlap::Points(arguments.overlap_points as usize),
+    };
+    let min_points = overlap.min_points();
+    if max_points.is_some_and(|max_points| max_points < min_points) {
+        bail!("each file needs at least {min_points} points to fit the overlap");
+    }
+
+    // shows the number of points split so far, and the files split off
+    let total_points = waypoints.len();
+    let files = AtomicUsize::new(0);
+    let bar = Arc::clone(progress);
+    let progress: ProgressCallback = Arc::new(move |progress| match progress {
+        Progress::PointsRead(points) => bar.show("splitting", points, total_points, || {
+            format!(
+                "{points} of {total_points} points, {} files",
+                files.load(Ordering::Relaxed)
+            )
+        }),
+        Progress::PartFinished { .. } => {
+            files.fetch_add(1, Ordering::Relaxed);
+        }
+        Progress::BytesWritten(_) => {}
+    });
+
+    let mut options = SplitOptions {
+        limits,
+        max_points,
+        measure,
+        speed_model: arguments.speed_model(),
+        cut_meters,
+        cut_indices,
+        detached_indices,
+        climbing,
+        snap,
+        backtrack,
+        overlap,
+        exclude_overlap: arguments.exclude_overlap,
+        strategies: Vec::new(),
+        progress: Some(progress),
+    };
+
+    loop {
+        let mut parts = Vec::new();
+        let mut attempt = Subsequences::new(waypoints.iter().cloned(), options.clone());
+        while let Some(subsequence) = attempt.next() {
+            let subsequence = subsequence?;
+            let first_index = attempt.next_index() - subsequence.len();
+            parts.push((first_index, subsequence));
+        }
+
+        let ranges: Vec<(usize, usize)> = parts
+            .iter()
+            .map(|(first_index, subsequence)| (*first_index, first_index + subsequence.len() - 1))
+            .collect();
+        let total = parts.len();
+        let mut files = Vec::new();
+        for (index, (first_index, subsequence)) in parts.into_iter().enumerate() {
+            let part_name = PartName {
+                basename,
+                index,
+                total,
+                start_meters: route_meters[first_index],
+                end_meters: route_meters[first_index + subsequence.len() - 1],
+                date: date(arguments, &subsequence),
+                track: track_name.as_deref(),
+                waypoints: &subsequence,
+                geocoder,
+                elevation_threshold: arguments.elevation_threshold(),
+            };
+            let name = file_name(arguments, &part_name, DEFAULT_NAME_TEMPLATE)?;
+            let new_track_name = track_name_from_template(arguments, &part_name)?;
+            let description = description(arguments, &part_name)?;
+
+            // update the GPX with the current set of waypoints
+            let track = get_track(&mut gpx)?;
+            track.name = Some(new_track_name.unwrap_or_else(|| default_track_name(&name)));
+            if description.is_some() {
+                track.description = description;
+            }
+            get_segment(&mut gpx)?.points = subsequence;
+
+            files.push(Part {
+                name,
+                gpx: gpx.clone(),
+                index,
+                start_meters: route_meters[first_index],
+                metadata: None,
+                extensions: None,
+                original_times: None,
+                as_route: false,
+            });
+        }
+
+        link_parts(arguments, &mut files);
+
+        // the size per point is only an estimate, so if any file came out too large,
+        // shrink the number of points by how far over it went and split again
+        let largest = match arguments.max_bytes {
+            Some(_) => files
+                .iter()
+                .map(|part| Ok(part.encode(arguments)?.len()))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .max(),
+            None => None,
+        };
+        if let (Some(max_bytes), Some(largest), Some(max_points)) =
+            (arguments.max_bytes, largest, options.max_points)
+        {
+            if largest > max_bytes {
+                let shrunk = (max_points as f64 * max_bytes as f64 / largest as f64) as usize;
+                let shrunk = shrunk.min(max_points - 1);
+                if shrunk < min_points {
+                    bail!("--max-bytes {max_bytes} is too small to fit the overlap in a file");
+                }
+                debug!(
+                    "largest file is {largest} bytes, splitting again with at most {shrunk} \
+                     points in each file"
+                );
+                options.max_points = Some(shrunk);
+                continue;
+            }
+        }
+
+        let cuts = ranges
+            .windows(2)
+            .map(|pair| {
+                let (_, last_index) = pair[0];
+                let (next_index, _) = pair[1];
+                let point = waypoints[last_index].point();
+                Cut {
+                    index: Some(last_index),
+                    km: Some(route_meters[last_index] / 1000.),
+                    position: Some((point.y(), point.x())),
+                    detached: next_index > last_index && !matches!(options.overlap, Overlap::None),
+                }
+            })
+            .collect();
+        let plan = RoutePlan {
+            points: Some(waypoints.len()),
+            cuts,
+        };
+
+        verbose!(
+            "split {} points, {:.1} km, into {}",
+            waypoints.len(),
+            route_meters.last().copied().unwrap_or_default() / 1000.,
+            count(files.len(), "file")
+        );
+        if logging::enabled(Level::Debug) {
+            for (part, (first_index, last_index)) in files.iter().zip(&ranges) {
+                debug!(
+                    "{}: points {first_index} to {last_index}, {:.2} to {:.2} km, ends because {}",
+                    part.name,
+                    route_meters[*first_index] / 1000.,
+                    route_meters[*last_index] / 1000.,
+                    cut_reason(
+                        &options,
+                        &waypoints,
+                        &route_meters,
+                        *first_index,
+                        *last_index
+                    )?
+                );
+            }
+        }
+        return Ok((files, plan));
+    }
+}
+
+/// Why a file from `split` ends at `last_index`, for `-vv`. The splitter
+/// doesn't say, so this makes the same checks it does in the same order.
+fn cut_reason(
+    options: &SplitOptions,
+    waypoints: &[Waypoint],
+    route_meters: &[f64],
+    first_index: usize,
+    last_index: usize,
+) -> Result<String> {
+    if last_index + 1 == waypoints.len() {
+        return Ok("the route ends there".to_owned());
+    }
+    if options.cut_indices.binary_search(&last_index).is_ok() {
+        return Ok(format!("the options cut the route at point {last_index}"));
+    }
+    let (start, end) = (route_meters[first_index], route_meters[last_index]);
+    if let Some(meters) = options
+        .cut_meters
+        .iter()
+        .find(|meters| start < **meters && **meters <= end)
+    {
+        return Ok(format!(
+            "the options cut the route at {:.2} km",
+            meters / 1000.
+        ));
+    }
+    if let Some(max_points) = options
+        .max_points
+        .filter(|max_points| *max_points == last_index - first_index + 1)
+    {
+        return Ok(format!(
+            "it has {max_points} points, the most a file can have"
+        ));
+    }
+
+    let part = &waypoints[first_index..=last_index];
+    let (gain, _) = gain_and_loss(part, 0.);
+    let mut reached = Vec::new();
+    for limit in &options.limits {
+        match *limit {
+            Limit::Meters(meters) if end - start >= meters => {
+                reached.push(format!(
+                    "{:.2} of {:.2} km",
+                    (end - start) / 1000.,
+                    meters / 1000.
+                ));
+            }
+            Limit::Seconds(seconds) => {
+                let elapsed = (time(&part[part.len() - 1])? - time(&part[0])?).as_seconds_f64();
+                if elapsed >= seconds {
+                    reached.push(format!(
+                        "{} of {} hours",
+                        format_hours(elapsed),
+                        format_hours(seconds)
+                    ));
+                }
+            }
+            Limit::Gain(limit) if gain >= limit => {
+                reached.push(format!("{gain:.0} of {limit:.0} m climbing"));
+            }
+            Limit::EstimatedSeconds(seconds) => {
+                let estimated = options
+                    .speed_model
+                    .map_or(0., |speed_model| speed_model.seconds(end - start, gain));
+                if estimated >= seconds {
+                    reached.push(format!(
+                        "{} of {} estimated hours",
+                        format_hours(estimated),
+                        format_hours(seconds)
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(
+        match (reached.is_empty(), &options.snap, &options.backtrack) {
+            (false, _, _) => format!("it reached {}", reached.join(" and ")),
+            (true, Some(_), _) => "the cut was moved to a landmark before the limit".to_owned(),
+            (true, None, Some(_)) => "the cut was moved back to a junction".to_owned(),
+            (true, None, None) => "a limit was reached".to_owned(),
+        },
+    )
+}
+
+/// Estimates how many points fit in a file of at most `max_bytes`, from the
+/// size of `gpx` with and without all of `waypoints` in its segment. The
+/// names of the files aren't known yet, so the basename stands in for them.
+fn points_under_bytes(
+    gpx: &mut Gpx,
+    waypoints: &[Waypoint],
+    basename: &str,
+    max_bytes: usize,
+) -> Result<usize> {
+    let original_name = get_track(gpx)?.name.replace(basename.to_owned());
+    let empty_bytes = encode(gpx)?.len();
+
+    get_segment(gpx)?.points = waypoints.to_vec();
+    let full_bytes = encode(gpx)?.len();
+    get_segment(gpx)?.points.clear();
+    get_track(gpx)?.name = original_name;
+
+    let point_bytes = (full_bytes - empty_bytes) as f64 / waypoints.len().max(1) as f64;
+    let fitting = (max_bytes.saturating_sub(empty_bytes) as f64 / point_bytes) as usize;
+    if fitting < 2 {
+        bail!("--max-bytes {max_bytes} is too small to fit two points in a file");
+    }
+    Ok(fitting)
+}
+
+/// Splits each segment of each track into a numbered file, keeping the rest of
+/// the track and the GPX file the same.
+fn split_per_segment(
+    arguments: &Arguments,
+    mut gpx: Gpx,
+    basename: &str,
+    geocoder: Option<&Geocoder>,
+) -> Result<Vec<Part>> {
+    let tracks = take(&mut gpx.tracks);
+    let mut files = Vec::new();
+    let total = tracks.iter().map(|track| track.segments.len()).sum();
+    let mut index = 0;
+    let mut start_meters = 0.;
+
+    for mut track in tracks {
+        let segments = take(&mut track.segments);
+
+        for segment in segments {
+            let end_meters = start_meters + route_length(&segment.points, arguments.measure())?;
+            let part_name = PartName {
+                basename,
+                index,
+                total,
+                start_meters,
+                end_meters,
+                date: date(arguments, &segment.points),
+                track: track.name.as_deref(),
+                waypoints: &segment.points,
+                geocoder,
+                elevation_threshold: arguments.elevation_threshold(),
+            };
+            let name = file_name(arguments, &part_name, DEFAULT_NAME_TEMPLATE)?;
+            let track_name = track_name_from_template(arguments, &part_name)?;
+            let description = description(arguments, &part_name)?;
+
+            gpx.tracks = vec![Track {
+                name: Some(track_name.unwrap_or_else(|| default_track_name(&name))),
+                description: description.or_else(|| track.description.clone()),
+                segments: vec![segment],
+                ..track.clone()
+            }];
+
+            files.push(Part {
+                name,
+                gpx: gpx.clone(),
+                index,
+                start_meters,
+                metadata: None,
+                extensions: None,
+                original_times: None,
+                as_route: false,
+            });
+            index += 1;
+            start_meters = end_meters;
+        }
+    }
+
+    link_parts(arguments, &mut files);
+    Ok(files)
+}
+
+/// Splits each track into a file, keeping the rest of the GPX file the same.
+/// Files are numbered so that tracks with the same name don't collide, and
+/// the track's name is appended to the number.
+fn split_per_track(
+    arguments: &Arguments,
+    mut gpx: Gpx,
+    basename: &str,
+    geocoder: Option<&Geocoder>,
+) -> Result<Vec<Part>> {
+    let tracks = take(&mut gpx.tracks);
+    let mut files = Vec::new();
+    let total = tracks.len();
+
+    for (index, track) in tracks.into_iter().enumerate() {
+        let points: Vec<Waypoint> = track
+            .segments
+            .iter()
+            .flat_map(|segment| segment.points.iter().cloned())
+            .collect();
+        let part_name = PartName {
+            basename,
+            index,
+            total,
+            start_meters: 0.,
+            end_meters: route_length(&points, arguments.measure())?,
+            date: date(arguments, &points),
+            track: track.name.as_deref(),
+            waypoints: &points,
+            geocoder,
+            elevation_threshold: arguments.elevation_threshold(),
+        };
+        let name = file_name(
+            arguments,
+            &part_name,
+            match track.name {
+                Some(_) => "{basename}_{index}_{track}",
+                None => DEFAULT_NAME_TEMPLATE,
+            },
+        )?;
+        let track_name = track_name_from_template(arguments, &part_name)?;
+        let description = description(arguments, &part_name)?;
+
+        gpx.tracks = vec![Track {
+            name: track_name.or(track.name),
+            description: description.or(track.description),
+            ..track
+        }];
+
+        files.push(Part {
+            name,
+            gpx: gpx.clone(),
+            index,
+            start_meters: 0.,
+            metadata: None,
+            extensions: None,
+            original_times: None,
+            as_route: false,
+        });
+    }
+
+    link_parts(arguments, &mut files);
+    Ok(files)
+}
+
+/// A file to write, and its contents. The `gpx` crate can't write extensions,
+/// so the display color, the points' extensions, and verbatim metadata are
+/// added separately.
+struct Part {
+    name: String,
+    gpx: Gpx,
+    /// Position of the file among all of the files, starting from zero.
+    index: usize,
+    /// Distance along the route where the file starts, in meters.
+    start_meters: f64,
+    metadata: Option<Rc<RawMetadata>>,
+    extensions: Option<Rc<PointExtensions>>,
+    /// Times of the track points and then the route points from before
+    /// `--strip-time`, which the points' extensions are found by.
+    original_times: Option<Vec<Option<Time>>>,
+    /// Whether to write the GPX file with a route rather than a track, because
+    /// the input had routes.
+    as_route: bool,
+}
+
+impl Part {
+    fn encode(&self, arguments: &Arguments) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        match arguments.format {
+            Format::Gpx => bytes = self.encode_gpx(arguments)?,
+            Format::Tcx => write_tcx(&mut bytes, &course(arguments, self)?)?,
+            Format::Fit => write_fit(&mut bytes, &course(arguments, self)?)?,
+            Format::Kml => {
+                let title = self.title(arguments);
+                let waypoints = self.waypoints();
+                let line = Line {
+                    name: &title,
+                    index: self.index,
+                    waypoints: &waypoints,
+                };
+                write_kml(&mut bytes, &title, &[line])?;
+            }
+            Format::Geojson => bytes = feature(arguments, self)?.to_string().into_bytes(),
+            Format::Csv => {
+                let waypoints = self.waypoints();
+                let meters = cumulative_meters(&waypoints, arguments.measure())?;
+                write_csv(&mut bytes, &waypoints, &meters)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /// Writes the file as GPX, with the changes from any GPX options, and
+    /// adding what the `gpx` crate can't write.
+    fn encode_gpx(&self, arguments: &Arguments) -> Result<Vec<u8>> {
+        let mut gpx = self.gpx.clone();
+        // the points' extensions are found by their times, so they're looked
+        // up in a copy with the times put back
+        let mut keyed = self.original_times.as_ref().map(|times| {
+            let mut keyed = self.gpx.clone();
+            for (waypoint, time) in points_mut(&mut keyed).zip(times) {
+                waypoint.time = *time;
+            }
+            keyed
+        });
+
+        if let Some(meters) = arguments.course_points {
+            let waypoints = self.waypoints();
+            gpx.waypoints = nearby_waypoints(&self.gpx.waypoints, &waypoints, meters)
+                .into_iter()
+                .map(|(_, waypoint)| {
+                    let mut waypoint = waypoint.clone();
+                    waypoint.type_ = Some(CoursePointKind::of(&waypoint).name().to_owned());
+                    waypoint
+                })
+                .collect();
+        }
+
+        // the bounds of the route, without the waypoints from the rest of it,
+        // so that maps for just this part can be fetched
+        if let (false, Some((min, max))) = (arguments.strip_metadata, bounds(&self.waypoints())) {
+            gpx.metadata.get_or_insert_with(Metadata::default).bounds =
+                Some(Rect::new(min.0, max.0));
+        }
+
+        if arguments.as_route || self.as_route {
+            tracks_to_routes(&mut gpx);
+            if let Some(keyed) = &mut keyed {
+                tracks_to_routes(keyed);
+            }
+        }
+
+        let mut bytes = encode(&gpx)?;
+        if let Some(extensions) = &self.extensions {
+            bytes = extensions
+                .add(&String::from_utf8(bytes)?, keyed.as_ref().unwrap_or(&gpx))
+                .into_bytes();
+        }
+        if let Some(color) = display_color(arguments, self.index) {
+            bytes = add_display_color(&String::from_utf8(bytes)?, color).into_bytes();
+        }
+        if let Some(metadata) = &self.metadata {
+            bytes = metadata.replace(&String::from_utf8(bytes)?).into_bytes();
+        }
+        Ok(bytes)
+    }
+
+    /// Name of the route in the file, for formats that name it separately
+    /// from the file. It's the track's name, without the file extension if
+    /// that's where it came from.
+    fn title(&self, arguments: &Arguments) -> String {
+        let name = self
+            .gpx
+            .tracks
+            .first()
+            .and_then(|track| track.name.as_ref())
+            .unwrap_or(&self.name);
+        strip_extension(arguments, name).to_owned()
+    }
+
+    /// All of the track points in the file, in order.
+    fn waypoints(&self) -> Vec<Waypoint> {
+        self.gpx
+            .tracks
+            .iter()
+            .flat_map(|track| &track.segments)
+            .flat_map(|segment| segment.points.iter().cloned())
+            .collect()
+    }
+}
+
+/// Name of the track in a file named `name` when there's no template for it,
+/// which leaves out `.gz` so that compressing doesn't change what's inside.
+fn default_track_name(name: &str) -> String {
+    name.strip_suffix(".gz").unwrap_or(name).to_owned()
+}
+
+/// Writes the tracks in `gpx` as routes, after any routes it already has.
+fn tracks_to_routes(gpx: &mut Gpx) {
+    gpx.routes
+        .extend(take(&mut gpx.tracks).into_iter().map(|track| {
+            Route {
+                name: track.name,
+                comment: track.comment,
+                description: track.description,
+                source: track.source,
+                links: track.links,
+                type_: track.type_,
+                points: track
+                    .segments
+                    .into_iter()
+                    .flat_map(|segment| segment.points)
+                    .collect(),
+                ..Route::default()
+            }
+        }));
+}
+
+/// `name` without the extension of the output format, or `.gz`.
+fn strip_extension<'a>(arguments: &Arguments, name: &'a str) -> &'a str {
+    let name = name.strip_suffix(".gz").unwrap_or(name);
+    name.strip_suffix(arguments.format.extension())
+        .unwrap_or(name)
+}
+
+/// Replaces characters that aren't allowed in filenames on common platforms.
+fn sanitize_filename(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Path to write the file called `name` to, in the output directory if there
+/// is one or next to the input file otherwise.
+fn output_path(input_file: &InputFile, name: &str) -> PathBuf {
+    match &input_file.output_dir {
+        Some(output_dir) => output_dir.join(name),
+        None => input_file.path.with_file_name(name),
+    }
+}
+
+/// Names a file with `--name-template`, or `default_template` if there isn't
+/// one.
+fn file_name(arguments: &Arguments, part: &PartName, default_template: &str) -> Result<String> {
+    let template = match &arguments.name_template {
+        Some(template) => template.clone(),
+        None => {
+            let mut template = default_template.to_owned();
+            if arguments.geocode_names {
+                template.push_str("_{start_place}-to-{end_place}");
+            }
+            if arguments.km_in_name {
+                template.push_str("_{start_km:04}-{end_km:04}km");
+            }
+            template.parse().map_err(|err: String| anyhow!(err))?
+        }
+    };
+
+    let pad = arguments
+        .pad
+        .unwrap_or_else(|| part.total.to_string().len().max(2));
+    let mut name = template.render(|placeholder| {
+        Ok(match part.value(placeholder, pad)? {
+            // places read as a single word in the file name
+            Value::Text(place) if placeholder.ends_with("_place") => {
+                Value::Text(sanitize_filename(&place).replace(char::is_whitespace, "-"))
+            }
+            Value::Text(text) => Value::Text(sanitize_filename(&text)),
+            value => value,
+        })
+    })?;
+    if let (true, Some(stem)) = (arguments.gzip, name.strip_suffix(".gz")) {
+        name.truncate(stem.len());
+    }
+    if let Some(stem) = name.strip_suffix(".gpx") {
+        name.truncate(stem.len());
+    }
+    let extension = arguments.format.extension();
+    if !name.ends_with(extension) {
+        name.push_str(extension);
+    }
+    if arguments.gzip {
+        name.push_str(".gz");
+    }
+    Ok(name)
+}
+
+/// Waypoints within this many meters of a file's route become course points
+/// in course formats, unless `--course-points` gives a different distance.
+const COURSE_POINT_METERS: f64 = 50.;
+
+/// The `landmarks` that are within `meters` of the route, each with the index
+/// of the nearest point of the route, in the order they're passed.
+fn nearby_waypoints<'a>(
+    landmarks: &'a [Waypoint],
+    waypoints: &[Waypoint],
+    meters: f64,
+) -> Vec<(usize, &'a Waypoint)> {
+    let mut nearby: Vec<(usize, &Waypoint)> = landmarks
+        .iter()
+        .filter_map(|landmark| {
+            let index = nearest_index(waypoints, &location(landmark))?;
+            let distance = location(landmark)
+                .haversine_distance_to(&location(&waypoints[index]))
+                .meters();
+            (distance <= meters).then_some((index, landmark))
+        })
+        .collect();
+    nearby.sort_by_key(|(index, _)| *index);
+    nearby
+}
+
+/// Speed in kilometers per hour used to give each point of a course a time,
+/// when the route doesn't have times and there's no `--speed`, and for
+/// `--synthesize-time`.
+const COURSE_SPEED: f64 = 15.;
+
+/// Times for riding `waypoints` at `speed_model` from `start`, with the
+/// distance to each point in `meters`, to the nearest second.
+fn estimated_times(
+    waypoints: &[Waypoint],
+    meters: &[f64],
+    speed_model: SpeedModel,
+    start: OffsetDateTime,
+) -> Vec<OffsetDateTime> {
+    let mut seconds = 0.;
+    let mut times = Vec::with_capacity(waypoints.len());
+    for (index, waypoint) in waypoints.iter().enumerate() {
+        if index > 0 {
+            let previous = &waypoints[index - 1];
+            let climb = match (previous.elevation, waypoint.elevation) {
+                (Some(a), Some(b)) => (b - a).max(0.),
+                _ => 0.,
+            };
+            seconds += speed_model.seconds(meters[index] - meters[index - 1], climb);
+        }
+        times.push(start + time::Duration::seconds(seconds.round() as i64));
+    }
+    times
+}
+
+/// Converts `part` to a course, with the distance along the course and a time
+/// for every point. If any of the points are missing a time, they're all given
+/// times estimated from the distance and climbing, starting at 2000-01-01.
+fn course(arguments: &Arguments, part: &Part) -> Result<Course> {
+    let waypoints = part.waypoints();
+    let meters = cumulative_meters(&waypoints, arguments.measure())?;
+
+    let times = match waypoints
+        .iter()
+        .map(|waypoint| waypoint.time.map(OffsetDateTime::from))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(times) => times,
+        None => estimated_times(
+            &waypoints,
+            &meters,
+            arguments.time_speed_model(),
+            OffsetDateTime::from_unix_timestamp(946684800)?,
+        ),
+    };
+
+    let track = waypoints
+        .iter()
+        .zip(meters)
+        .zip(times)
+        .map(|((waypoint, meters), time)| {
+            let point = waypoint.point();
+            TrackPoint {
+                latitude: point.y(),
+                longitude: point.x(),
+                elevation: waypoint.elevation,
+                meters,
+                time,
+            }
+        })
+        .collect();
+
+    let meters = arguments.course_points.unwrap_or(COURSE_POINT_METERS);
+    let course_points = nearby_waypoints(&part.gpx.waypoints, &waypoints, meters)
+        .into_iter()
+        .filter_map(|(index, waypoint)| {
+            Some(CoursePoint {
+                name: waypoint.name.clone().or_else(|| waypoint.symbol.clone())?,
+                index,
+                kind: CoursePointKind::of(waypoint),
+            })
+        })
+        .collect();
+
+    Ok(Course {
+        name: part.title(arguments),
+        track,
+        course_points,
+    })
+}
+
+/// A GeoJSON feature with the track of `part` as a line, and its name,
+/// number, distance, and climbing as properties.
+fn feature(arguments: &Arguments, part: &Part) -> Result<Json> {
+    let waypoints = part.waypoints();
+    let meters = route_length(&waypoints, arguments.measure())?;
+    let (gain, loss) = gain_and_loss(&waypoints, arguments.elevation_threshold());
+    let round = |value: f64, places: i32| {
+        let scale = 10f64.powi(places);
+        Json::Number((value * scale).round() / scale)
+    };
+
+    let coordinates = waypoints
+        .iter()
+        .map(|waypoint| {
+            let point = waypoint.point();
+            let mut position = vec![Json::Number(point.x()), Json::Number(point.y())];
+            position.extend(waypoint.elevation.map(Json::Number));
+            Json::Array(position)
+        })
+        .collect();
+
+    Ok(Json::object([
+        ("type", Json::String("Feature".to_owned())),
+        (
+            "properties",
+            Json::object([
+                ("name", Json::String(part.title(arguments))),
+                ("index", Json::Number((part.index + 1) as f64)),
+                ("distance_km", round(meters / 1000., 3)),
+                ("gain_m", round(gain, 1)),
+                ("loss_m", round(loss, 1)),
+            ]),
+        ),
+        (
+            "geometry",
+            Json::object([
+                ("type", Json::String("LineString".to_owned())),
+                ("coordinates", Json::Array(coordinates)),
+            ]),
+        ),
+    ]))
+}
+
+/// Adds links to the previous and next files to the metadata of each file for
+/// `--link-parts`. The links are relative, since the files are written next
+/// to each other.
+fn link_parts(arguments: &Arguments, parts: &mut [Part]) {
+    if !arguments.link_parts {
+        return;
+    }
+
+    let names: Vec<String> = parts
+        .iter()
+        .map(|part| percent_encode(&part.name))
+        .collect();
+    for (index, part) in parts.iter_mut().enumerate() {
+        let links = &mut part.gpx.metadata.get_or_insert_with(Default::default).links;
+        if let Some(previous) = index.checked_sub(1) {
+            links.push(Link {
+                href: names[previous].clone(),
+                text: Some("Previous part".to_owned()),
+                type_: None,
+            });
+        }
+        if let Some(next) = names.get(index + 1) {
+            links.push(Link {
+                href: next.clone(),
+                text: Some("Next part".to_owned()),
+                type_: None,
+            });
+        }
+    }
+}
+
+/// Escapes `name` to be used as a relative URL.
+fn percent_encode(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+/// Display color for the file at `index` with `--cycle-colors`, cycling
+/// through the colors so that neighbouring files are different.
+fn display_color(arguments: &Arguments, index: usize) -> Option<&'static str> {
+    arguments
+        .cycle_colors
+        .then(|| DISPLAY_COLORS[index % DISPLAY_COLORS.len()])
+}
+
+/// Describes the track in a file for `--describe`.
+fn description(arguments: &Arguments, part: &PartName) -> Result<Option<String>> {
+    if !arguments.describe {
+        return Ok(None);
+    }
+
+    let template: Template = DESCRIPTION_TEMPLATE
+        .parse()
+        .map_err(|err: String| anyhow!(err))?;
+    template
+        .render(|placeholder| part.value(placeholder, 0))
+        .map(Some)
+}
+
+/// Template used for `--describe`.
+const DESCRIPTION_TEMPLATE: &str = "Part {index} of {total}: {km:.1} km, {gain:,} m up, \
+    {loss:,} m down, from {start} to {end}";
+
+/// Names the track in a file with `--track-name-template`, if there is one.
+/// Unlike file names, `{index}` isn't padded.
+fn track_name_from_template(arguments: &Arguments, part: &PartName) -> Result<Option<String>> {
+    arguments
+        .track_name_template
+        .as_ref()
+        .map(|template| template.render(|placeholder| part.value(placeholder, 0)))
+        .transpose()
+}
+
+/// Date of the first waypoint, in the time zone from `--timezone`.
+fn date(arguments: &Arguments, waypoints: &[Waypoint]) -> Option<Date> {
+    let time = OffsetDateTime::from(waypoints.first()?.time?);
+    Some(
+        time.to_offset(arguments.timezone.unwrap_or(UtcOffset::UTC))
+            .date(),
+    )
+}
+
+/// Template used to name files when there's no `--name-template`.
+const DEFAULT_NAME_TEMPLATE: &str = "{basename}_{index}";
+
+/// Everything that a file can be named after with `--name-template`.
+struct PartName<'a> {
+    basename: &'a str,
+    index: usize,
+    total: usize,
+    start_meters: f64,
+    end_meters: f64,
+    date: Option<Date>,
+    track: Option<&'a str>,
+    waypoints: &'a [Waypoint],
+    geocoder: Option<&'a Geocoder>,
+    /// Threshold for the climbing and descent, from `--elevation-threshold`.
+    elevation_threshold: f64,
+}
+
+impl PartName<'_> {
+    fn value(&self, placeholder: &str, pad: usize) -> Result<Value> {
+        Ok(match placeholder {
+            "basename" => Value::Text(self.basename.to_owned()),
+            "index" => Value::Padded(self.index + 1, pad),
+            "total" => Value::Integer(self.total),
+            "start_km" => Value::Number(self.start_meters / 1000.),
+            "end_km" => Value::Number(self.end_meters / 1000.),
+            "km" => Value::Number((self.end_meters - self.start_meters) / 1000.),
+            "gain" => Value::Number(gain_and_loss(self.waypoints, self.elevation_threshold).0),
+            "loss" => Value::Number(gain_and_loss(self.waypoints, self.elevation_threshold).1),
+            "start" => Value::Text(coordinates(self.waypoints.first())),
+            "end" => Value::Text(coordinates(self.waypoints.last())),
+            "date" => Value::Text(
+                self.date
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "file {} has no timestamp for {{date}} in the template",
+                            self.index + 1
+                        )
+                    })?
+                    .to_string(),
+            ),
+            "track" => Value::Text(self.track.unwrap_or_default().to_owned()),
+            "start_place" => Value::Text(self.place(self.waypoints.first())?),
+            "end_place" => Value::Text(self.place(self.waypoints.last())?),
+            _ => bail!("unknown placeholder {{{placeholder}}} in the template"),
+        })
+    }
+
+    /// Name of the place at `waypoint`.
+    fn place(&self, waypoint: Option<&Waypoint>) -> Result<String> {
+        let geocoder = self
+            .geocoder
+            .ok_or_else(|| anyhow!("--geocode-names is needed for places in the template"))?;
+        Ok(waypoint
+            .map(|waypoint| geocoder.place(waypoint))
+            .unwrap_or_default())
+    }
+}
+
+/// Writes each of `parts` to the output directory, returning their paths.
+/// Unless `--force` is used, nothing is written if any of the files already
+/// exist, so that a run never leaves a mix of old and new files behind.
+fn write_files(
+    arguments: &Arguments,
+    input_file: &InputFile,
+    parts: &[Part],
+    progress: &ProgressBar,
+) -> Result<Vec<PathBuf>> {
+    let outputs: Vec<PathBuf> = parts
+        .iter()
+        .map(|part| output_path(input_file, &part.name))
+        .collect();
+
+    if !arguments.force {
+        if let Some(existing) = outputs.iter().find(|output| output.exists()) {
+            bail!(
+                "{} already exists, use --force to overwrite it",
+                existing.display()
+            );
+        }
+    }
+
+    for (index, (part, output)) in parts.iter().zip(&outputs).enumerate() {
+        progress.show("writing", index, parts.len(), || {
+            format!("{index} of {} files", parts.len())
+        });
+        let mut file = create_output(arguments, output, "file")?;
+        let mut bytes = part.encode(arguments)?;
+        if arguments.gzip {
+            bytes = gzip(&bytes);
+        }
+        file.write_all(&bytes)?;
+        verbose!("wrote {}, {} bytes", output.display(), bytes.len());
+    }
+
+    Ok(outputs)
+}
+
+/// Writes an SVG elevation profile for each of `parts`, named after its file.
+fn write_profiles(arguments: &Arguments, input_file: &InputFile, parts: &[Part]) -> Result<()> {
+    for part in parts {
+        let waypoints = part.waypoints();
+        let meters = cumulative_meters(&waypoints, arguments.measure())?;
+        let points: Vec<(f64, f64)> = waypoints
+            .iter()
+            .zip(&meters)
+            .filter_map(|(waypoint, meters)| {
+                Some((part.start_meters + meters, waypoint.elevation?))
+            })
+            .collect();
+        let length = meters.last().copied().unwrap_or_default();
+        let (gain, loss) = gain_and_loss(&waypoints, arguments.elevation_threshold());
+        let title = part.title(arguments);
+        let details = format!("{:.1} km, {gain:.0} m up, {loss:.0} m down", length / 1000.);
+        let profile = Profile {
+            title: &title,
+            details: &details,
+            start_meters: part.start_meters,
+            end_meters: part.start_meters + length,
+            points: &points,
+        };
+
+        let path = output_path(
+            input_file,
+            &format!("{}.svg", strip_extension(arguments, &part.name)),
+        );
+        let file = create_output(arguments, &path, "profile")?;
+        write_profile(BufWriter::new(file), &profile)
+            .with_context(|| format!("failed to write profile {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Draws all of `parts` on a map in an SVG image at `path`.
+fn write_preview(arguments: &Arguments, path: &Path, basename: &str, parts: &[Part]) -> Result<()> {
+    let titles: Vec<String> = parts.iter().map(|part| part.title(arguments)).collect();
+    let waypoints: Vec<Vec<Waypoint>> = parts.iter().map(Part::waypoints).collect();
+    let lines: Vec<Line> = parts
+        .iter()
+        .zip(&titles)
+        .zip(&waypoints)
+        .map(|((part, title), waypoints)| Line {
+            name: title,
+            index: part.index,
+            waypoints,
+        })
+        .collect();
+
+    let file = create_output(arguments, path, "preview")?;
+    write_map(BufWriter::new(file), basename, &lines)
+        .with_context(|| format!("failed to write preview {}", path.display()))
+}
+
+/// Writes all of `parts` into a single zip archive at `path`.
+fn write_zip_file(arguments: &Arguments, path: &Path, parts: &[Part]) -> Result<()> {
+    let entries = parts
+        .iter()
+        .map(|part| Ok((part.name.clone(), part.encode(arguments)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let file = create_output(arguments, path, "zip archive")?;
+    let mut writer = BufWriter::new(file);
+    write_zip(&mut writer, &entries)
+        .and_then(|()| writer.flush())
+        .with_context(|| format!("failed to write zip archive {}", path.display()))
+}
+
+/// Prints the files that would be written, without writing them.
+fn print_dry_run(arguments: &Arguments, input_file: &InputFile, parts: &[Part]) -> Result<()> {
+    for part in parts {
+        let waypoints = part.waypoints();
+        let estimate = match estimated_seconds(arguments, &waypoints)? {
+            Some(seconds) => format!(", {} estimated", format_hours(seconds)),
+            None => String::new(),
+        };
+        println!(
+            "{}: {} points, {:.1} km{estimate}",
+            output_path(input_file, &part.name).display(),
+            waypoints.len(),
+            route_length(&waypoints, arguments.measure())? / 1000.,
+        );
+    }
+    Ok(())
+}
+
+/// Prints where each route would be cut with `--plan`, along with the closest
+/// of the `landmarks` that has a name.
+fn print_plan(route_plans: &[RoutePlan], landmarks: &[Waypoint]) {
+    let landmarks: Vec<(Location, &str)> = landmarks
+        .iter()
+        .filter_map(|landmark| Some((location(landmark), landmark.name.as_deref()?)))
+        .collect();
+    for (number, route_plan) in route_plans.iter().enumerate() {
+        if route_plans.len() > 1 {
+            println!("route {}:", number + 1);
+        }
+        if route_plan.cuts.is_empty() {
+            println!("no cuts, the route fits in a single file");
+        }
+        for (cut_number, cut) in route_plan.cuts.iter().enumerate() {
+            let (latitude, longitude) = cut.position.unwrap_or_default();
+            let cut_location = Location::new(latitude, longitude);
+            let nearest = landmarks
+                .iter()
+                .map(|(location, name)| {
+                    (cut_location.haversine_distance_to(location).meters(), name)
+                })
+                .min_by(|(a, _), (b, _)| a.total_cmp(b));
+            let near = match nearest {
+                Some((meters, name)) => format!(", near {name} ({:.1} km away)", meters / 1000.),
+                None => String::new(),
+            };
+            println!(
+                "cut {}: point {}, {:.1} km, {latitude:.5}, {longitude:.5}{near}",
+                cut_number + 1,
+                cut.index.unwrap_or_default(),
+                cut.km.unwrap_or_default(),
+            );
+        }
+    }
+}
+
+/// Estimated seconds to ride `waypoints` with `--estimate`, from the distance
+/// and climbing of each leg, or `None` without it.
+fn estimated_seconds(arguments: &Arguments, waypoints: &[Waypoint]) -> Result<Option<f64>> {
+    let (true, Some(speed_model)) = (arguments.estimate, arguments.speed_model()) else {
+        return Ok(None);
+    };
+    let measure = arguments.measure();
+    let mut seconds = 0.;
+    let mut last_elevation: Option<f64> = None;
+    for (index, waypoint) in waypoints.iter().enumerate() {
+        let climb = match (waypoint.elevation, last_elevation) {
+            (Some(elevation), Some(last_elevation)) => (elevation - last_elevation).max(0.),
+            _ => 0.,
+        };
+        last_elevation = waypoint.elevation.or(last_elevation);
+        if index > 0 {
+            let meters = measure.distance(&waypoints[index - 1], waypoint)?;
+            seconds += speed_model.seconds(meters, climb);
+        }
+    }
+    Ok(Some(seconds))
+}
+
+/// Formats a number of seconds as hours and minutes, like `5:07`.
+fn format_hours(seconds: f64) -> String {
+    let minutes = (seconds / 60.).round() as u64;
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+/// Reads back the files that were `written` for `--verify`, and checks that
+/// each one has the points of its route in `originals` up to where the route
+/// was cut according to `route_plans`, and nothing else, and that none of the
+/// route is missing.
+fn verify(
+    arguments: &Arguments,
+    written: &[PathBuf],
+    originals: &[Vec<Waypoint>],
+    route_plans: &[RoutePlan],
+) -> Result<()> {
+    let mut files = written.iter();
+    for (original, route_plan) in originals.iter().zip(route_plans) {
+        let last_indices = route_plan
+            .cuts
+            .iter()
+            .map(|cut| cut.index.unwrap_or_default())
+            .chain([original.len().saturating_sub(1)]);
+        // the points of the route from all of the files, without the points
+        // repeated from the file before
+        let mut joined = Vec::with_capacity(original.len());
+        for last_index in last_indices {
+            let path = files
+                .next()
+                .ok_or_else(|| anyhow!("verify failed: fewer files were written than expected"))?;
+            let mut gpx = read_bytes(path).and_then(|input| parse_input(&input, None))?;
+            routes_to_tracks(&mut gpx);
+            let points: Vec<Waypoint> = take(&mut gpx.tracks)
+                .into_iter()
+                .flat_map(|track| track.segments)
+                .flat_map(|segment| segment.points)
+                .collect();
+
+            let Some(first_index) = (last_index + 1).checked_sub(points.len()) else {
+                bail!(
+                    "verify failed: {} has more points than the route has up to where it ends",
+                    path.display()
+                );
+            };
+            if first_index > joined.len() {
+                bail!(
+                    "verify failed: points {} to {} of the route are missing before {}",
+                    joined.len(),
+                    first_index - 1,
+                    path.display()
+                );
+            }
+            for (offset, point) in points.iter().enumerate() {
+                if *point != original[first_index + offset] {
+                    bail!(
+                        "verify failed: point {offset} of {} is different from point {} of the \
+                         route",
+                        path.display(),
+                        first_index + offset
+                    );
+                }
+            }
+            let repeated = joined.len() - first_index;
+            joined.extend(points.into_iter().skip(repeated));
+        }
+
+        if joined.len() != original.len() {
+            bail!(
+                "verify failed: the files have {} of the {} points of the route",
+                joined.len(),
+                original.len()
+            );
+        }
+        let measure = arguments.measure();
+        let (joined_meters, original_meters) = (
+            route_length(&joined, measure)?,
+            route_length(original, measure)?,
+        );
+        if (joined_meters - original_meters).abs() > 1e-6 * original_meters.max(1.) {
+            bail!(
+                "verify failed: the files cover {:.3} km of the {:.3} km route",
+                joined_meters / 1000.,
+                original_meters / 1000.
+            );
+        }
+    }
+    if files.next().is_some() {
+        bail!("verify failed: more files were written than expected");
+    }
+    info!("verified {} files against the route", written.len());
+    Ok(())
+}
+
+/// Prints a table of the files that were written, with where each one starts
+/// and ends, to check the split without opening them.
+fn print_summary(arguments: &Arguments, parts: &[Part]) -> Result<()> {
+    if arguments.quiet {
+        return Ok(());
+    }
+    let mut header = vec!["#", "file", "points", "dupes", "km", "gain m", "loss m"];
+    if arguments.estimate {
+        header.push("est time");
+    }
+    header.extend(["start", "end"]);
+    let mut rows = Vec::new();
+    let mut exact_duplicates = 0;
+    let mut near_duplicates = 0;
+    for part in parts {
+        let waypoints = part.waypoints();
+        let (gain, loss) = gain_and_loss(&waypoints, arguments.elevation_threshold());
+        let (exact, near) = duplicate_counts(&waypoints);
+        exact_duplicates += exact;
+        near_duplicates += near;
+        let mut row = vec![
+            (part.index + 1).to_string(),
+            part.name.clone(),
+            waypoints.len().to_string(),
+            (exact + near).to_string(),
+            format!(
+                "{:.1}",
+                route_length(&waypoints, arguments.measure())? / 1000.
+            ),
+            format!("{gain:.0}"),
+            format!("{loss:.0}"),
+        ];
+        row.extend(estimated_seconds(arguments, &waypoints)?.map(format_hours));
+        row.extend([
+            coordinates(waypoints.first()),
+            coordinates(waypoints.last()),
+        ]);
+        rows.push(row);
+    }
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .fold(header[column].len(), usize::max)
+        })
+        .collect();
+    let names = header.clone();
+    let header: Vec<String> = header.into_iter().map(str::to_owned).collect();
+    for row in [&header].into_iter().chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .zip(&names)
+            .map(|((cell, width), name)| match *name {
+                // the file name and coordinates are text, the rest are numbers
+                "file" | "start" | "end" => format!("{cell:<width$}"),
+                _ => format!("{cell:>width$}"),
+            })
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+    if exact_duplicates > 0 {
+        info!(
+            "{exact_duplicates} points are duplicates of the point before them, use --dedupe \
+             to remove them"
+        );
+    }
+    if near_duplicates > 0 {
+        info!(
+            "{near_duplicates} points are within a meter of the point before them, use \
+             --dedupe=1m to remove them"
+        );
+    }
+    Ok(())
+}
+
+/// Writes the report for `--report` to `path`, describing the files that were
+/// written for `parts`.
+fn write_report(
+    arguments: &Arguments,
+    input_file: &InputFile,
+    parts: &[Part],
+    path: &Path,
+) -> Result<()> {
+    let mut reported = Vec::new();
+    for part in parts {
+        let file = match arguments.zip {
+            Some(_) => part.name.clone(),
+            None => output_path(input_file, &part.name).display().to_string(),
+        };
+        // the files are encoded again rather than read back, since they might
+        // be in a zip archive
+        let mut bytes = part.encode(arguments)?;
+        if arguments.gzip {
+            bytes = gzip(&bytes);
+        }
+        let waypoints = part.waypoints();
+        let bounds = match bounds(&waypoints) {
+            Some((min, max)) => Json::object([
+                ("min_latitude", Json::Number(min.y())),
+                ("min_longitude", Json::Number(min.x())),
+                ("max_latitude", Json::Number(max.y())),
+                ("max_longitude", Json::Number(max.x())),
+            ]),
+            None => Json::Null,
+        };
+        let km = route_length(&waypoints, arguments.measure())? / 1000.;
+        let (gain, loss) = gain_and_loss(&waypoints, arguments.elevation_threshold());
+        let (exact, near) = duplicate_counts(&waypoints);
+        let estimated_hours = match estimated_seconds(arguments, &waypoints)? {
+            Some(seconds) => Json::Number((seconds / 36.).round() / 100.),
+            None => Json::Null,
+        };
+        reported.push(Json::object([
+            ("index", Json::Number((part.index + 1) as f64)),
+            ("file", Json::String(file)),
+            ("points", Json::Number(waypoints.len() as f64)),
+            (
+                "duplicates",
+                Json::object([
+                    ("exact", Json::Number(exact as f64)),
+                    ("near", Json::Number(near as f64)),
+                ]),
+            ),
+            ("km", Json::Number((km * 1000.).round() / 1000.)),
+            ("gain_m", Json::Number(gain.round())),
+            ("loss_m", Json::Number(loss.round())),
+            ("estimated_hours", estimated_hours),
+            ("bounds", bounds),
+            ("crc32", Json::String(format!("{:08x}", crc32(&bytes)))),
+        ]));
+    }
+
+    let report = Json::object([
+        ("input", Json::String(input_file.path.display().to_string())),
+        (
+            "arguments",
+            Json::Array(std::env::args().skip(1).map(Json::String).collect()),
+        ),
+        ("parts", Json::Array(reported)),
+    ]);
+    fs::write(path, report.to_pretty_string())
+        .with_context(|| format!("failed to write report {}", path.display()))
+}
+
+/// Southwest and northeast corners of the smallest box around `waypoints`, or
+/// `None` if there aren't any.
+fn bounds(waypoints: &[Waypoint]) -> Option<(Point, Point)> {
+    let first = waypoints.first()?.point();
+    Some(
+        waypoints
+            .iter()
+            .fold((first, first), |(min, max), waypoint| {
+                let point = waypoint.point();
+                (
+                    Point::new(min.x().min(point.x()), min.y().min(point.y())),
+                    Point::new(max.x().max(point.x()), max.y().max(point.y())),
+                )
+            }),
+    )
+}
+
+/// Writes all of `parts` into a single file at `path`, for `--overview`.
+fn write_overview(
+    arguments: &Arguments,
+    path: &Path,
+    basename: &str,
+    parts: &[Part],
+) -> Result<()> {
+    let mut bytes = Vec::new();
+    match arguments.format {
+        Format::Kml => {
+            let titles: Vec<String> = parts.iter().map(|part| part.title(arguments)).collect();
+            let waypoints: Vec<Vec<Waypoint>> = parts.iter().map(Part::waypoints).collect();
+            let lines: Vec<Line> = parts
+                .iter()
+                .zip(&titles)
+                .zip(&waypoints)
+                .map(|((part, title), waypoints)| Line {
+                    name: title,
+                    index: part.index,
+                    waypoints,
+                })
+                .collect();
+            write_kml(&mut bytes, basename, &lines)?;
+        }
+        Format::Geojson => {
+            let features = parts
+                .iter()
+                .map(|part| feature(arguments, part))
+                .collect::<Result<_>>()?;
+            let collection = Json::object([
+                ("type", Json::String("FeatureCollection".to_owned())),
+                ("features", Json::Array(features)),
+            ]);
+            bytes = collection.to_string().into_bytes();
+        }
+        _ => bail!("--overview only supports --format kml and geojson"),
+    }
+    if arguments.gzip {
+        bytes = gzip(&bytes);
+    }
+
+    let mut file = create_output(arguments, path, "file")?;
+    file.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Creates the file at `path`, which can only already exist with `--force`.
+/// `what` describes the file in errors.
+fn create_output(arguments: &Arguments, path: &Path, what: &str) -> Result<File> {
+    if arguments.force {
+        File::create(path)
+    } else {
+        File::create_new(path)
+    }
+    .with_context(|| format!("failed to create {what} {}", path.display()))
+}
+
+/// Finds numbered files from a previous run that weren't overwritten by this
+/// one, such as when the route is split into fewer files than before. Only
+/// files named `basename` followed by a number padded to `pad` digits are
+/// counted, so that other files that happen to start with the same name are
+/// never touched. They're removed with `--remove-stale`, and otherwise there's
+/// a warning about them.
+fn remove_stale_files(
+    arguments: &Arguments,
+    input: &Path,
+    basename: &str,
+    pad: usize,
+    written: &[PathBuf],
+) -> Result<()> {
+    let directory = match written.first().and_then(|output| output.parent()) {
+        Some(directory) if directory.as_os_str().is_empty() => Path::new("."),
+        Some(directory) => directory,
+        None => return Ok(()),
+    };
+
+    let prefix = format!("{basename}_");
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let number = name
+            .strip_prefix(&prefix)
+            .map(|rest| rest.strip_suffix(".gz").unwrap_or(rest))
+            .and_then(|rest| rest.strip_suffix(arguments.format.extension()));
+        let numbered = number.is_some_and(|number| {
+            number.len() == pad && number.chars().all(|c| c.is_ascii_digit())
+        });
+        if !numbered
+            || written
+                .iter()
+                .any(|output| output.file_name() == path.file_name())
+            || path.file_name() == input.file_name()
+        {
+            continue;
+        }
+
+        if arguments.remove_stale {
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove stale file {}", path.display()))?;
+            info!("removed stale file {}", path.display());
+        } else {
+            info!(
+                "warning: {} is left over from a previous run, use --remove-stale to remove it",
+                path.display()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn encode(gpx: &Gpx) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    gpx::write(gpx, &mut bytes)?;
+    Ok(bytes)
+}
+
+fn get_track(gpx: &mut Gpx) -> Result<&mut Track> {
+    gpx.tracks
+        .get_mut(0)
+        .ok_or_else(|| anyhow!("gpx file missing track 0"))
+}
+
+fn get_segment(gpx: &mut Gpx) -> Result<&mut TrackSegment> {
+    get_track(gpx)?
+        .segments
+        .get_mut(0)
+        .ok_or_else(|| anyhow!("gpx track 0 missing segment 0"))
+}
+
+/// File formats that the parts can be written in.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Gpx,
+    /// Garmin TCX courses, which older Edge devices navigate more reliably
+    /// than GPX tracks.
+    Tcx,
+    /// Binary FIT courses, the native format of Garmin and Wahoo devices.
+    Fit,
+    /// KML, for looking at where the splits land in Google Earth.
+    Kml,
+    /// GeoJSON line features, for web maps and GIS tools.
+    Geojson,
+    /// A row for each point, with its position, elevation, time, and distance
+    /// from the start of the file, for spreadsheets and scripts.
+    Csv,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Gpx => ".gpx",
+            Format::Tcx => ".tcx",
+            Format::Fit => ".fit",
+            Format::Kml => ".kml",
+            Format::Geojson => ".geojson",
+            Format::Csv => ".csv",
+        }
+    }
+}
+
+/// What to do when a file has more than one of something, where only one can
+/// be split.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Multiple {
+    /// Join them end to end and split them as one.
+    Merge,
+    /// Split the first and leave out the rest.
+    First,
+    /// Split each of them on its own, numbering the files for each.
+    Each,
+    /// Stop with an error.
+    Error,
+}
+
+/// Total distance in meters between each consecutive pair of waypoints.
+fn route_length(waypoints: &[Waypoint], measure: Measure) -> Result<f64> {
+    waypoints
+        .windows(2)
+        .map(|pair| Ok(measure.distance(&pair[0], &pair[1])?))
+        .sum()
+}
+
+/// Index of the waypoint closest to `location`, or `None` if there are no
+/// waypoints.
+fn nearest_index(waypoints: &[Waypoint], location: &Location) -> Option<usize> {
+    waypoints
+        .iter()
+        .map(|waypoint| {
+            location
+                .haversine_distance_to(&self::location(waypoint))
+                .meters()
+        })
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+/// Indices of the waypoints that are the last of their date, in the time zone
+/// given by `offset`.
+fn date_change_indices(waypoints: &[Waypoint], offset: UtcOffset) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for (index, pair) in waypoints.windows(2).enumerate() {
+        if time(&pair[0])?.to_offset(offset).date() != time(&pair[1])?.to_offset(offset).date() {
+            indices.push(index);
+        }
+    }
+    Ok(indices)
+}
+
+/// Indices of the waypoints closest to `towns`, where each one is at least
+/// `min_meters` along the route from the previous one. Towns further than
+/// `radius_meters` from the route are ignored.
+fn town_indices(
+    waypoints: &[Waypoint],
+    towns: &[Waypoint],
+    min_meters: f64,
+    radius_meters: f64,
+) -> Result<Vec<usize>> {
+    let mut town_indices: Vec<usize> = towns
+        .iter()
+        .map(location)
+        .filter_map(|town| {
+            let index = nearest_index(waypoints, &town)?;
+            let meters = town
+                .haversine_distance_to(&location(&waypoints[index]))
+                .meters();
+            (meters <= radius_meters).then_some(index)
+        })
+        .collect();
+    town_indices.sort();
+
+    let route_meters = cumulative_meters(waypoints, Measure::Horizontal)?;
+    let mut indices = Vec::new();
+    let mut last_cut_meters = 0.;
+    for index in town_indices {
+        if route_meters[index] - last_cut_meters >= min_meters {
+            indices.push(index);
+            last_cut_meters = route_meters[index];
+        }
+    }
+    Ok(indices)
+}
+
+/// Marks each waypoint that's partway up a climb, where a climb is any stretch
+/// at least `min_meters` long with an average `grade` or steeper. The top of
+/// each climb isn't marked. Waypoints without an elevation are never part of
+/// a climb.
+fn climbing_waypoints(waypoints: &[Waypoint], grade: f64, min_meters: f64) -> Result<Vec<bool>> {
+    let route_meters = cumulative_meters(waypoints, Measure::Horizontal)?;
+    let mut climbing = vec![false; waypoints.len()];
+
+    // for each waypoint, find the first waypoint at least `min_meters` ahead and
+    // check the average grade between them
+    let mut end = 0;
+    for start in 0..waypoints.len() {
+        end = end.max(start);
+        while end < waypoints.len() && route_meters[end] - route_meters[start] < min_meters {
+            end += 1;
+        }
+        if end == waypoints.len() {
+            break;
+        }
+
+        let (Some(start_elevation), Some(end_elevation)) =
+            (waypoints[start].elevation, waypoints[end].elevation)
+        else {
+            continue;
+        };
+
+        let climb = end_elevation - start_elevation;
+        if climb / (route_meters[end] - route_meters[start]) >= grade {
+            climbing[start..end].fill(true);
+        }
+    }
+
+    Ok(climbing)
+}
+
+/// Indices of the waypoints that are followed by more than `gap_seconds`
+/// without another waypoint.
+fn gap_indices(waypoints: &[Waypoint], gap_seconds: f64) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for (index, pair) in waypoints.windows(2).enumerate() {
+        if (time(&pair[1])? - time(&pair[0])?).as_seconds_f64() > gap_seconds {
+            indices.push(index);
+        }
+    }
+    Ok(indices)
+}
+
+/// Indices of the waypoints that are followed by a waypoint more than
+/// `jump_meters` away.
+fn jump_indices(waypoints: &[Waypoint], jump_meters: f64) -> Result<Vec<usize>> {
+    let mut indices = Vec::new();
+    for (index, pair) in waypoints.windows(2).enumerate() {
+        if Measure::Horizontal.distance(&pair[0], &pair[1])? > jump_meters {
+            indices.push(index);
+        }
+    }
+    Ok(indices)
+}
+
+/// Distance in meters along the route to each waypoint.
+fn cumulative_meters(waypoints: &[Waypoint], measure: Measure) -> Result<Vec<f64>> {
+    let mut meters = Vec::with_capacity(waypoints.len());
+    let mut total = 0.;
+    for (index, waypoint) in waypoints.iter().enumerate() {
+        if index > 0 {
+            total += measure.distance(&waypoints[index - 1], waypoint)?;
+        }
+        meters.push(total);
+    }
+    Ok(meters)
+}
+
+/// Indices of the waypoints that are followed by a waypoint in a different
+/// tile of a grid with `grid_degrees` sized tiles.
+fn tile_change_indices(waypoints: &[Waypoint], grid_degrees: f64) -> Vec<usize> {
+    let tile = |waypoint: &Waypoint| {
+        let point = waypoint.point();
+        (
+            (point.y() / grid_degrees).floor() as i64,
+            (point.x() / grid_degrees).floor() as i64,
+        )
+    };
+
+    waypoints
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| tile(&pair[0]) != tile(&pair[1]))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Index of the waypoint furthest from the first waypoint, or `None` if the
+/// route doesn't come back at least halfway toward its start from there.
+fn turnaround_index(waypoints: &[Waypoint]) -> Option<usize> {
+    let start = location(waypoints.first()?);
+    let meters: Vec<f64> = waypoints
+        .iter()
+        .map(|waypoint| start.haversine_distance_to(&location(waypoint)).meters())
+        .collect();
+    let (index, furthest) = meters
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+    let end = meters[meters.len() - 1];
+    (*furthest > 0. && end <= furthest / 2.).then_some(index)
+}
+
+/// Marks each waypoint that's at a junction, where the heading turns by at
+/// least `angle_degrees` or one of `landmarks` is within `radius_meters`.
+fn junction_waypoints(
+    waypoints: &[Waypoint],
+    landmarks: &[Location],
+    angle_degrees: f64,
+    radius_meters: f64,
+) -> Vec<bool> {
+    let mut junctions: Vec<bool> = waypoints
+        .iter()
+        .map(|waypoint| nearest_meters(landmarks, &location(waypoint)) <= radius_meters)
+        .collect();
+
+    for (index, triple) in waypoints.windows(3).enumerate() {
+        // repeated points have no heading, so they can't be a turn
+        if triple[0].point() == triple[1].point() || triple[1].point() == triple[2].point() {
+            continue;
+        }
+        let turn =
+            (bearing(&triple[1], &triple[2]) - bearing(&triple[0], &triple[1])).rem_euclid(360.);
+        if turn.min(360. - turn) >= angle_degrees {
+            junctions[index + 1] = true;
+        }
+    }
+
+    junctions
+}
+
+/// Initial bearing from `a` to `b` in degrees clockwise from north.
+fn bearing(a: &Waypoint, b: &Waypoint) -> f64 {
+    let (a, b) = (a.point(), b.point());
+    let (latitude_a, latitude_b) = (a.y().to_radians(), b.y().to_radians());
+    let delta_longitude = (b.x() - a.x()).to_radians();
+
+    let y = delta_longitude.sin() * latitude_b.cos();
+    let x = latitude_a.cos() * latitude_b.sin()
+        - latitude_a.sin() * latitude_b.cos() * delta_longitude.cos();
+    y.atan2(x).to_degrees()
+}
+
+/// Total meters climbed and descended over `waypoints`, skipping over any
+/// without an elevation. Changes are only counted once they add up to at
+/// least `threshold` from the last elevation that was counted, so that noise
+/// doesn't inflate the totals.
+fn gain_and_loss(waypoints: &[Waypoint], threshold: f64) -> (f64, f64) {
+    let mut gain = 0.;
+    let mut loss = 0.;
+    let mut counted_elevation: Option<f64> = None;
+    for elevation in waypoints.iter().filter_map(|waypoint| waypoint.elevation) {
+        let Some(counted) = counted_elevation else {
+            counted_elevation = Some(elevation);
+            continue;
+        };
+        if elevation - counted >= threshold {
+            gain += elevation - counted;
+            counted_elevation = Some(elevation);
+        } else if counted - elevation >= threshold {
+            loss += counted - elevation;
+            counted_elevation = Some(elevation);
+        }
+    }
+    (gain, loss)
+}
+
+/// Distance in meters within which a point counts as a near duplicate of the
+/// point before it.
+const NEAR_DUPLICATE_METERS: f64 = 1.;
+
+/// Numbers of points in `waypoints` at exactly the same position as the point
+/// before them, and within `NEAR_DUPLICATE_METERS` of it.
+fn duplicate_counts(waypoints: &[Waypoint]) -> (usize, usize) {
+    let mut exact = 0;
+    let mut near = 0;
+    for pair in waypoints.windows(2) {
+        if pair[0].point() == pair[1].point() {
+            exact += 1;
+        } else if location(&pair[0])
+            .haversine_distance_to(&location(&pair[1]))
+            .meters()
+            < NEAR_DUPLICATE_METERS
+        {
+            near += 1;
+        }
+    }
+    (exact, near)
+}
+
+/// Leaves out the points at the same position as the last point that was
+/// kept, or closer to it than `tolerance` meters if there is one, for
+/// `--dedupe`.
+fn dedupe(waypoints: &mut Vec<Waypoint>, tolerance: Option<f64>) {
+    let mut last_kept: Option<Point> = None;
+    waypoints.retain(|waypoint| {
+        let here = waypoint.point();
+        let keep = last_kept.is_none_or(|last_kept| match tolerance {
+            Some(tolerance) => {
+                Location::new(last_kept.y(), last_kept.x())
+                    .haversine_distance_to(&Location::new(here.y(), here.x()))
+                    .meters()
+                    >= tolerance
+            }
+            None => last_kept != here,
+        });
+        if keep {
+            last_kept = Some(here);
+        }
+        keep
+    });
+}
+
+/// Latitude and longitude of `waypoint`, like `45.00100, -109.99950`.
+fn coordinates(waypoint: Option<&Waypoint>) -> String {
+    waypoint.map_or_else(String::new, |waypoint| {
+        let point = waypoint.point();
+        format!("{:.5}, {:.5}", point.y(), point.x())
+    })
+}
+
+/// Distance in meters from `target` to the closest of `locations`.
+fn nearest_meters(locations: &[Location], target: &Location) -> f64 {
+    locations
+        .iter()
+        .map(|location| target.haversine_distance_to(location).meters())
+        .fold(f64::INFINITY, f64::min)
+}
+
+fn location(waypoint: &Waypoint) -> Location {
+    let point = waypoint.point();
+    Location::new(point.y(), point.x())
+}
+
+fn parse_location(value: &str) -> Result<Location, String> {
+    let (latitude, longitude) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected latitude,longitude but found {value}"))?;
+    let latitude: f64 = latitude.trim().parse().map_err(|err| format!("{err}"))?;
+    let longitude: f64 = longitude.trim().parse().map_err(|err| format!("{err}"))?;
+    Ok(Location::new(latitude, longitude))
+}
+
+fn parse_utc_offset(value: &str) -> Result<UtcOffset, String> {
+    if value.eq_ignore_ascii_case("z") || value.eq_ignore_ascii_case("utc") {
+        return Ok(UtcOffset::UTC);
+    }
+
+    let invalid = || format!("expected an offset like -06:00 but found {value}");
+
+    let (sign, offset) = match value.split_at_checked(1) {
+        Some(("+", offset)) => (1, offset),
+        Some(("-", offset)) => (-1, offset),
+        _ => return Err(invalid()),
+    };
+    let (hours, minutes) = offset.split_once(':').unwrap_or((offset, "0"));
+    let hours: i8 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i8 = minutes.parse().map_err(|_| invalid())?;
+
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|err| format!("{err}"))
+}
+
+/// Parses a time like `2025-06-13T07:00`, with optional seconds and UTC
+/// offset.
+fn parse_start_time(value: &str) -> Result<OffsetDateTime, String> {
+    let invalid = || format!("expected a time like 2025-06-13T07:00 but found {value}");
+
+    let (date, time) = value.split_once('T').ok_or_else(invalid)?;
+    let (time, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(index) => (&time[..index], parse_utc_offset(&time[index..])?),
+        None => (time, UtcOffset::UTC),
+    };
+    let format = match time.len() {
+        5 => "[year]-[month]-[day]T[hour]:[minute]",
+        _ => "[year]-[month]-[day]T[hour]:[minute]:[second]",
+    };
+    let format = format_description::parse_borrowed::<2>(format).map_err(|err| format!("{err}"))?;
+    PrimitiveDateTime::parse(&format!("{date}T{time}"), &format)
+        .map(|time| time.assume_offset(offset))
+        .map_err(|_| invalid())
+}
+
+/// Parses a number that has to be above zero, like kilometers per file, the
+/// same as `SplitterBuilder` checks it.
+fn parse_positive(value: &str) -> Result<f64, String> {
+    let number: f64 = value
+        .trim()
+        .parse()
+        .map_err(|_| format!("expected a number but found {value}"))?;
+    if is_positive(number) {
+        Ok(number)
+    } else {
+        Err(format!("expected a positive number but found {value}"))
+    }
+}
+
+/// Parses a percentage of a route to split at, which has to be between 0 and
+/// 100 for there to be anything on each side of it.
+fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent = parse_positive(value)?;
+    if percent < 100. {
+        Ok(percent)
+    } else {
+        Err(format!("expected a percentage below 100 but found {value}"))
+    }
+}
+
+/// Parses a duration like `2h`, `45m`, or `1h30m` into seconds.
+fn parse_duration(value: &str) -> Result<f64, String> {
+    let invalid = || format!("expected a duration like 2h or 45m but found {value}");
+
+    let mut seconds = 0.;
+    let mut rest = value.trim();
+    while !rest.is_empty() {
+        let unit_start = rest
+            .find(|c: char| c.is_ascii_alphabetic())
+            .ok_or_else(invalid)?;
+        let (number, tail) = rest.split_at(unit_start);
+        let unit_end = tail
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_end);
+
+        let number: f64 = number.trim().parse().map_err(|_| invalid())?;
+        seconds += number
+            * match unit {
+                "s" => 1.,
+                "m" | "min" => 60.,
+                "h" => 3600.,
+                "d" => 86400.,
+                _ => return Err(invalid()),
+            };
+        rest = tail.trim_start();
+    }
+
+    if seconds > 0. {
+        Ok(seconds)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Parses a distance like `5km` or `800m` into meters.
+fn parse_distance(value: &str) -> Result<f64, String> {
+    let invalid = || format!("expected a distance like 5km or 800m but found {value}");
+
+    let unit_start = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .ok_or_else(invalid)?;
+    let (number, unit) = value.split_at(unit_start);
+    let number: f64 = number.trim().parse().map_err(|_| invalid())?;
+
+    let meters = number
+        * match unit {
+            "m" => 1.,
+            "km" => 1000.,
+            "mi" => 1609.344,
+            _ => return Err(invalid()),
+        };
+
+    if meters > 0. {
+        Ok(meters)
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Parses a size like `1M`, `500K`, or `2048` into bytes. Suffixes are binary
+/// multiples, so `1K` is 1024 bytes.
+fn parse_bytes(value: &str) -> Result<usize, String> {
+    let invalid = || format!("expected a size like 1M or 500K but found {value}");
+
+    let unit_start = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    let number: f64 = number.trim().parse().map_err(|_| invalid())?;
+
+    let bytes = number
+        * match unit.to_ascii_uppercase().as_str() {
+            "" | "B" => 1.,
+            "K" | "KB" | "KIB" => 1024.,
+            "M" | "MB" | "MIB" => 1024. * 1024.,
+            "G" | "GB" | "GIB" => 1024. * 1024. * 1024.,
+            _ => return Err(invalid()),
+        };
+
+    if bytes >= 1. {
+        Ok(bytes as usize)
+    } else {
+        Err(invalid())
+    }
 }
@@ -0,0 +1,91 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io::Write;
+
+use anyhow::Result;
+use xml::writer::XmlEvent;
+use xml::EmitterConfig;
+use xml::EventWriter;
+
+/// Writes indented XML for the formats other than GPX and for SVG images, one
+/// element at a time.
+pub struct XmlWriter<W: Write>(EventWriter<W>);
+
+impl<W: Write> XmlWriter<W> {
+    /// Starts a document with a root element named `root`, in `namespace`.
+    pub fn new(writer: W, root: &str, namespace: &str) -> Result<XmlWriter<W>> {
+        XmlWriter::with_attributes(writer, root, namespace, &[])
+    }
+
+    /// Starts a document like `new`, with `attributes` on the root element.
+    pub fn with_attributes(
+        writer: W,
+        root: &str,
+        namespace: &str,
+        attributes: &[(&str, &str)],
+    ) -> Result<XmlWriter<W>> {
+        let mut writer = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(writer);
+        let mut element = XmlEvent::start_element(root).default_ns(namespace);
+        for (name, value) in attributes {
+            element = element.attr(*name, value);
+        }
+        writer.write(element)?;
+        Ok(XmlWriter(writer))
+    }
+
+    pub fn start(&mut self, name: &str) -> Result<()> {
+        self.start_with(name, &[])
+    }
+
+    /// Starts an element with `attributes`.
+    pub fn start_with(&mut self, name: &str, attributes: &[(&str, &str)]) -> Result<()> {
+        let mut element = XmlEvent::start_element(name);
+        for (name, value) in attributes {
+            element = element.attr(*name, value);
+        }
+        Ok(self.0.write(element)?)
+    }
+
+    /// Writes an element with `attributes` and nothing in it.
+    pub fn empty(&mut self, name: &str, attributes: &[(&str, &str)]) -> Result<()> {
+        self.start_with(name, attributes)?;
+        self.end()
+    }
+
+    pub fn end(&mut self) -> Result<()> {
+        Ok(self.0.write(XmlEvent::end_element())?)
+    }
+
+    /// Writes an element containing only `text`.
+    pub fn text(&mut self, name: &str, text: &str) -> Result<()> {
+        self.text_with(name, &[], text)
+    }
+
+    /// Writes an element with `attributes` containing only `text`.
+    pub fn text_with(&mut self, name: &str, attributes: &[(&str, &str)], text: &str) -> Result<()> {
+        self.start_with(name, attributes)?;
+        self.0.write(XmlEvent::characters(text))?;
+        self.end()
+    }
+
+    /// Ends the root element and flushes the underlying writer.
+    pub fn finish(mut self) -> Result<()> {
+        self.end()?;
+        self.0.into_inner().flush()?;
+        Ok(())
+    }
+}
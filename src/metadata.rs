@@ -0,0 +1,124 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+/// The `<metadata>` element of a GPX file exactly as it was written, along
+/// with the namespace prefixes declared on the root element, since the
+/// metadata's extensions may use them.
+pub struct RawMetadata {
+    element: String,
+    namespaces: Vec<(String, String)>,
+}
+
+impl RawMetadata {
+    /// Finds the metadata in the text of a GPX file, if it has any.
+    pub fn find(xml: &str) -> Option<RawMetadata> {
+        let (start, end) = element_range(xml)?;
+        Some(RawMetadata {
+            element: xml[start..end].to_owned(),
+            namespaces: root_namespaces(xml),
+        })
+    }
+
+    /// Replaces the metadata in `xml`, which has to have been written by the
+    /// `gpx` crate, with this metadata.
+    pub fn replace(&self, xml: &str) -> String {
+        let xml = declare_namespaces(xml, &self.namespaces);
+        let Some((_, root_end)) = root_tag(&xml) else {
+            return xml;
+        };
+
+        let mut replaced = String::with_capacity(xml.len() + self.element.len());
+        replaced.push_str(&xml[..root_end]);
+        match element_range(&xml) {
+            Some((start, end)) => {
+                replaced.push_str(&xml[root_end..start]);
+                replaced.push_str(&self.element);
+                replaced.push_str(&xml[end..]);
+            }
+            None => {
+                // metadata goes before everything else in the file
+                replaced.push_str("\n  ");
+                replaced.push_str(&self.element);
+                replaced.push_str(&xml[root_end..]);
+            }
+        }
+        replaced
+    }
+}
+
+/// Start and end of the first `<metadata>` element in `xml`.
+fn element_range(xml: &str) -> Option<(usize, usize)> {
+    let start = xml.match_indices("<metadata").find_map(|(index, _)| {
+        let next = xml[index + "<metadata".len()..].chars().next()?;
+        (next == '>' || next == '/' || next.is_whitespace()).then_some(index)
+    })?;
+
+    let open_end = start + xml[start..].find('>')? + 1;
+    if xml[..open_end].ends_with("/>") {
+        return Some((start, open_end));
+    }
+    let close = "</metadata>";
+    Some((start, open_end + xml[open_end..].find(close)? + close.len()))
+}
+
+/// Prefixes and URIs of the namespaces declared on the root element of `xml`.
+pub fn root_namespaces(xml: &str) -> Vec<(String, String)> {
+    root_tag(xml)
+        .map(|(start, end)| namespaces(&xml[start..end]))
+        .unwrap_or_default()
+}
+
+/// Declares `namespaces` on the root element of `xml`, other than any prefixes
+/// that are already declared there.
+pub fn declare_namespaces(xml: &str, namespaces: &[(String, String)]) -> String {
+    let declared = root_namespaces(xml);
+    let Some((_, root_end)) = root_tag(xml) else {
+        return xml.to_owned();
+    };
+
+    let mut declaring = String::with_capacity(xml.len() + 256);
+    declaring.push_str(&xml[..root_end - 1]);
+    for (prefix, uri) in namespaces {
+        if !declared.iter().any(|(declared, _)| declared == prefix) {
+            declaring.push_str(&format!(" xmlns:{prefix}=\"{uri}\""));
+        }
+    }
+    declaring.push_str(&xml[root_end - 1..]);
+    declaring
+}
+
+/// Start and end of the root element's start tag.
+fn root_tag(xml: &str) -> Option<(usize, usize)> {
+    let start = xml.find("<gpx")?;
+    Some((start, start + xml[start..].find('>')? + 1))
+}
+
+/// Prefixes and URIs of the namespaces declared in a start tag.
+fn namespaces(tag: &str) -> Vec<(String, String)> {
+    let mut namespaces = Vec::new();
+    for (index, _) in tag.match_indices("xmlns:") {
+        let rest = &tag[index + "xmlns:".len()..];
+        let Some((prefix, value)) = rest.split_once('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        if let Some(end) = value[1..].find(quote) {
+            namespaces.push((prefix.trim().to_owned(), value[1..end + 1].to_owned()));
+        }
+    }
+    namespaces
+}
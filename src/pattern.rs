@@ -0,0 +1,237 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::str::FromStr;
+
+/// A small subset of regular expressions, which is plenty for matching
+/// waypoint names: `.` matches any character, `*`, `+`, and `?` repeat the
+/// preceding character, `^` and `$` anchor to the start and end, and `\`
+/// escapes the following character. Like a regular expression, the pattern
+/// matches anywhere in the text unless it's anchored. Alternation, groups,
+/// character classes, and counted repetition aren't supported, so their
+/// characters have to be escaped to match them.
+#[derive(Clone, Debug)]
+pub struct Pattern {
+    tokens: Vec<Token>,
+    anchored_start: bool,
+    anchored_end: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Token {
+    atom: Atom,
+    repeat: Repeat,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Atom {
+    Any,
+    Char(char),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Repeat {
+    One,
+    ZeroOrOne,
+    ZeroOrMore,
+    OneOrMore,
+}
+
+impl Pattern {
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+
+        // whether the tokens from each index match the text from each
+        // position, so that backtracking never tries the same thing twice
+        let mut memo = vec![None; (self.tokens.len() + 1) * (text.len() + 1)];
+        let starts = if self.anchored_start {
+            0..=0
+        } else {
+            0..=text.len()
+        };
+        starts
+            .into_iter()
+            .any(|start| self.match_here(0, start, &text, &mut memo))
+    }
+
+    /// Whether the tokens from `index` on match `text` from `position` on.
+    fn match_here(
+        &self,
+        index: usize,
+        position: usize,
+        text: &[char],
+        memo: &mut [Option<bool>],
+    ) -> bool {
+        let key = index * (text.len() + 1) + position;
+        if let Some(matched) = memo[key] {
+            return matched;
+        }
+        let matched = match self.tokens.get(index) {
+            None => !self.anchored_end || position == text.len(),
+            Some(token) => self.match_token(token, index, position, text, memo),
+        };
+        memo[key] = Some(matched);
+        matched
+    }
+
+    fn match_token(
+        &self,
+        token: &Token,
+        index: usize,
+        position: usize,
+        text: &[char],
+        memo: &mut [Option<bool>],
+    ) -> bool {
+        let matches = |c: &char| match token.atom {
+            Atom::Any => true,
+            Atom::Char(expected) => *c == expected,
+        };
+
+        let (min, max) = match token.repeat {
+            Repeat::One => (1, 1),
+            Repeat::ZeroOrOne => (0, 1),
+            Repeat::ZeroOrMore => (0, usize::MAX),
+            Repeat::OneOrMore => (1, usize::MAX),
+        };
+
+        let available = text[position..]
+            .iter()
+            .take(max)
+            .take_while(|c| matches(c))
+            .count();
+
+        // greedy, backtracking one character at a time
+        (min..=available)
+            .rev()
+            .any(|count| self.match_here(index + 1, position + count, text, memo))
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut chars = value.chars().peekable();
+        let mut tokens: Vec<Token> = Vec::new();
+
+        let anchored_start = chars.next_if_eq(&'^').is_some();
+        let mut anchored_end = false;
+
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                '.' => Atom::Any,
+                '\\' => Atom::Char(
+                    chars
+                        .next()
+                        .ok_or_else(|| format!("pattern {value} ends with an escape"))?,
+                ),
+                '$' if chars.peek().is_none() => {
+                    anchored_end = true;
+                    break;
+                }
+                '|' | '(' | ')' | '[' | ']' | '{' | '}' => {
+                    return Err(format!(
+                        "pattern {value} uses `{c}`, which isn't supported, \
+                         escape it as `\\{c}` to match it"
+                    ));
+                }
+                '*' | '+' | '?' => {
+                    let token = tokens
+                        .last_mut()
+                        .filter(|token| matches!(token.repeat, Repeat::One))
+                        .ok_or_else(|| format!("pattern {value} has nothing to repeat"))?;
+                    token.repeat = match c {
+                        '*' => Repeat::ZeroOrMore,
+                        '+' => Repeat::OneOrMore,
+                        _ => Repeat::ZeroOrOne,
+                    };
+                    continue;
+                }
+                c => Atom::Char(c),
+            };
+
+            tokens.push(Token {
+                atom,
+                repeat: Repeat::One,
+            });
+        }
+
+        Ok(Pattern {
+            tokens,
+            anchored_start,
+            anchored_end,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use super::*;
+
+    fn is_match(pattern: &str, text: &str) -> bool {
+        pattern.parse::<Pattern>().unwrap().is_match(text)
+    }
+
+    #[test]
+    fn matches_like_a_regular_expression() {
+        assert!(is_match("Town", "Big Town Market"));
+        assert!(!is_match("town", "Big Town Market"));
+        assert!(is_match("^Big", "Big Town"));
+        assert!(!is_match("^Town", "Big Town"));
+        assert!(is_match("Town$", "Big Town"));
+        assert!(!is_match("Big$", "Big Town"));
+        assert!(is_match("^B.g T", "Bag Town"));
+        assert!(is_match("^Lo*p$", "Lp"));
+        assert!(is_match("^Lo*p$", "Looop"));
+        assert!(!is_match("^Lo+p$", "Lp"));
+        assert!(is_match("^Lo+p$", "Lop"));
+        assert!(is_match("^Lo?p$", "Lp"));
+        assert!(!is_match("^Lo?p$", "Loop"));
+        assert!(is_match("^Water.*Stop$", "Water and Food Stop"));
+        assert!(is_match("", "anything"));
+        assert!(is_match("^$", ""));
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert!(is_match(r"^Mile 3\.5$", "Mile 3.5"));
+        assert!(!is_match(r"^Mile 3\.5$", "Mile 305"));
+        assert!(is_match(r"\(Resupply\)", "Lima (Resupply)"));
+        assert!(is_match(r"\$", "Costs $"));
+        assert!(is_match(r"a\*", "a*"));
+    }
+
+    #[test]
+    fn rejects_unsupported_syntax() {
+        for c in ['|', '(', ')', '[', ']', '{', '}'] {
+            let error = format!("a{c}b").parse::<Pattern>().unwrap_err();
+            assert!(error.contains(&format!("`{c}`")), "{error}");
+        }
+        assert!("*a".parse::<Pattern>().is_err());
+        assert!("a**".parse::<Pattern>().is_err());
+        assert!("a\\".parse::<Pattern>().is_err());
+    }
+
+    #[test]
+    fn backtracks_in_polynomial_time() {
+        let started = Instant::now();
+        let text = "a".repeat(500);
+        assert!(!is_match(&(".*".repeat(20) + "b"), &text));
+        assert!(!is_match(&("a*".repeat(30) + "$b"), &text));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+
+use crate::json::Json;
+
+/// Where the routes in a file are cut, which is saved as JSON by
+/// `--save-plan` so that it can be edited and split with `--apply-plan`.
+pub struct Plan {
+    /// Name of the file that was split.
+    pub input: String,
+    /// The routes that were split, in the order they're split in.
+    pub routes: Vec<RoutePlan>,
+}
+
+/// Where a single route is cut.
+#[derive(Default)]
+pub struct RoutePlan {
+    /// Number of points in the route, to catch a plan being applied to a
+    /// different route.
+    pub points: Option<usize>,
+    pub cuts: Vec<Cut>,
+}
+
+/// A place where a route is cut. Each file ends at `index` if there is one,
+/// otherwise after the first point past `km` along the route. The position is
+/// only there to show where the cut is.
+pub struct Cut {
+    pub index: Option<usize>,
+    pub km: Option<f64>,
+    pub position: Option<(f64, f64)>,
+    /// Whether the next file starts after the cut, without repeating the last
+    /// point of this one.
+    pub detached: bool,
+}
+
+impl Plan {
+    pub fn parse(text: &str) -> Result<Plan> {
+        let json = Json::parse(text)?;
+        let input = json
+            .get("input")
+            .and_then(Json::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        let routes = json
+            .get("routes")
+            .and_then(Json::as_array)
+            .ok_or_else(|| anyhow!("plan has no routes"))?
+            .iter()
+            .enumerate()
+            .map(|(number, route)| {
+                RoutePlan::from_json(route).with_context(|| format!("in route {}", number + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Plan { input, routes })
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("input", Json::String(self.input.clone())),
+            (
+                "routes",
+                Json::Array(self.routes.iter().map(RoutePlan::to_json).collect()),
+            ),
+        ])
+    }
+}
+
+impl RoutePlan {
+    fn from_json(json: &Json) -> Result<RoutePlan> {
+        let points = json.get("points").map(index).transpose()?;
+        let cuts = json
+            .get("cuts")
+            .and_then(Json::as_array)
+            .ok_or_else(|| anyhow!("route has no cuts"))?
+            .iter()
+            .enumerate()
+            .map(|(number, cut)| {
+                Cut::from_json(cut).with_context(|| format!("in cut {}", number + 1))
+            })
+            .collect::<Result<_>>()?;
+        Ok(RoutePlan { points, cuts })
+    }
+
+    fn to_json(&self) -> Json {
+        let mut entries = Vec::new();
+        if let Some(points) = self.points {
+            entries.push(("points", Json::Number(points as f64)));
+        }
+        entries.push((
+            "cuts",
+            Json::Array(self.cuts.iter().map(Cut::to_json).collect()),
+        ));
+        Json::object(entries)
+    }
+}
+
+impl Cut {
+    fn from_json(json: &Json) -> Result<Cut> {
+        let index = json.get("index").map(index).transpose()?;
+        let km = match json.get("km") {
+            Some(km) => match km.as_f64() {
+                Some(km) if km.is_finite() && km >= 0. => Some(km),
+                _ => bail!("km must be a positive number"),
+            },
+            None => None,
+        };
+        if index.is_none() && km.is_none() {
+            bail!("cut needs an index or km");
+        }
+        Ok(Cut {
+            index,
+            km,
+            position: None,
+            detached: json.get("detached") == Some(&Json::Bool(true)),
+        })
+    }
+
+    fn to_json(&self) -> Json {
+        let mut entries = Vec::new();
+        if let Some(index) = self.index {
+            entries.push(("index", Json::Number(index as f64)));
+        }
+        if let Some(km) = self.km {
+            entries.push(("km", Json::Number((km * 1000.).round() / 1000.)));
+        }
+        if let Some((latitude, longitude)) = self.position {
+            entries.push(("latitude", Json::Number(latitude)));
+            entries.push(("longitude", Json::Number(longitude)));
+        }
+        if self.detached {
+            entries.push(("detached", Json::Bool(true)));
+        }
+        Json::object(entries)
+    }
+}
+
+fn index(json: &Json) -> Result<usize> {
+    match json.as_f64() {
+        Some(number) if number >= 0. && number.fract() == 0. => Ok(number as usize),
+        _ => bail!("expected a whole number but found {json}"),
+    }
+}
@@ -0,0 +1,73 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Waypoints read between reports of `Progress::PointsRead`, so that long
+/// parts still show progress without a call for every waypoint.
+pub const PROGRESS_POINTS: usize = 10_000;
+
+/// How far splitting has gotten, for showing progress on long routes.
+#[derive(Clone, Copy, Debug)]
+pub enum Progress {
+    /// Number of waypoints read from the start of the route. Waypoints that
+    /// are read again after snapping to a landmark count again.
+    PointsRead(usize),
+    /// A part of the route has been split off, with its position starting
+    /// from zero and its number of waypoints.
+    PartFinished { index: usize, points: usize },
+    /// Number of bytes written so far by a `ProgressWriter`.
+    BytesWritten(usize),
+}
+
+/// Called with each report of progress. It can be called from whichever
+/// thread the route is split on.
+pub type ProgressCallback = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Writer that reports the number of bytes written through it, for writing
+/// the parts.
+pub struct ProgressWriter<W: Write> {
+    writer: W,
+    progress: ProgressCallback,
+    written: usize,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    pub fn new(writer: W, progress: ProgressCallback) -> ProgressWriter<W> {
+        ProgressWriter {
+            writer,
+            progress,
+            written: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.writer.write(buf)?;
+        self.written += written;
+        (self.progress)(Progress::BytesWritten(self.written));
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io;
+use std::io::IsTerminal;
+use std::io::Read;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Time to wait before first drawing the bar, so that it doesn't flash up
+/// for files that only take a moment.
+const FIRST_DRAW_DELAY: Duration = Duration::from_millis(500);
+/// Time between redraws, so that drawing doesn't slow down splitting.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 30;
+
+/// A bar on stderr showing how far along splitting a long route is, so that
+/// it doesn't look stuck. It's only drawn when stderr is a terminal, and
+/// taken off the screen when it's dropped.
+pub struct ProgressBar {
+    enabled: bool,
+    started: Instant,
+    /// When the bar was last drawn, if it's on the screen.
+    drawn: Mutex<Option<Instant>>,
+}
+
+impl ProgressBar {
+    pub fn new(quiet: bool) -> Arc<ProgressBar> {
+        Arc::new(ProgressBar {
+            enabled: !quiet && io::stderr().is_terminal(),
+            started: Instant::now(),
+            drawn: Mutex::new(None),
+        })
+    }
+
+    /// Shows that `done` of `total` things have been done in `stage`, like
+    /// points split, followed by `details`, which is only called when the bar
+    /// is redrawn.
+    pub fn show(&self, stage: &str, done: usize, total: usize, details: impl FnOnce() -> String) {
+        if !self.enabled || self.started.elapsed() < FIRST_DRAW_DELAY {
+            return;
+        }
+        let mut drawn = self.drawn.lock().unwrap();
+        let recently_drawn = drawn.is_some_and(|drawn| drawn.elapsed() < REDRAW_INTERVAL);
+        if recently_drawn && done < total {
+            return;
+        }
+
+        let fraction = match total {
+            0 => 1.,
+            _ => (done as f64 / total as f64).min(1.),
+        };
+        let filled = (fraction * BAR_WIDTH as f64) as usize;
+        let mut stderr = io::stderr().lock();
+        // \x1b[K clears the rest of the line, in case it was longer before
+        write!(
+            stderr,
+            "\r{stage:<9} [{}{}] {:>3.0}%  {}\x1b[K",
+            "=".repeat(filled),
+            " ".repeat(BAR_WIDTH - filled),
+            fraction * 100.,
+            details()
+        )
+        .and_then(|()| stderr.flush())
+        .ok();
+        *drawn = Some(Instant::now());
+    }
+
+    /// Takes the bar off the screen, before printing anything else.
+    pub fn clear(&self) {
+        if self.drawn.lock().unwrap().take().is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Reader that shows how many of the `total` bytes it has read on a progress
+/// bar.
+pub struct ReadProgress<R: Read> {
+    reader: R,
+    read: usize,
+    total: usize,
+    bar: Arc<ProgressBar>,
+}
+
+impl<R: Read> ReadProgress<R> {
+    pub fn new(reader: R, total: usize, bar: Arc<ProgressBar>) -> ReadProgress<R> {
+        ReadProgress {
+            reader,
+            read: 0,
+            total,
+            bar,
+        }
+    }
+}
+
+impl<R: Read> Read for ReadProgress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.reader.read(buf)?;
+        self.read += count;
+        let (read, total) = (self.read, self.total);
+        self.bar.show("reading", read, total, || {
+            format!("{} of {} MB", megabytes(read), megabytes(total))
+        });
+        Ok(count)
+    }
+}
+
+fn megabytes(bytes: usize) -> String {
+    format!("{:.1}", bytes as f64 / 1_000_000.)
+}
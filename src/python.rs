@@ -0,0 +1,330 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! A Python module with the `python` feature, for splitting routes from
+//! Python without running the command for each file. `python/gpxsplit.pyi`
+//! describes it.
+//!
+//! ```python
+//! import gpxsplit
+//!
+//! with open("route.gpx") as file:
+//!     parts = gpxsplit.split(file.read(), km_per_file=150, overlap_points=2)
+//! ```
+//!
+//! It's written against Python's stable C API, so it doesn't need anything
+//! to build but works with any Python from 3.10 on. Build it with
+//! `cargo build --release --features python` and copy
+//! `target/release/libgpxsplit.so` to `gpxsplit.so` somewhere on Python's
+//! path. On macOS, Python's functions are only found once it's loaded, which
+//! the linker has to be told about with
+//! `RUSTFLAGS="-C link-arg=-undefined -C link-arg=dynamic_lookup"`.
+
+use std::ffi::c_char;
+use std::ffi::c_int;
+use std::ffi::c_void;
+use std::ffi::CString;
+use std::ptr;
+use std::slice;
+
+use crate::split_gpx_bytes;
+use crate::Splitter;
+use crate::SplitterBuilder;
+
+/// A Python object, which is only ever handled by pointer.
+#[repr(C)]
+pub struct PyObject {
+    _private: [u8; 0],
+}
+
+type PyCFunctionWithKeywords =
+    unsafe extern "C" fn(*mut PyObject, *mut PyObject, *mut PyObject) -> *mut PyObject;
+
+#[repr(C)]
+struct PyMethodDef {
+    name: *const c_char,
+    function: Option<PyCFunctionWithKeywords>,
+    flags: c_int,
+    doc: *const c_char,
+}
+
+#[repr(C)]
+struct PyModuleDefBase {
+    refcnt: isize,
+    type_: *mut c_void,
+    init: *mut c_void,
+    index: isize,
+    copy: *mut PyObject,
+}
+
+#[repr(C)]
+struct PyModuleDef {
+    base: PyModuleDefBase,
+    name: *const c_char,
+    doc: *const c_char,
+    size: isize,
+    methods: *mut PyMethodDef,
+    slots: *mut c_void,
+    traverse: *mut c_void,
+    clear: *mut c_void,
+    free: *mut c_void,
+}
+
+const METH_VARARGS: c_int = 1;
+const METH_KEYWORDS: c_int = 2;
+const PYTHON_API_VERSION: c_int = 1013;
+
+extern "C" {
+    static mut PyExc_TypeError: *mut PyObject;
+    static mut PyExc_ValueError: *mut PyObject;
+    static mut _Py_NoneStruct: PyObject;
+
+    fn PyModule_Create2(module: *mut PyModuleDef, api_version: c_int) -> *mut PyObject;
+    fn PyTuple_Size(tuple: *mut PyObject) -> isize;
+    fn PyTuple_GetItem(tuple: *mut PyObject, index: isize) -> *mut PyObject;
+    fn PyDict_Next(
+        dict: *mut PyObject,
+        position: *mut isize,
+        key: *mut *mut PyObject,
+        value: *mut *mut PyObject,
+    ) -> c_int;
+    fn PyUnicode_AsUTF8AndSize(unicode: *mut PyObject, size: *mut isize) -> *const c_char;
+    fn PyUnicode_FromStringAndSize(text: *const c_char, size: isize) -> *mut PyObject;
+    fn PyBytes_AsStringAndSize(
+        bytes: *mut PyObject,
+        buffer: *mut *mut c_char,
+        size: *mut isize,
+    ) -> c_int;
+    fn PyFloat_AsDouble(float: *mut PyObject) -> f64;
+    fn PyLong_AsSize_t(long: *mut PyObject) -> usize;
+    fn PyObject_IsTrue(object: *mut PyObject) -> c_int;
+    fn PyObject_GetIter(object: *mut PyObject) -> *mut PyObject;
+    fn PyIter_Next(iterator: *mut PyObject) -> *mut PyObject;
+    fn PyList_New(size: isize) -> *mut PyObject;
+    fn PyList_SetItem(list: *mut PyObject, index: isize, item: *mut PyObject) -> c_int;
+    fn Py_DecRef(object: *mut PyObject);
+    fn PyErr_Occurred() -> *mut PyObject;
+    fn PyErr_Clear();
+    fn PyErr_SetString(exception: *mut PyObject, message: *const c_char);
+    fn PyEval_SaveThread() -> *mut c_void;
+    fn PyEval_RestoreThread(state: *mut c_void);
+}
+
+static mut METHODS: [PyMethodDef; 2] = [
+    PyMethodDef {
+        name: c"split".as_ptr(),
+        function: Some(split),
+        flags: METH_VARARGS | METH_KEYWORDS,
+        doc: c"split(gpx, **options)\n--\n\n\
+Splits the first segment of the first track of the GPX file in gpx, a str or\n\
+bytes, returning the contents of a GPX file for each part. The keyword\n\
+arguments are the options of SplitterBuilder: km_per_file, hours_per_file,\n\
+gain_per_file, est_hours_per_file, speed, climbing_per_hour, max_points,\n\
+at_km (a list), overlap_points, overlap_km, no_overlap, exclude_overlap,\n\
+and three_d_distance. Options that don't make sense raise ValueError."
+            .as_ptr(),
+    },
+    PyMethodDef {
+        name: ptr::null(),
+        function: None,
+        flags: 0,
+        doc: ptr::null(),
+    },
+];
+
+static mut MODULE: PyModuleDef = PyModuleDef {
+    base: PyModuleDefBase {
+        refcnt: 1,
+        type_: ptr::null_mut(),
+        init: ptr::null_mut(),
+        index: 0,
+        copy: ptr::null_mut(),
+    },
+    name: c"gpxsplit".as_ptr(),
+    doc: c"Splits long GPX routes into shorter ones that navigation devices can handle.".as_ptr(),
+    size: -1,
+    methods: ptr::null_mut(),
+    slots: ptr::null_mut(),
+    traverse: ptr::null_mut(),
+    clear: ptr::null_mut(),
+    free: ptr::null_mut(),
+};
+
+/// Creates the module when Python imports it.
+///
+/// # Safety
+///
+/// Only Python calls this, with the interpreter's lock held.
+#[no_mangle]
+pub unsafe extern "C" fn PyInit_gpxsplit() -> *mut PyObject {
+    let module = &raw mut MODULE;
+    (*module).methods = (&raw mut METHODS).cast();
+    PyModule_Create2(module, PYTHON_API_VERSION)
+}
+
+/// The Python exception has already been raised.
+struct Raised;
+
+/// Raises `exception` with `message`.
+unsafe fn raise(exception: *mut PyObject, message: &str) -> Raised {
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    PyErr_SetString(exception, message.as_ptr());
+    Raised
+}
+
+unsafe extern "C" fn split(
+    _module: *mut PyObject,
+    args: *mut PyObject,
+    kwargs: *mut PyObject,
+) -> *mut PyObject {
+    split_parts(args, kwargs).unwrap_or(ptr::null_mut())
+}
+
+unsafe fn split_parts(args: *mut PyObject, kwargs: *mut PyObject) -> Result<*mut PyObject, Raised> {
+    if PyTuple_Size(args) != 1 {
+        return Err(raise(
+            PyExc_TypeError,
+            "split() takes the gpx file as its only positional argument",
+        ));
+    }
+    let input = bytes(PyTuple_GetItem(args, 0))?;
+
+    let mut builder = Splitter::builder();
+    if !kwargs.is_null() {
+        let mut position = 0;
+        let mut key = ptr::null_mut();
+        let mut value = ptr::null_mut();
+        while PyDict_Next(kwargs, &mut position, &mut key, &mut value) != 0 {
+            if value == &raw mut _Py_NoneStruct {
+                continue;
+            }
+            let name = String::from_utf8_lossy(bytes(key)?).into_owned();
+            builder = option(builder, &name, value)?;
+        }
+    }
+    let options = match builder.build() {
+        Ok(splitter) => splitter.options().clone(),
+        Err(error) => return Err(raise(PyExc_ValueError, &error.describe())),
+    };
+
+    // other Python threads can run while the route is split
+    let state = PyEval_SaveThread();
+    let parts = split_gpx_bytes(input, options);
+    PyEval_RestoreThread(state);
+    let parts = parts.map_err(|error| raise(PyExc_ValueError, &error.describe()))?;
+
+    let list = PyList_New(parts.len() as isize);
+    if list.is_null() {
+        return Err(Raised);
+    }
+    for (index, part) in parts.iter().enumerate() {
+        let text = PyUnicode_FromStringAndSize(part.as_ptr().cast(), part.len() as isize);
+        if text.is_null() {
+            Py_DecRef(list);
+            return Err(Raised);
+        }
+        PyList_SetItem(list, index as isize, text);
+    }
+    Ok(list)
+}
+
+/// Sets the option named `name` on `builder` to `value`.
+unsafe fn option(
+    builder: SplitterBuilder,
+    name: &str,
+    value: *mut PyObject,
+) -> Result<SplitterBuilder, Raised> {
+    Ok(match name {
+        "km_per_file" => builder.km_per_file(float(value)?),
+        "hours_per_file" => builder.hours_per_file(float(value)?),
+        "gain_per_file" => builder.gain_per_file(float(value)?),
+        "est_hours_per_file" => builder.est_hours_per_file(float(value)?),
+        "speed" => builder.speed(float(value)?),
+        "climbing_per_hour" => builder.climbing_per_hour(float(value)?),
+        "max_points" => builder.max_points(count(value)?),
+        "at_km" => {
+            let iterator = PyObject_GetIter(value);
+            if iterator.is_null() {
+                return Err(Raised);
+            }
+            let mut builder = builder;
+            loop {
+                let item = PyIter_Next(iterator);
+                if item.is_null() {
+                    break;
+                }
+                let km = float(item);
+                Py_DecRef(item);
+                builder = builder.at_km(km?);
+            }
+            Py_DecRef(iterator);
+            if !PyErr_Occurred().is_null() {
+                return Err(Raised);
+            }
+            builder
+        }
+        "overlap_points" => builder.overlap_points(count(value)?),
+        "overlap_km" => builder.overlap_km(float(value)?),
+        "no_overlap" if truth(value)? => builder.no_overlap(),
+        "no_overlap" => builder,
+        "exclude_overlap" => builder.exclude_overlap(truth(value)?),
+        "three_d_distance" => builder.three_d_distance(truth(value)?),
+        _ => {
+            return Err(raise(
+                PyExc_TypeError,
+                &format!("split() got an unexpected keyword argument '{name}'"),
+            ))
+        }
+    })
+}
+
+/// The UTF-8 text of a `str`, or the contents of `bytes`. The slice is valid
+/// as long as the object is.
+unsafe fn bytes<'a>(object: *mut PyObject) -> Result<&'a [u8], Raised> {
+    let mut size = 0;
+    let text = PyUnicode_AsUTF8AndSize(object, &mut size);
+    if !text.is_null() {
+        return Ok(slice::from_raw_parts(text.cast(), size as usize));
+    }
+    PyErr_Clear();
+    let mut buffer = ptr::null_mut();
+    if PyBytes_AsStringAndSize(object, &mut buffer, &mut size) == 0 {
+        return Ok(slice::from_raw_parts(buffer.cast(), size as usize));
+    }
+    PyErr_Clear();
+    Err(raise(PyExc_TypeError, "expected str or bytes"))
+}
+
+unsafe fn float(object: *mut PyObject) -> Result<f64, Raised> {
+    let value = PyFloat_AsDouble(object);
+    if value == -1. && !PyErr_Occurred().is_null() {
+        return Err(Raised);
+    }
+    Ok(value)
+}
+
+unsafe fn count(object: *mut PyObject) -> Result<usize, Raised> {
+    let value = PyLong_AsSize_t(object);
+    if value == usize::MAX && !PyErr_Occurred().is_null() {
+        return Err(Raised);
+    }
+    Ok(value)
+}
+
+unsafe fn truth(object: *mut PyObject) -> Result<bool, Raised> {
+    match PyObject_IsTrue(object) {
+        -1 => Err(Raised),
+        truth => Ok(truth == 1),
+    }
+}
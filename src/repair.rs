@@ -0,0 +1,278 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use time::format_description::well_known::Iso8601;
+use time::OffsetDateTime;
+use time::PrimitiveDateTime;
+use xml::attribute::Attribute;
+use xml::name::Name;
+use xml::reader::XmlEvent;
+use xml::EmitterConfig;
+use xml::EventReader;
+
+/// Elements that points can have, other than those checked by `valid_text`.
+const POINT_ELEMENTS: [&str; 6] = ["name", "cmt", "desc", "src", "link", "extensions"];
+
+/// Rewrites a GPX file so that the `gpx` crate can read it, for `--lenient`.
+/// Points without a valid position are left out, longitudes past the
+/// antimeridian are wrapped around, and elements of points that are unknown or
+/// have invalid values are removed. Returns the repaired file and a
+/// description of each kind of repair that was made.
+pub fn repair(input: &[u8]) -> Result<(Vec<u8>, Vec<String>)> {
+    let mut writer = EmitterConfig::new().create_writer(Vec::new());
+    let mut repairs = Repairs::default();
+
+    let mut path: Vec<String> = Vec::new();
+    let mut skip_depth = 0;
+    let mut child: Option<Child> = None;
+    // speed is only allowed in GPX 1.0 files
+    let mut gpx10 = false;
+
+    for event in EventReader::new(input) {
+        let event = event?;
+
+        // inside a point that's left out
+        if skip_depth > 0 {
+            match event {
+                XmlEvent::StartElement { .. } => skip_depth += 1,
+                XmlEvent::EndElement { .. } => skip_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+
+        // inside an element of a point, which is written or left out once
+        // it's been read to the end
+        if let Some(current) = &mut child {
+            match &event {
+                XmlEvent::StartElement { .. } => {
+                    current.depth += 1;
+                    current.nested = true;
+                }
+                XmlEvent::EndElement { .. } => current.depth -= 1,
+                XmlEvent::Characters(text) | XmlEvent::CData(text) => current.text.push_str(text),
+                _ => {}
+            }
+            current.events.push(event);
+            if current.depth > 0 {
+                continue;
+            }
+
+            let current = child.take().unwrap();
+            let name = current.name.as_str();
+            if POINT_ELEMENTS.contains(&name) {
+                for event in &current.events {
+                    if let Some(event) = event.as_writer_event() {
+                        writer.write(event)?;
+                    }
+                }
+            } else if !current.nested && valid_text(name, current.text.trim(), gpx10) {
+                // gpx doesn't trim the whitespace around numbers
+                let trimmed = current.text.trim();
+                if trimmed != current.text {
+                    *repairs.trimmed.entry(current.name.clone()).or_default() += 1;
+                }
+                for event in &current.events {
+                    match event {
+                        XmlEvent::Characters(_) | XmlEvent::CData(_) => {}
+                        XmlEvent::EndElement { .. } => {
+                            writer.write(xml::writer::XmlEvent::characters(trimmed))?;
+                            writer.write(xml::writer::XmlEvent::end_element())?;
+                        }
+                        event => {
+                            if let Some(event) = event.as_writer_event() {
+                                writer.write(event)?;
+                            }
+                        }
+                    }
+                }
+            } else {
+                *repairs.removed.entry(current.name).or_default() += 1;
+            }
+            continue;
+        }
+
+        match &event {
+            XmlEvent::StartElement {
+                name,
+                attributes,
+                namespace,
+            } => {
+                let local_name = name.local_name.as_str();
+                let parent = path.last().map(String::as_str);
+
+                if matches!(parent, Some("wpt" | "trkpt" | "rtept")) {
+                    child = Some(Child {
+                        name: local_name.to_owned(),
+                        events: vec![event.clone()],
+                        text: String::new(),
+                        depth: 1,
+                        nested: false,
+                    });
+                    continue;
+                }
+
+                let mut attributes: Vec<Attribute> = attributes
+                    .iter()
+                    .map(|attribute| attribute.borrow())
+                    .collect();
+                let longitude;
+
+                if local_name == "gpx" && parent.is_none() {
+                    let version = attributes
+                        .iter()
+                        .position(|attribute| attribute.name.local_name == "version");
+                    match version {
+                        Some(index) if attributes[index].value == "1.0" => gpx10 = true,
+                        Some(index) if attributes[index].value == "1.1" => {}
+                        Some(index) => {
+                            attributes[index].value = "1.1";
+                            repairs.version = true;
+                        }
+                        None => {
+                            attributes.push(Attribute::new(Name::local("version"), "1.1"));
+                            repairs.version = true;
+                        }
+                    }
+                } else if matches!(local_name, "wpt" | "trkpt" | "rtept") {
+                    for attribute in &mut attributes {
+                        if matches!(attribute.name.local_name, "lat" | "lon") {
+                            attribute.value = attribute.value.trim();
+                        }
+                    }
+                    let value = |name: &str| {
+                        attributes
+                            .iter()
+                            .find(|attribute| attribute.name.local_name == name)
+                            .and_then(|attribute| attribute.value.parse::<f64>().ok())
+                            .filter(|value| value.is_finite())
+                    };
+                    let (Some(latitude), Some(lon)) = (value("lat"), value("lon")) else {
+                        repairs.points += 1;
+                        skip_depth = 1;
+                        continue;
+                    };
+                    if !(-90.0..=90.0).contains(&latitude) {
+                        repairs.points += 1;
+                        skip_depth = 1;
+                        continue;
+                    }
+                    if !(-180.0..180.0).contains(&lon) {
+                        repairs.longitudes += 1;
+                        longitude = ((lon + 180.).rem_euclid(360.) - 180.).to_string();
+                        for attribute in &mut attributes {
+                            if attribute.name.local_name == "lon" {
+                                attribute.value = &longitude;
+                            }
+                        }
+                    }
+                }
+
+                writer.write(xml::writer::XmlEvent::StartElement {
+                    name: name.borrow(),
+                    attributes: Cow::Owned(attributes),
+                    namespace: Cow::Borrowed(namespace),
+                })?;
+                path.push(name.local_name.clone());
+                continue;
+            }
+            XmlEvent::EndElement { .. } => {
+                path.pop();
+            }
+            _ => {}
+        }
+
+        if let Some(event) = event.as_writer_event() {
+            writer.write(event)?;
+        }
+    }
+
+    Ok((writer.into_inner(), repairs.descriptions()))
+}
+
+/// An element of a point, read up to its end before deciding whether to keep
+/// it.
+struct Child {
+    name: String,
+    events: Vec<XmlEvent>,
+    text: String,
+    depth: usize,
+    /// Whether it has elements in it, which only the elements in
+    /// `POINT_ELEMENTS` can have.
+    nested: bool,
+}
+
+#[derive(Default)]
+struct Repairs {
+    version: bool,
+    points: usize,
+    longitudes: usize,
+    /// Number of each element that was removed from points.
+    removed: BTreeMap<String, usize>,
+    /// Number of each element that had whitespace around its value.
+    trimmed: BTreeMap<String, usize>,
+}
+
+impl Repairs {
+    fn descriptions(&self) -> Vec<String> {
+        let mut descriptions = Vec::new();
+        if self.version {
+            descriptions.push("set the GPX version to 1.1".to_owned());
+        }
+        if self.points > 0 {
+            descriptions.push(format!(
+                "left out {} points without a valid position",
+                self.points
+            ));
+        }
+        if self.longitudes > 0 {
+            descriptions.push(format!(
+                "wrapped {} longitudes past the antimeridian",
+                self.longitudes
+            ));
+        }
+        for (name, count) in &self.removed {
+            descriptions.push(format!(
+                "removed {count} unknown or invalid <{name}> elements from points"
+            ));
+        }
+        for (name, count) in &self.trimmed {
+            descriptions.push(format!("trimmed whitespace from {count} <{name}> elements"));
+        }
+        descriptions
+    }
+}
+
+/// Whether `text` is a valid value for the element of a point called `name`.
+fn valid_text(name: &str, text: &str, gpx10: bool) -> bool {
+    match name {
+        // an empty elevation is allowed, unlike the others
+        "ele" => text.is_empty() || text.parse::<f64>().is_ok(),
+        "time" => {
+            OffsetDateTime::parse(text, &Iso8601::PARSING).is_ok()
+                || PrimitiveDateTime::parse(text, &Iso8601::PARSING).is_ok()
+        }
+        "geoidheight" | "hdop" | "vdop" | "pdop" | "ageofdgpsdata" => text.parse::<f64>().is_ok(),
+        "speed" => gpx10 && text.parse::<f64>().is_ok(),
+        "sat" => text.parse::<u64>().is_ok(),
+        "dgpsid" => text.parse::<u16>().is_ok(),
+        "fix" => ["none", "2d", "3d", "dgps", "pps"].contains(&text),
+        "sym" | "type" => !text.is_empty(),
+        _ => false,
+    }
+}
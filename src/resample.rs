@@ -0,0 +1,77 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use geo_types::Point;
+use geoutils::Location;
+use gpx::Waypoint;
+use time::Duration;
+use time::OffsetDateTime;
+
+/// Points every `spacing` meters along `waypoints`, measured horizontally,
+/// from the first point to the last one, which are both kept. The position,
+/// elevation, and time of each new point are interpolated linearly between
+/// the points on either side of it, and elevations and times are left out
+/// where either of those points doesn't have one.
+pub fn resample(waypoints: &[Waypoint], spacing: f64) -> Vec<Waypoint> {
+    let (Some(first), Some(last)) = (waypoints.first(), waypoints.last()) else {
+        return Vec::new();
+    };
+    if waypoints.len() == 1 {
+        return vec![first.clone()];
+    }
+
+    let mut resampled = vec![first.clone()];
+    let mut travelled = 0.;
+    for pair in waypoints.windows(2) {
+        let leg = location(&pair[0])
+            .haversine_distance_to(&location(&pair[1]))
+            .meters();
+        // counted rather than added up, so that rounding doesn't build up
+        // over long routes
+        let mut next = resampled.len() as f64 * spacing;
+        while leg > 0. && next < travelled + leg {
+            resampled.push(interpolate(&pair[0], &pair[1], (next - travelled) / leg));
+            next = resampled.len() as f64 * spacing;
+        }
+        travelled += leg;
+    }
+    resampled.push(last.clone());
+    resampled
+}
+
+/// The point `fraction` of the way from `a` to `b`.
+fn interpolate(a: &Waypoint, b: &Waypoint, fraction: f64) -> Waypoint {
+    let between = |a: f64, b: f64| a + (b - a) * fraction;
+    let (from, to) = (a.point(), b.point());
+    let mut waypoint = Waypoint::new(Point::new(
+        between(from.x(), to.x()),
+        between(from.y(), to.y()),
+    ));
+    if let (Some(a), Some(b)) = (a.elevation, b.elevation) {
+        waypoint.elevation = Some(between(a, b));
+    }
+    if let (Some(a), Some(b)) = (a.time, b.time) {
+        let (a, b) = (OffsetDateTime::from(a), OffsetDateTime::from(b));
+        // to the second, like most GPX files, since fractions of a second
+        // don't always read back exactly
+        let offset = ((b - a).as_seconds_f64() * fraction).round();
+        waypoint.time = Some((a + Duration::seconds(offset as i64)).into());
+    }
+    waypoint
+}
+
+fn location(waypoint: &Waypoint) -> Location {
+    let point = waypoint.point();
+    Location::new(point.y(), point.x())
+}
@@ -0,0 +1,72 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use geo_types::Point;
+use gpx::Waypoint;
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.;
+
+/// Leaves out the points of `waypoints` that are within `tolerance` meters of
+/// the line between the points kept on either side of them, with the
+/// Douglas-Peucker algorithm. The first and last points are always kept.
+pub fn simplify(waypoints: &mut Vec<Waypoint>, tolerance: f64) {
+    if waypoints.len() < 3 {
+        return;
+    }
+
+    let mut keep = vec![false; waypoints.len()];
+    keep[0] = true;
+    keep[waypoints.len() - 1] = true;
+    // a stack instead of recursion, since tracks can have millions of points
+    let mut spans = vec![(0, waypoints.len() - 1)];
+    while let Some((first, last)) = spans.pop() {
+        let (start, end) = (waypoints[first].point(), waypoints[last].point());
+        let farthest = (first + 1..last)
+            .map(|index| (index, distance_to_leg(waypoints[index].point(), start, end)))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((index, _)) = farthest.filter(|(_, meters)| *meters > tolerance) {
+            keep[index] = true;
+            spans.push((first, index));
+            spans.push((index, last));
+        }
+    }
+
+    let mut kept = keep.into_iter();
+    waypoints.retain(|_| kept.next().unwrap());
+}
+
+/// Meters from `point` to the nearest place on the straight leg from `start`
+/// to `end`. Measuring to the leg rather than the whole line through it keeps
+/// the far end of an out and back route.
+fn distance_to_leg(point: Point, start: Point, end: Point) -> f64 {
+    let (leg_x, leg_y) = offset(start, end);
+    let (x, y) = offset(start, point);
+    let length_squared = leg_x * leg_x + leg_y * leg_y;
+    let along = if length_squared > 0. {
+        ((x * leg_x + y * leg_y) / length_squared).clamp(0., 1.)
+    } else {
+        0.
+    };
+    (x - along * leg_x).hypot(y - along * leg_y)
+}
+
+/// Meters east and north from `from` to `to`, on a flat projection around
+/// `from`, which is close enough over the length of a leg.
+fn offset(from: Point, to: Point) -> (f64, f64) {
+    let squash = from.y().to_radians().cos();
+    (
+        (to.x() - from.x()).to_radians() * squash * EARTH_RADIUS_METERS,
+        (to.y() - from.y()).to_radians() * EARTH_RADIUS_METERS,
+    )
+}
@@ -0,0 +1,415 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::sync::Arc;
+
+use gpx::Gpx;
+use gpx::Waypoint;
+
+use crate::error::Result;
+use crate::error::SplitError;
+use crate::limit::Limit;
+use crate::limit::Measure;
+use crate::limit::Overlap;
+use crate::limit::SpeedModel;
+use crate::limit::SplitOptions;
+use crate::limit::Subsequences;
+use crate::progress::Progress;
+use crate::progress::ProgressCallback;
+use crate::split;
+use crate::strategy::SplitStrategy;
+
+/// Returns an error for an option that doesn't make sense.
+macro_rules! invalid {
+    ($($arg:tt)*) => {
+        return Err(SplitError::InvalidOption(format!($($arg)*)))
+    };
+}
+
+/// Splits routes with options that have been checked, made with
+/// `Splitter::builder`. The options are the same as those of the command.
+#[derive(Clone)]
+pub struct Splitter {
+    options: SplitOptions,
+}
+
+impl Splitter {
+    pub fn builder() -> SplitterBuilder {
+        SplitterBuilder::default()
+    }
+
+    pub fn options(&self) -> &SplitOptions {
+        &self.options
+    }
+
+    /// Splits the first segment of the first track of `gpx`, the same as
+    /// `split`.
+    pub fn split(&self, gpx: Gpx) -> impl Iterator<Item = Result<Gpx>> {
+        split(gpx, self.options.clone())
+    }
+
+    /// Splits a route that's read from `waypoints`.
+    pub fn subsequences<Waypoints: IntoIterator<Item = Waypoint>>(
+        &self,
+        waypoints: Waypoints,
+    ) -> Subsequences<Waypoints::IntoIter> {
+        Subsequences::new(waypoints, self.options.clone())
+    }
+}
+
+/// Whether `value` can be split by as a distance, a duration, an amount of
+/// climbing, or a speed, which is when it's a finite number above zero. The
+/// command checks its options with this too, so that it accepts the same
+/// values as `SplitterBuilder`.
+pub fn is_positive(value: f64) -> bool {
+    value.is_finite() && value > 0.
+}
+
+/// Options for the simple interfaces to other languages, which only split by
+/// distance and number of points. Either can be zero to not use it.
+#[cfg(any(feature = "ffi", feature = "wasm"))]
+pub(crate) fn simple_options(km_per_file: f64, max_points: u32) -> Result<SplitOptions> {
+    let mut builder = Splitter::builder();
+    if km_per_file > 0. {
+        builder = builder.km_per_file(km_per_file);
+    }
+    if max_points > 0 {
+        builder = builder.max_points(max_points as usize);
+    }
+    Ok(builder.build()?.options)
+}
+
+/// Options for a `Splitter`, which are checked by `build`. By default a single
+/// point is repeated at the start of each file and distances are measured
+/// horizontally, but at least one way to split the route has to be set.
+#[derive(Clone, Default)]
+pub struct SplitterBuilder {
+    km_per_file: Option<f64>,
+    hours_per_file: Option<f64>,
+    gain_per_file: Option<f64>,
+    est_hours_per_file: Option<f64>,
+    speed: Option<f64>,
+    climbing_per_hour: Option<f64>,
+    max_points: Option<usize>,
+    at_km: Vec<f64>,
+    overlap_points: Option<usize>,
+    overlap_km: Option<f64>,
+    no_overlap: bool,
+    exclude_overlap: bool,
+    three_d_distance: bool,
+    strategies: Vec<Box<dyn SplitStrategy>>,
+    progress: Option<ProgressCallback>,
+}
+
+impl SplitterBuilder {
+    /// Kilometers to include in each file.
+    pub fn km_per_file(mut self, km: f64) -> SplitterBuilder {
+        self.km_per_file = Some(km);
+        self
+    }
+
+    /// Hours between the timestamps of the points in each file.
+    pub fn hours_per_file(mut self, hours: f64) -> SplitterBuilder {
+        self.hours_per_file = Some(hours);
+        self
+    }
+
+    /// Meters of climbing to include in each file.
+    pub fn gain_per_file(mut self, meters: f64) -> SplitterBuilder {
+        self.gain_per_file = Some(meters);
+        self
+    }
+
+    /// Hours of estimated riding time to include in each file, which needs a
+    /// `speed`.
+    pub fn est_hours_per_file(mut self, hours: f64) -> SplitterBuilder {
+        self.est_hours_per_file = Some(hours);
+        self
+    }
+
+    /// Average speed on flat ground in kilometers per hour, to estimate riding
+    /// time.
+    pub fn speed(mut self, km_per_hour: f64) -> SplitterBuilder {
+        self.speed = Some(km_per_hour);
+        self
+    }
+
+    /// Meters of climbing that add an hour to the estimated riding time.
+    pub fn climbing_per_hour(mut self, meters: f64) -> SplitterBuilder {
+        self.climbing_per_hour = Some(meters);
+        self
+    }
+
+    /// Most points in each file, including those repeated from the file
+    /// before.
+    pub fn max_points(mut self, points: usize) -> SplitterBuilder {
+        self.max_points = Some(points);
+        self
+    }
+
+    /// Splits the route at this many kilometers from its start, which can be
+    /// done more than once.
+    pub fn at_km(mut self, km: f64) -> SplitterBuilder {
+        self.at_km.push(km);
+        self
+    }
+
+    /// Number of points from the end of each file to repeat at the start of
+    /// the next one.
+    pub fn overlap_points(mut self, points: usize) -> SplitterBuilder {
+        self.overlap_points = Some(points);
+        self
+    }
+
+    /// Kilometers from the end of each file to repeat at the start of the next
+    /// one, instead of a number of points.
+    pub fn overlap_km(mut self, km: f64) -> SplitterBuilder {
+        self.overlap_km = Some(km);
+        self
+    }
+
+    /// Doesn't repeat any points, so that the files are strictly disjoint.
+    pub fn no_overlap(mut self) -> SplitterBuilder {
+        self.no_overlap = true;
+        self
+    }
+
+    /// Whether to leave the legs between repeated points out of the limits.
+    pub fn exclude_overlap(mut self, exclude_overlap: bool) -> SplitterBuilder {
+        self.exclude_overlap = exclude_overlap;
+        self
+    }
+
+    /// Whether to include changes in elevation in distances.
+    pub fn three_d_distance(mut self, three_d_distance: bool) -> SplitterBuilder {
+        self.three_d_distance = three_d_distance;
+        self
+    }
+
+    /// Adds another way of deciding where to cut, which is treated like the
+    /// limits.
+    pub fn strategy(mut self, strategy: impl SplitStrategy + 'static) -> SplitterBuilder {
+        self.strategies.push(Box::new(strategy));
+        self
+    }
+
+    /// Calls `progress` as the route is split, for showing progress on long
+    /// routes.
+    pub fn progress(
+        mut self,
+        progress: impl Fn(Progress) + Send + Sync + 'static,
+    ) -> SplitterBuilder {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Checks the options, with an error for the first one that doesn't make
+    /// sense, the same as the command.
+    pub fn build(self) -> Result<Splitter> {
+        for (value, name) in [
+            (self.km_per_file, "km_per_file"),
+            (self.hours_per_file, "hours_per_file"),
+            (self.gain_per_file, "gain_per_file"),
+            (self.est_hours_per_file, "est_hours_per_file"),
+            (self.speed, "speed"),
+            (self.climbing_per_hour, "climbing_per_hour"),
+            (self.overlap_km, "overlap_km"),
+        ] {
+            if value.is_some_and(|value| !is_positive(value)) {
+                invalid!("{name} must be a positive number");
+            }
+        }
+        if self.at_km.iter().any(|km| !(km.is_finite() && *km >= 0.)) {
+            invalid!("at_km must not be negative");
+        }
+        if self.est_hours_per_file.is_some() && self.speed.is_none() {
+            invalid!("est_hours_per_file needs a speed to estimate riding time");
+        }
+
+        let overlap = match (self.overlap_points, self.overlap_km, self.no_overlap) {
+            (None, None, false) => Overlap::Points(1),
+            (Some(0), None, false) => {
+                invalid!("overlap_points must be at least 1, or use no_overlap")
+            }
+            (Some(points), None, false) => Overlap::Points(points),
+            (None, Some(km), false) => Overlap::Meters(km * 1000.),
+            (None, None, true) => Overlap::None,
+            _ => invalid!("only one of overlap_points, overlap_km, and no_overlap can be used"),
+        };
+        if self.exclude_overlap && self.no_overlap {
+            invalid!("exclude_overlap can't be used with no_overlap");
+        }
+        let min_points = overlap.min_points().max(2);
+        if self
+            .max_points
+            .is_some_and(|max_points| max_points < min_points)
+        {
+            invalid!("max_points must be at least {min_points} to fit the overlap");
+        }
+
+        let mut limits = Vec::new();
+        if let Some(km_per_file) = self.km_per_file {
+            limits.push(Limit::Meters(km_per_file * 1000.));
+        }
+        if let Some(hours_per_file) = self.hours_per_file {
+            limits.push(Limit::Seconds(hours_per_file * 3600.));
+        }
+        if let Some(gain_per_file) = self.gain_per_file {
+            limits.push(Limit::Gain(gain_per_file));
+        }
+        if let Some(est_hours_per_file) = self.est_hours_per_file {
+            limits.push(Limit::EstimatedSeconds(est_hours_per_file * 3600.));
+        }
+        if limits.is_empty()
+            && self.max_points.is_none()
+            && self.at_km.is_empty()
+            && self.strategies.is_empty()
+        {
+            invalid!("at least one way to split the route is needed, like km_per_file");
+        }
+
+        let mut cut_meters: Vec<f64> = self.at_km.iter().map(|km| km * 1000.).collect();
+        cut_meters.sort_by(f64::total_cmp);
+
+        let options = SplitOptions {
+            limits,
+            max_points: self.max_points,
+            measure: if self.three_d_distance {
+                Measure::Slope
+            } else {
+                Measure::Horizontal
+            },
+            speed_model: self.speed.map(|speed| SpeedModel {
+                meters_per_second: speed / 3.6,
+                seconds_per_meter_climbed: self
+                    .climbing_per_hour
+                    .map_or(0., |climbing_per_hour| 3600. / climbing_per_hour),
+            }),
+            cut_meters,
+            overlap,
+            exclude_overlap: self.exclude_overlap,
+            strategies: self.strategies,
+            progress: self.progress,
+            ..SplitOptions::default()
+        };
+        options.validate()?;
+        Ok(Splitter { options })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::tests::route;
+    use crate::tests::waypoints;
+
+    fn rejects(builder: SplitterBuilder, message: &str) {
+        match builder.build() {
+            Err(SplitError::InvalidOption(error)) => {
+                assert!(error.contains(message), "{error}")
+            }
+            Err(error) => panic!("{error}"),
+            Ok(_) => panic!("options with {message} were accepted"),
+        }
+    }
+
+    #[test]
+    fn rejects_bad_options() {
+        rejects(Splitter::builder(), "at least one way to split");
+        for value in [0., -1., f64::NAN, f64::INFINITY] {
+            rejects(Splitter::builder().km_per_file(value), "km_per_file");
+            rejects(Splitter::builder().hours_per_file(value), "hours_per_file");
+            rejects(Splitter::builder().gain_per_file(value), "gain_per_file");
+            rejects(
+                Splitter::builder().est_hours_per_file(1.).speed(value),
+                "speed",
+            );
+            rejects(
+                Splitter::builder().km_per_file(1.).overlap_km(value),
+                "overlap_km",
+            );
+        }
+        rejects(Splitter::builder().at_km(-1.), "at_km");
+        rejects(Splitter::builder().est_hours_per_file(1.), "needs a speed");
+        rejects(
+            Splitter::builder().km_per_file(1.).overlap_points(0),
+            "overlap_points",
+        );
+        rejects(
+            Splitter::builder()
+                .km_per_file(1.)
+                .overlap_points(2)
+                .no_overlap(),
+            "only one of",
+        );
+        rejects(
+            Splitter::builder()
+                .km_per_file(1.)
+                .no_overlap()
+                .exclude_overlap(true),
+            "exclude_overlap",
+        );
+        rejects(Splitter::builder().max_points(1), "max_points");
+        rejects(
+            Splitter::builder().max_points(3).overlap_points(3),
+            "max_points",
+        );
+    }
+
+    #[test]
+    fn builds_options() {
+        let splitter = Splitter::builder()
+            .km_per_file(100.)
+            .at_km(50.)
+            .at_km(20.)
+            .overlap_points(2)
+            .build()
+            .unwrap();
+        let options = splitter.options();
+        assert!(matches!(options.limits[..], [Limit::Meters(meters)] if meters == 100_000.));
+        assert_eq!(options.cut_meters, [20_000., 50_000.]);
+        assert!(matches!(options.overlap, Overlap::Points(2)));
+    }
+
+    #[test]
+    fn reports_progress() {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let splitter = Splitter::builder()
+            .max_points(3)
+            .progress({
+                let reports = reports.clone();
+                move |progress| reports.lock().unwrap().push(progress)
+            })
+            .build()
+            .unwrap();
+        let parts: Vec<Gpx> = splitter
+            .split(route(waypoints(5)))
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(parts.len(), 2);
+
+        let finished: Vec<(usize, usize)> = reports
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|progress| match progress {
+                Progress::PartFinished { index, points } => Some((*index, *points)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(finished, [(0, 3), (1, 3)]);
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use gpx::Waypoint;
+
+use crate::error::Result;
+use crate::limit::time;
+use crate::limit::Measure;
+
+/// A way of deciding where to cut a route, in addition to the limits in
+/// `SplitOptions`. It's told about each leg of a subsequence in order, and a
+/// subsequence is cut off after the first leg that it says to cut after, the
+/// same as when a limit is reached. That includes the legs between the
+/// waypoints carried over from the previous subsequence, unless
+/// `exclude_overlap` is set.
+///
+/// Strategies need to be `Clone`, since the options they're in are copied for
+/// each route that's split, and `Send`, so that the options can be moved to
+/// another thread to split on.
+pub trait SplitStrategy: CloneStrategy + Send {
+    /// Called before the first leg of each subsequence.
+    fn start(&mut self) {}
+
+    /// Whether to cut the subsequence off after `next`, which is the waypoint
+    /// after `prev` in the route.
+    fn should_cut(&mut self, prev: &Waypoint, next: &Waypoint) -> Result<bool>;
+}
+
+/// Copies a boxed `SplitStrategy`, which is implemented for every strategy
+/// that's `Clone`.
+pub trait CloneStrategy {
+    fn clone_strategy(&self) -> Box<dyn SplitStrategy>;
+}
+
+impl<Strategy: SplitStrategy + Clone + 'static> CloneStrategy for Strategy {
+    fn clone_strategy(&self) -> Box<dyn SplitStrategy> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn SplitStrategy> {
+    fn clone(&self) -> Box<dyn SplitStrategy> {
+        self.clone_strategy()
+    }
+}
+
+/// Cuts once a subsequence is longer than a distance.
+#[derive(Clone)]
+pub struct MaxDistance {
+    meters: f64,
+    measure: Measure,
+    total: f64,
+}
+
+impl MaxDistance {
+    pub fn new(meters: f64, measure: Measure) -> MaxDistance {
+        MaxDistance {
+            meters,
+            measure,
+            total: 0.,
+        }
+    }
+}
+
+impl SplitStrategy for MaxDistance {
+    fn start(&mut self) {
+        self.total = 0.;
+    }
+
+    fn should_cut(&mut self, prev: &Waypoint, next: &Waypoint) -> Result<bool> {
+        self.total += self.measure.distance(prev, next)?;
+        Ok(self.total > self.meters)
+    }
+}
+
+/// Cuts once the timestamps in a subsequence span more than a number of
+/// seconds. Every waypoint needs a timestamp.
+#[derive(Clone)]
+pub struct MaxDuration {
+    seconds: f64,
+    total: f64,
+}
+
+impl MaxDuration {
+    pub fn new(seconds: f64) -> MaxDuration {
+        MaxDuration { seconds, total: 0. }
+    }
+}
+
+impl SplitStrategy for MaxDuration {
+    fn start(&mut self) {
+        self.total = 0.;
+    }
+
+    fn should_cut(&mut self, prev: &Waypoint, next: &Waypoint) -> Result<bool> {
+        self.total += (time(next)? - time(prev)?).as_seconds_f64();
+        Ok(self.total > self.seconds)
+    }
+}
+
+/// Cuts once a subsequence has a number of waypoints. Unlike `max_points` in
+/// `SplitOptions`, this is deferred past climbs and can be moved by snapping,
+/// like the other strategies.
+#[derive(Clone)]
+pub struct MaxPoints {
+    points: usize,
+    count: usize,
+}
+
+impl MaxPoints {
+    pub fn new(points: usize) -> MaxPoints {
+        MaxPoints { points, count: 1 }
+    }
+}
+
+impl SplitStrategy for MaxPoints {
+    fn start(&mut self) {
+        self.count = 1;
+    }
+
+    fn should_cut(&mut self, _prev: &Waypoint, _next: &Waypoint) -> Result<bool> {
+        self.count += 1;
+        Ok(self.count >= self.points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gpx::Gpx;
+
+    use super::*;
+    use crate::error::SplitError;
+    use crate::split_all;
+    use crate::tests::route;
+    use crate::tests::waypoints;
+    use crate::Splitter;
+
+    /// Number of points in each part of a route of `count` points split with
+    /// `strategy`.
+    fn part_sizes(count: usize, strategy: impl SplitStrategy + 'static) -> Result<Vec<usize>> {
+        split_route(route(waypoints(count)), strategy)
+    }
+
+    fn split_route(gpx: Gpx, strategy: impl SplitStrategy + 'static) -> Result<Vec<usize>> {
+        let splitter = Splitter::builder().strategy(strategy).build()?;
+        let parts = split_all(gpx, splitter.options().clone())?;
+        Ok(parts
+            .iter()
+            .map(|part| part.tracks[0].segments[0].points.len())
+            .collect())
+    }
+
+    #[test]
+    fn max_distance_cuts_after_passing_the_distance() {
+        // the points are about 1.1 km apart
+        let sizes = part_sizes(7, MaxDistance::new(2000., Measure::Horizontal)).unwrap();
+        assert_eq!(sizes, [3, 3, 3]);
+    }
+
+    #[test]
+    fn max_duration_cuts_after_passing_the_duration() {
+        let sizes = part_sizes(7, MaxDuration::new(150.)).unwrap();
+        assert_eq!(sizes, [4, 4]);
+    }
+
+    #[test]
+    fn max_duration_needs_times() {
+        let mut waypoints = waypoints(3);
+        waypoints[1].time = None;
+        let error = split_route(route(waypoints), MaxDuration::new(150.)).unwrap_err();
+        assert!(matches!(error, SplitError::MissingTime { .. }));
+    }
+
+    #[test]
+    fn max_points_cuts_at_the_number_of_points() {
+        let sizes = part_sizes(7, MaxPoints::new(3)).unwrap();
+        assert_eq!(sizes, [3, 3, 3]);
+    }
+
+    #[test]
+    fn strategies_start_again_for_each_part() {
+        let mut strategy: Box<dyn SplitStrategy> = Box::new(MaxPoints::new(3));
+        let waypoints = waypoints(3);
+        assert!(!strategy.should_cut(&waypoints[0], &waypoints[1]).unwrap());
+        assert!(strategy.should_cut(&waypoints[1], &waypoints[2]).unwrap());
+        let mut copy = strategy.clone();
+        strategy.start();
+        assert!(!strategy.should_cut(&waypoints[0], &waypoints[1]).unwrap());
+        // the copy keeps counting from where it was
+        assert!(copy.should_cut(&waypoints[1], &waypoints[2]).unwrap());
+    }
+}
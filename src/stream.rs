@@ -0,0 +1,250 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io::Read;
+use std::io::Write;
+
+use anyhow::bail;
+use anyhow::Result;
+use geo_types::Point;
+use gpx::Waypoint;
+use xml::reader::XmlEvent;
+use xml::EmitterConfig;
+use xml::EventWriter;
+use xml::ParserConfig;
+
+/// Limits on the size of each file, like the options of the same names.
+pub struct StreamLimits {
+    pub meters: Option<f64>,
+    pub max_points: Option<usize>,
+}
+
+/// Splits the first segment of the first track in a GPX file as it's read,
+/// for files too large to hold in memory. Each point is written to the
+/// current file as soon as it's read, so only everything before the first
+/// point, like the waypoints, is held onto. Like splitting normally, each file
+/// after the first starts with the last point of the file before it.
+///
+/// Files are created with `create`, which is given the position of each file
+/// starting from zero, and the distance between points is measured with
+/// `leg`. Everything in the file is copied as it is, including extensions
+/// unless `strip_extensions` is set. Returns the number of files.
+pub fn split_streaming<R: Read, W: Write>(
+    reader: R,
+    limits: &StreamLimits,
+    strip_extensions: bool,
+    leg: impl Fn(&Waypoint, &Waypoint) -> Result<f64>,
+    mut create: impl FnMut(usize) -> Result<W>,
+) -> Result<usize> {
+    let reader = ParserConfig::new()
+        .trim_whitespace(true)
+        .cdata_to_characters(true)
+        .create_reader(reader);
+
+    let mut path: Vec<String> = Vec::new();
+    let mut tracks = 0;
+    let mut segments = 0;
+
+    // everything before the first point, which starts every file
+    let mut prefix: Vec<XmlEvent> = Vec::new();
+    let mut started = false;
+    let mut point: Option<StreamPoint> = None;
+
+    let mut output: Option<Output<W>> = None;
+    let mut files = 0;
+    let mut previous: Option<StreamPoint> = None;
+    let mut cut_pending = false;
+    // depth inside an extensions element that's being left out
+    let mut skipping = 0;
+
+    for event in reader {
+        let event = event?;
+
+        if strip_extensions {
+            match &event {
+                XmlEvent::StartElement { name, .. }
+                    if skipping > 0 || name.local_name == "extensions" =>
+                {
+                    skipping += 1;
+                    continue;
+                }
+                XmlEvent::EndElement { .. } if skipping > 0 => {
+                    skipping -= 1;
+                    continue;
+                }
+                _ if skipping > 0 => continue,
+                _ => {}
+            }
+        }
+
+        // inside a point, which is written once it's been read to the end
+        if let Some(current) = &mut point {
+            match &event {
+                XmlEvent::StartElement { name, .. } => {
+                    current.depth += 1;
+                    current.in_elevation = name.local_name == "ele" && current.depth == 2;
+                }
+                XmlEvent::EndElement { .. } => {
+                    current.depth -= 1;
+                    current.in_elevation = false;
+                }
+                XmlEvent::Characters(text) if current.in_elevation => {
+                    current.waypoint.elevation = text.trim().parse().ok();
+                }
+                _ => {}
+            }
+            current.events.push(event);
+            if current.depth > 0 {
+                continue;
+            }
+            let current = point.take().unwrap();
+
+            if cut_pending {
+                if let Some(output) = output.take() {
+                    output.finish()?;
+                }
+            }
+            let output = match &mut output {
+                Some(output) => output,
+                None => {
+                    let mut started = Output::start(create(files)?, &prefix)?;
+                    files += 1;
+                    if let Some(previous) = &previous {
+                        started.write(previous)?;
+                    }
+                    output.insert(started)
+                }
+            };
+
+            output.write(&current)?;
+            if let Some(previous) = &previous {
+                output.meters += leg(&previous.waypoint, &current.waypoint)?;
+            }
+            cut_pending = limits.meters.is_some_and(|meters| output.meters > meters)
+                || limits
+                    .max_points
+                    .is_some_and(|max_points| output.points >= max_points);
+            previous = Some(current);
+            continue;
+        }
+
+        match &event {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                match name.local_name.as_str() {
+                    "trk" if path.len() == 1 => tracks += 1,
+                    "trkseg" if path.len() == 2 => segments += 1,
+                    "trkpt" if path.len() == 3 && tracks == 1 && segments == 1 => {
+                        let value = |name: &str| {
+                            attributes
+                                .iter()
+                                .find(|attribute| attribute.name.local_name == name)
+                                .and_then(|attribute| attribute.value.trim().parse::<f64>().ok())
+                        };
+                        let (Some(latitude), Some(longitude)) = (value("lat"), value("lon")) else {
+                            bail!("gpx point is missing its position");
+                        };
+                        started = true;
+                        point = Some(StreamPoint {
+                            waypoint: Waypoint::new(Point::new(longitude, latitude)),
+                            events: vec![event],
+                            depth: 1,
+                            in_elevation: false,
+                        });
+                        continue;
+                    }
+                    _ => {}
+                }
+                path.push(name.local_name.clone());
+            }
+            // the rest of the file after the first segment is left out
+            XmlEvent::EndElement { name } if name.local_name == "trkseg" && started => break,
+            XmlEvent::EndElement { .. } => {
+                path.pop();
+            }
+            _ => {}
+        }
+
+        if !started {
+            prefix.push(event);
+        }
+    }
+
+    match output {
+        Some(output) => output.finish()?,
+        None => bail!("gpx file has no points in the first segment of the first track"),
+    }
+    Ok(files)
+}
+
+/// A point as it's read, with the events to write it again.
+struct StreamPoint {
+    waypoint: Waypoint,
+    events: Vec<XmlEvent>,
+    depth: usize,
+    in_elevation: bool,
+}
+
+/// The file that points are currently being written to.
+struct Output<W: Write> {
+    writer: EventWriter<W>,
+    /// Number of elements open after the prefix, which are closed at the end.
+    open: usize,
+    points: usize,
+    meters: f64,
+}
+
+impl<W: Write> Output<W> {
+    fn start(writer: W, prefix: &[XmlEvent]) -> Result<Output<W>> {
+        let mut writer = EmitterConfig::new()
+            .perform_indent(true)
+            .create_writer(writer);
+        let mut open = 0;
+        for event in prefix {
+            match event {
+                XmlEvent::StartElement { .. } => open += 1,
+                XmlEvent::EndElement { .. } => open -= 1,
+                _ => {}
+            }
+            if let Some(event) = event.as_writer_event() {
+                writer.write(event)?;
+            }
+        }
+        Ok(Output {
+            writer,
+            open,
+            points: 0,
+            meters: 0.,
+        })
+    }
+
+    fn write(&mut self, point: &StreamPoint) -> Result<()> {
+        for event in &point.events {
+            if let Some(event) = event.as_writer_event() {
+                self.writer.write(event)?;
+            }
+        }
+        self.points += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        for _ in 0..self.open {
+            self.writer.write(xml::writer::XmlEvent::end_element())?;
+        }
+        self.writer.into_inner().flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io::Write;
+
+use anyhow::Result;
+
+use crate::kml::Line;
+use crate::markup::XmlWriter;
+
+const NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+const WIDTH: f64 = 600.;
+const HEIGHT: f64 = 200.;
+/// Space around the plot for the title and the labels on the axes.
+const LEFT: f64 = 50.;
+const RIGHT: f64 = 15.;
+const TOP: f64 = 40.;
+const BOTTOM: f64 = 25.;
+
+/// The smallest range of elevations to draw, so that flat parts don't have
+/// their noise stretched over the whole height.
+const MIN_ELEVATION_SPAN: f64 = 50.;
+
+/// Kilometers between the distance markers, the smallest of which that
+/// doesn't need more than `MAX_MARKERS` is used.
+const MARKER_STEPS: [f64; 11] = [0.5, 1., 2., 5., 10., 20., 50., 100., 200., 500., 1000.];
+const MAX_MARKERS: f64 = 8.;
+
+/// Size of the longer side of a map, without its margins.
+const MAP_SIZE: f64 = 800.;
+const MAP_MARGIN: f64 = 20.;
+
+/// Line colors for each part of a map, the same as in KML files.
+const COLORS: [&str; 6] = [
+    "#ff0000", "#0000ff", "#00aa00", "#ff00ff", "#ffaa00", "#00aaff",
+];
+
+/// The elevation profile of a part of a route.
+pub struct Profile<'a> {
+    pub title: &'a str,
+    /// A line under the title, like the part's distance and climbing.
+    pub details: &'a str,
+    /// Where the part starts and ends, in meters along the whole route.
+    pub start_meters: f64,
+    pub end_meters: f64,
+    /// Meters along the whole route and elevation of each point that has an
+    /// elevation.
+    pub points: &'a [(f64, f64)],
+}
+
+/// Writes `profile` as an SVG image, with markers every few kilometers along
+/// the route and dashed lines where the part was split from the rest of it.
+pub fn write_profile<W: Write>(writer: W, profile: &Profile) -> Result<()> {
+    let start = profile.start_meters;
+    let length = (profile.end_meters - start).max(1.);
+    let (mut low, mut high) = profile.points.iter().fold(
+        (f64::INFINITY, f64::NEG_INFINITY),
+        |(low, high), (_, elevation)| (low.min(*elevation), high.max(*elevation)),
+    );
+    if profile.points.is_empty() {
+        (low, high) = (0., MIN_ELEVATION_SPAN);
+    } else if high - low < MIN_ELEVATION_SPAN {
+        let padding = (MIN_ELEVATION_SPAN - (high - low)) / 2.;
+        (low, high) = (low - padding, high + padding);
+    }
+
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let x = |meters: f64| LEFT + (meters - start) / length * plot_width;
+    let y = |elevation: f64| TOP + (high - elevation) / (high - low) * plot_height;
+    let baseline = TOP + plot_height;
+
+    let width = number(WIDTH);
+    let height = number(HEIGHT);
+    let view_box = format!("0 0 {width} {height}");
+    let mut writer = XmlWriter::with_attributes(
+        writer,
+        "svg",
+        NAMESPACE,
+        &[
+            ("width", &width),
+            ("height", &height),
+            ("viewBox", &view_box),
+            ("font-family", "sans-serif"),
+            ("font-size", "11"),
+        ],
+    )?;
+    writer.empty(
+        "rect",
+        &[("width", &width), ("height", &height), ("fill", "white")],
+    )?;
+    writer.text_with(
+        "text",
+        &[
+            ("x", &number(LEFT)),
+            ("y", "16"),
+            ("font-size", "14"),
+            ("font-weight", "bold"),
+        ],
+        profile.title,
+    )?;
+    writer.text_with(
+        "text",
+        &[("x", &number(LEFT)), ("y", "31"), ("fill", "#555")],
+        profile.details,
+    )?;
+
+    // the lowest and highest elevations, with faint lines across the plot
+    for elevation in [low, high] {
+        line(
+            &mut writer,
+            (LEFT, y(elevation)),
+            (WIDTH - RIGHT, y(elevation)),
+            &[("stroke", "#ddd")],
+        )?;
+        writer.text_with(
+            "text",
+            &[
+                ("x", &number(LEFT - 4.)),
+                ("y", &number(y(elevation) + 4.)),
+                ("text-anchor", "end"),
+            ],
+            &format!("{elevation:.0} m"),
+        )?;
+    }
+
+    if profile.points.is_empty() {
+        writer.text_with(
+            "text",
+            &[
+                ("x", &number(LEFT + plot_width / 2.)),
+                ("y", &number(TOP + plot_height / 2.)),
+                ("text-anchor", "middle"),
+                ("fill", "#555"),
+            ],
+            "no elevations",
+        )?;
+    } else {
+        let line_points: Vec<String> = profile
+            .points
+            .iter()
+            .map(|(meters, elevation)| format!("{},{}", number(x(*meters)), number(y(*elevation))))
+            .collect();
+        let line_points = line_points.join(" ");
+        let (first, _) = profile.points[0];
+        let (last, _) = profile.points[profile.points.len() - 1];
+        let area = format!(
+            "{},{baseline} {line_points} {},{baseline}",
+            number(x(first)),
+            number(x(last))
+        );
+        writer.empty("polygon", &[("points", &area), ("fill", "#cfe0f1")])?;
+        writer.empty(
+            "polyline",
+            &[
+                ("points", &line_points),
+                ("fill", "none"),
+                ("stroke", "#2b6cb0"),
+                ("stroke-width", "1.5"),
+                ("stroke-linejoin", "round"),
+            ],
+        )?;
+    }
+
+    // distance markers at round numbers of kilometers along the whole route
+    line(
+        &mut writer,
+        (LEFT, baseline),
+        (WIDTH - RIGHT, baseline),
+        &[("stroke", "#333")],
+    )?;
+    let step = MARKER_STEPS
+        .into_iter()
+        .find(|step| length / 1000. / step <= MAX_MARKERS)
+        .unwrap_or(MARKER_STEPS[MARKER_STEPS.len() - 1]);
+    let mut km = (start / 1000. / step).ceil() * step;
+    while km * 1000. <= start + length {
+        let marker = x(km * 1000.);
+        line(
+            &mut writer,
+            (marker, baseline),
+            (marker, baseline + 4.),
+            &[("stroke", "#333")],
+        )?;
+        writer.text_with(
+            "text",
+            &[
+                ("x", &number(marker)),
+                ("y", &number(baseline + 16.)),
+                ("text-anchor", "middle"),
+            ],
+            &if step < 1. {
+                format!("{km:.1}")
+            } else {
+                format!("{km:.0}")
+            },
+        )?;
+        km += step;
+    }
+
+    // where the part was split from the rest of the route
+    for (meters, anchor, offset) in [(start, "start", 3.), (start + length, "end", -3.)] {
+        let boundary = x(meters);
+        line(
+            &mut writer,
+            (boundary, TOP),
+            (boundary, baseline),
+            &[("stroke", "#c53030"), ("stroke-dasharray", "4 3")],
+        )?;
+        writer.text_with(
+            "text",
+            &[
+                ("x", &number(boundary + offset)),
+                ("y", &number(TOP + 11.)),
+                ("text-anchor", anchor),
+                ("fill", "#c53030"),
+            ],
+            &format!("{:.1} km", meters / 1000.),
+        )?;
+    }
+
+    writer.finish()
+}
+
+fn line<W: Write>(
+    writer: &mut XmlWriter<W>,
+    from: (f64, f64),
+    to: (f64, f64),
+    style: &[(&str, &str)],
+) -> Result<()> {
+    let (x1, y1, x2, y2) = (number(from.0), number(from.1), number(to.0), number(to.1));
+    let mut attributes = vec![("x1", x1.as_str()), ("y1", &y1), ("x2", &x2), ("y2", &y2)];
+    attributes.extend_from_slice(style);
+    writer.empty("line", &attributes)
+}
+
+/// A coordinate, rounded to keep the files small.
+fn number(value: f64) -> String {
+    let rounded = (value * 10.).round() / 10.;
+    format!("{rounded}")
+}
+
+/// Writes an SVG map titled `title` with each of `lines` in its own color, its
+/// number at the middle of it, and a circle where each line after the first
+/// one starts. The map is projected so that it isn't stretched near the
+/// poles.
+pub fn write_map<W: Write>(writer: W, title: &str, lines: &[Line]) -> Result<()> {
+    let points = lines
+        .iter()
+        .flat_map(|line| line.waypoints)
+        .map(|waypoint| waypoint.point());
+    let (west, east, south, north) = points.fold(
+        (
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ),
+        |(west, east, south, north), point| {
+            (
+                west.min(point.x()),
+                east.max(point.x()),
+                south.min(point.y()),
+                north.max(point.y()),
+            )
+        },
+    );
+    let (west, east, south, north) = if west.is_finite() {
+        (west, east, south, north)
+    } else {
+        (0., 0., 0., 0.)
+    };
+    let squash = ((south + north) / 2.).to_radians().cos();
+    let degrees_wide = (east - west) * squash;
+    let degrees_high = north - south;
+    let scale = MAP_SIZE / degrees_wide.max(degrees_high).max(f64::EPSILON);
+    let x = |longitude: f64| MAP_MARGIN + (longitude - west) * squash * scale;
+    let y = |latitude: f64| TOP + (north - latitude) * scale;
+
+    let width = number(degrees_wide * scale + MAP_MARGIN * 2.);
+    let height = number(degrees_high * scale + TOP + MAP_MARGIN);
+    let view_box = format!("0 0 {width} {height}");
+    let mut writer = XmlWriter::with_attributes(
+        writer,
+        "svg",
+        NAMESPACE,
+        &[
+            ("width", &width),
+            ("height", &height),
+            ("viewBox", &view_box),
+            ("font-family", "sans-serif"),
+            ("font-size", "12"),
+        ],
+    )?;
+    writer.empty(
+        "rect",
+        &[("width", &width), ("height", &height), ("fill", "white")],
+    )?;
+    writer.text_with(
+        "text",
+        &[
+            ("x", &number(MAP_MARGIN)),
+            ("y", "22"),
+            ("font-size", "14"),
+            ("font-weight", "bold"),
+        ],
+        title,
+    )?;
+
+    for line in lines {
+        let color = COLORS[line.index % COLORS.len()];
+        let mut coordinates: Vec<String> = line
+            .waypoints
+            .iter()
+            .map(|waypoint| {
+                let point = waypoint.point();
+                format!("{},{}", number(x(point.x())), number(y(point.y())))
+            })
+            .collect();
+        // points that land on the same spot only make the file bigger
+        coordinates.dedup();
+        writer.empty(
+            "polyline",
+            &[
+                ("points", &coordinates.join(" ")),
+                ("fill", "none"),
+                ("stroke", color),
+                ("stroke-width", "2.5"),
+                ("stroke-linejoin", "round"),
+                ("stroke-linecap", "round"),
+            ],
+        )?;
+    }
+
+    // drawn after the lines so that they aren't hidden under them
+    for (number_in_map, line) in lines.iter().enumerate() {
+        let Some(first) = line.waypoints.first() else {
+            continue;
+        };
+        if number_in_map > 0 {
+            let point = first.point();
+            writer.empty(
+                "circle",
+                &[
+                    ("cx", &number(x(point.x()))),
+                    ("cy", &number(y(point.y()))),
+                    ("r", "5"),
+                    ("fill", "white"),
+                    ("stroke", "#333"),
+                    ("stroke-width", "2"),
+                ],
+            )?;
+        }
+        let middle = line.waypoints[line.waypoints.len() / 2].point();
+        writer.text_with(
+            "text",
+            &[
+                ("x", &number(x(middle.x()) + 6.)),
+                ("y", &number(y(middle.y()) - 6.)),
+                ("fill", COLORS[line.index % COLORS.len()]),
+                ("font-weight", "bold"),
+                ("stroke", "white"),
+                ("stroke-width", "3"),
+                ("paint-order", "stroke"),
+            ],
+            &(line.index + 1).to_string(),
+        )?;
+    }
+
+    writer.finish()
+}
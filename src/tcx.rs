@@ -0,0 +1,222 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io::Read;
+use std::io::Write;
+
+use anyhow::Context;
+use anyhow::Result;
+use geo_types::Point;
+use gpx::Gpx;
+use gpx::GpxVersion;
+use gpx::Track;
+use gpx::TrackSegment;
+use gpx::Waypoint;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+use time::UtcOffset;
+use xml::reader::XmlEvent;
+use xml::EventReader;
+
+use crate::course::Course;
+use crate::markup::XmlWriter;
+
+const NAMESPACE: &str = "http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2";
+
+/// Course point names longer than this don't match the TCX schema, and older
+/// devices reject the whole file because of them.
+const MAX_COURSE_POINT_NAME: usize = 10;
+
+/// Writes `course` as a Garmin TCX file with a single course.
+pub fn write_tcx<W: Write>(writer: W, course: &Course) -> Result<()> {
+    let mut writer = XmlWriter::new(writer, "TrainingCenterDatabase", NAMESPACE)?;
+    writer.start("Courses")?;
+    writer.start("Course")?;
+    writer.text("Name", &course.name)?;
+
+    // the whole course is a single lap
+    writer.start("Lap")?;
+    writer.text("TotalTimeSeconds", &format!("{:.1}", course.seconds()))?;
+    writer.text("DistanceMeters", &format!("{:.1}", course.meters()))?;
+    if let (Some(first), Some(last)) = (course.track.first(), course.track.last()) {
+        position(
+            &mut writer,
+            "BeginPosition",
+            first.latitude,
+            first.longitude,
+        )?;
+        position(&mut writer, "EndPosition", last.latitude, last.longitude)?;
+    }
+    writer.text("Intensity", "Active")?;
+    writer.end()?;
+
+    writer.start("Track")?;
+    for point in &course.track {
+        writer.start("Trackpoint")?;
+        writer.text("Time", &timestamp(point.time))?;
+        position(&mut writer, "Position", point.latitude, point.longitude)?;
+        if let Some(elevation) = point.elevation {
+            writer.text("AltitudeMeters", &format!("{elevation:.1}"))?;
+        }
+        writer.text("DistanceMeters", &format!("{:.1}", point.meters))?;
+        writer.end()?;
+    }
+    writer.end()?;
+
+    for course_point in &course.course_points {
+        let point = &course.track[course_point.index];
+        let name: String = course_point
+            .name
+            .chars()
+            .take(MAX_COURSE_POINT_NAME)
+            .collect();
+
+        writer.start("CoursePoint")?;
+        writer.text("Name", &name)?;
+        writer.text("Time", &timestamp(point.time))?;
+        position(&mut writer, "Position", point.latitude, point.longitude)?;
+        writer.text("PointType", course_point.kind.name())?;
+        writer.end()?;
+    }
+
+    writer.end()?;
+    writer.end()?;
+    writer.finish()
+}
+
+fn position<W: Write>(
+    writer: &mut XmlWriter<W>,
+    name: &str,
+    latitude: f64,
+    longitude: f64,
+) -> Result<()> {
+    writer.start(name)?;
+    writer.text("LatitudeDegrees", &latitude.to_string())?;
+    writer.text("LongitudeDegrees", &longitude.to_string())?;
+    writer.end()
+}
+
+fn timestamp(time: OffsetDateTime) -> String {
+    let time = time.to_offset(UtcOffset::UTC);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        time.year(),
+        time.month() as u8,
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+    )
+}
+
+/// Whether `data` looks like a TCX file, from the name of its root element
+/// near the start.
+pub fn is_tcx(data: &[u8]) -> bool {
+    let start = &data[..data.len().min(1024)];
+    String::from_utf8_lossy(start).contains("<TrainingCenterDatabase")
+}
+
+/// Reads the trackpoints of every course and activity in a TCX file into a
+/// GPX file with a single track, and any course points as waypoints.
+/// Trackpoints without a position, like those from indoor rides, are skipped.
+/// Their `DistanceMeters` aren't kept, since distances are measured again from
+/// the positions, the same as for GPX input.
+pub fn read_tcx<R: Read>(reader: R) -> Result<Gpx> {
+    let mut track = Track::new();
+    let mut segment = TrackSegment::new();
+    let mut course_points = Vec::new();
+
+    let mut path: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut point: Option<TcxPoint> = None;
+
+    for event in EventReader::new(reader) {
+        match event? {
+            XmlEvent::StartElement { name, .. } => {
+                if matches!(name.local_name.as_str(), "Trackpoint" | "CoursePoint") {
+                    point = Some(TcxPoint::default());
+                }
+                path.push(name.local_name);
+                text.clear();
+            }
+            XmlEvent::Characters(characters) | XmlEvent::CData(characters) => {
+                text.push_str(&characters);
+            }
+            XmlEvent::EndElement { .. } => {
+                let Some(element) = path.pop() else {
+                    continue;
+                };
+                let parent = path.last().map(String::as_str);
+                let text = text.trim();
+                match (element.as_str(), parent, &mut point) {
+                    ("Name", Some("Course"), _) => track.name = Some(text.to_owned()),
+                    ("Name", Some("CoursePoint"), Some(point)) => {
+                        point.name = Some(text.to_owned())
+                    }
+                    ("LatitudeDegrees", Some("Position"), Some(point)) => {
+                        point.latitude = text.parse().ok()
+                    }
+                    ("LongitudeDegrees", Some("Position"), Some(point)) => {
+                        point.longitude = text.parse().ok()
+                    }
+                    ("AltitudeMeters", _, Some(point)) => point.elevation = text.parse().ok(),
+                    ("Time", _, Some(point)) => {
+                        let time = OffsetDateTime::parse(text, &Rfc3339)
+                            .with_context(|| format!("invalid time {text} in tcx file"))?;
+                        point.time = Some(time);
+                    }
+                    ("PointType", _, Some(point)) => point.kind = Some(text.to_owned()),
+                    ("Trackpoint", _, _) => segment
+                        .points
+                        .extend(point.take().and_then(TcxPoint::waypoint)),
+                    ("CoursePoint", _, _) => {
+                        course_points.extend(point.take().and_then(TcxPoint::waypoint))
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    track.segments.push(segment);
+    Ok(Gpx {
+        version: GpxVersion::Gpx11,
+        waypoints: course_points,
+        tracks: vec![track],
+        ..Gpx::default()
+    })
+}
+
+/// A trackpoint or course point, as it's read.
+#[derive(Default)]
+struct TcxPoint {
+    latitude: Option<f64>,
+    longitude: Option<f64>,
+    elevation: Option<f64>,
+    time: Option<OffsetDateTime>,
+    name: Option<String>,
+    kind: Option<String>,
+}
+
+impl TcxPoint {
+    fn waypoint(self) -> Option<Waypoint> {
+        let mut waypoint = Waypoint::new(Point::new(self.longitude?, self.latitude?));
+        waypoint.elevation = self.elevation;
+        waypoint.time = self.time.map(Into::into);
+        waypoint.name = self.name;
+        waypoint.type_ = self.kind;
+        Some(waypoint)
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::mem::take;
+use std::str::FromStr;
+
+use anyhow::Result;
+
+/// A filename or name template, where placeholders like `{index}` are
+/// replaced with values for each file. A placeholder can be followed by a
+/// format like `{index:02}` to pad it to a width with zeros, `{end_km:.1}` to
+/// round a number to a precision, or `{gain:,}` to separate the thousands with
+/// commas. `{{` and `}}` are literal braces.
+#[derive(Clone, Debug)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Clone, Debug)]
+enum Piece {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+#[derive(Clone, Debug)]
+struct Placeholder {
+    name: String,
+    grouped: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+/// The value of a placeholder. Numbers are rounded to whole numbers unless the
+/// placeholder has a precision.
+pub enum Value {
+    Integer(usize),
+    /// An integer that's padded with zeros to a width unless the placeholder
+    /// has its own width.
+    Padded(usize, usize),
+    Number(f64),
+    Text(String),
+}
+
+impl Template {
+    /// Replaces each placeholder with the value from `lookup`, which should
+    /// return an error for names it doesn't know.
+    pub fn render(&self, lookup: impl Fn(&str) -> Result<Value>) -> Result<String> {
+        let mut rendered = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(literal) => rendered.push_str(literal),
+                Piece::Placeholder(placeholder) => {
+                    rendered.push_str(&placeholder.format(lookup(&placeholder.name)?))
+                }
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+impl Placeholder {
+    fn format(&self, value: Value) -> String {
+        if self.grouped {
+            let ungrouped = Placeholder {
+                grouped: false,
+                zero: false,
+                width: 0,
+                ..self.clone()
+            };
+            let grouped = group_thousands(&ungrouped.format(value));
+            let width = self.width;
+            return format!("{grouped:>width$}");
+        }
+
+        let width = self.width;
+        match (value, self.zero) {
+            (Value::Padded(integer, pad), _) if width == 0 => format!("{integer:0pad$}"),
+            (Value::Padded(integer, _), true) => format!("{integer:0width$}"),
+            (Value::Padded(integer, _), false) => format!("{integer:width$}"),
+            (Value::Integer(integer), true) => format!("{integer:0width$}"),
+            (Value::Integer(integer), false) => format!("{integer:width$}"),
+            (Value::Number(number), zero) => {
+                let precision = self.precision.unwrap_or(0);
+                if zero {
+                    format!("{number:0width$.precision$}")
+                } else {
+                    format!("{number:width$.precision$}")
+                }
+            }
+            (Value::Text(text), _) => format!("{text:width$}"),
+        }
+    }
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut chars = value.chars().peekable();
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => literal.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => literal.push('}'),
+                '{' => {
+                    let mut inside = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => inside.push(c),
+                            None => return Err(format!("template {value} has an unclosed {{")),
+                        }
+                    }
+
+                    if !literal.is_empty() {
+                        pieces.push(Piece::Literal(take(&mut literal)));
+                    }
+                    pieces.push(Piece::Placeholder(
+                        placeholder(&inside)
+                            .ok_or_else(|| format!("invalid placeholder {{{inside}}}"))?,
+                    ));
+                }
+                '}' => return Err(format!("template {value} has an unopened }}")),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(literal));
+        }
+
+        Ok(Template { pieces })
+    }
+}
+
+/// Parses the inside of a placeholder, like `index:02` or `end_km:.1`.
+fn placeholder(inside: &str) -> Option<Placeholder> {
+    let (name, format) = inside.split_once(':').unwrap_or((inside, ""));
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+
+    let (grouped, format) = match format.strip_prefix(',') {
+        Some(format) => (true, format),
+        None => (false, format),
+    };
+    let (width, precision) = match format.split_once('.') {
+        Some((width, precision)) => (width, Some(precision.parse().ok()?)),
+        None => (format, None),
+    };
+    let zero = width.starts_with('0');
+
+    Some(Placeholder {
+        name: name.to_owned(),
+        grouped,
+        zero,
+        width: if width.is_empty() {
+            0
+        } else {
+            width.parse().ok()?
+        },
+        precision,
+    })
+}
+
+/// Adds commas between the thousands of the whole number part of a formatted
+/// number, leaving anything else alone.
+fn group_thousands(formatted: &str) -> String {
+    let digits_start = formatted
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(formatted.len());
+    let digits_end = formatted[digits_start..]
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(formatted.len(), |end| digits_start + end);
+    let digits = &formatted[digits_start..digits_end];
+
+    let mut grouped = formatted[..digits_start].to_owned();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped.push_str(&formatted[digits_end..]);
+    grouped
+}
@@ -0,0 +1,183 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use geoutils::Location;
+use gpx::Gpx;
+use gpx::Waypoint;
+
+/// Something wrong with a file, found by `gpxsplit validate`.
+pub struct Problem {
+    /// Whether the file can't be split properly because of this, rather than
+    /// it only being worth knowing about.
+    pub error: bool,
+    pub description: String,
+}
+
+impl Problem {
+    pub fn error(description: String) -> Problem {
+        Problem {
+            error: true,
+            description,
+        }
+    }
+
+    pub fn warning(description: String) -> Problem {
+        Problem {
+            error: false,
+            description,
+        }
+    }
+}
+
+/// Counts the tracks, segments, routes, points, and waypoints in `gpx`.
+pub fn describe(gpx: &Gpx) -> String {
+    let segments = gpx.tracks.iter().map(|track| track.segments.len()).sum();
+    let points = gpx
+        .tracks
+        .iter()
+        .flat_map(|track| &track.segments)
+        .map(|segment| segment.points.len())
+        .chain(gpx.routes.iter().map(|route| route.points.len()))
+        .sum();
+    [
+        count(gpx.tracks.len(), "track"),
+        count(segments, "segment"),
+        count(gpx.routes.len(), "route"),
+        count(points, "point"),
+        count(gpx.waypoints.len(), "waypoint"),
+    ]
+    .join(", ")
+}
+
+/// Checks that `gpx` has routes to split, and that the points of each one
+/// have timestamps in order and elevations, without consecutive points
+/// further apart than `max_gap_meters`. Points are numbered from zero, the
+/// same as in plans.
+pub fn validate(gpx: &Gpx, max_gap_meters: f64) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    if gpx.tracks.is_empty() && gpx.routes.is_empty() {
+        problems.push(Problem::error("file has no tracks or routes".to_owned()));
+    }
+
+    let mut lines = Vec::new();
+    for (track_number, track) in gpx.tracks.iter().enumerate() {
+        if track.segments.is_empty() {
+            problems.push(Problem::error(format!(
+                "track {} has no segments",
+                track_number + 1
+            )));
+        }
+        for (segment_number, segment) in track.segments.iter().enumerate() {
+            lines.push((
+                format!("track {} segment {}", track_number + 1, segment_number + 1),
+                &segment.points,
+            ));
+        }
+    }
+    for (route_number, route) in gpx.routes.iter().enumerate() {
+        lines.push((format!("route {}", route_number + 1), &route.points));
+    }
+
+    for (name, points) in lines {
+        validate_points(&name, points, max_gap_meters, &mut problems);
+    }
+    problems
+}
+
+fn validate_points(
+    name: &str,
+    points: &[Waypoint],
+    max_gap_meters: f64,
+    problems: &mut Vec<Problem>,
+) {
+    match points.len() {
+        0 => problems.push(Problem::error(format!("{name} has no points"))),
+        1 => problems.push(Problem::error(format!("{name} only has one point"))),
+        _ => {}
+    }
+
+    let timed = points.iter().filter(|point| point.time.is_some()).count();
+    if timed > 0 && timed < points.len() {
+        problems.push(Problem::warning(format!(
+            "{} in {name} have no timestamp",
+            count_of(points.len() - timed, points.len())
+        )));
+    }
+    let backwards: Vec<usize> = points
+        .windows(2)
+        .enumerate()
+        .filter_map(|(index, pair)| match (pair[0].time, pair[1].time) {
+            (Some(a), Some(b)) if b < a => Some(index + 1),
+            _ => None,
+        })
+        .collect();
+    if let Some(first) = backwards.first() {
+        problems.push(Problem::error(format!(
+            "timestamps in {name} go backwards at {}, first at point {first}",
+            count(backwards.len(), "point")
+        )));
+    }
+
+    let missing_elevation = points
+        .iter()
+        .filter(|point| point.elevation.is_none())
+        .count();
+    if missing_elevation > 0 {
+        problems.push(Problem::warning(format!(
+            "{} in {name} have no elevation",
+            count_of(missing_elevation, points.len())
+        )));
+    }
+
+    let gaps: Vec<(usize, f64)> = points
+        .windows(2)
+        .enumerate()
+        .map(|(index, pair)| {
+            (
+                index + 1,
+                location(&pair[0])
+                    .haversine_distance_to(&location(&pair[1]))
+                    .meters(),
+            )
+        })
+        .filter(|(_, meters)| *meters > max_gap_meters)
+        .collect();
+    if let Some((index, meters)) = gaps.iter().max_by(|(_, a), (_, b)| a.total_cmp(b)) {
+        problems.push(Problem::warning(format!(
+            "{name} has {} longer than {:.1} km between points, the longest is {:.1} km \
+             before point {index}",
+            count(gaps.len(), "gap"),
+            max_gap_meters / 1000.,
+            meters / 1000.
+        )));
+    }
+}
+
+/// A number of things, like `1 point` or `3 points`.
+pub fn count(number: usize, noun: &str) -> String {
+    match number {
+        1 => format!("1 {noun}"),
+        _ => format!("{number} {noun}s"),
+    }
+}
+
+/// A number of the `total` points, like `3 of 40 points`.
+fn count_of(number: usize, total: usize) -> String {
+    format!("{number} of {}", count(total, "point"))
+}
+
+fn location(waypoint: &Waypoint) -> Location {
+    let point = waypoint.point();
+    Location::new(point.y(), point.x())
+}
@@ -0,0 +1,99 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+//! Splitting for web pages, with the `wasm` feature, built with
+//! `cargo build --lib --release --target wasm32-unknown-unknown --features wasm`.
+//!
+//! The exported functions only pass numbers, so that they can be called
+//! without any generated bindings. A page copies the file into memory from
+//! `gpxsplit_alloc`, calls `gpxsplit_split`, reads the result, and hands both
+//! buffers back to `gpxsplit_free`.
+
+use std::slice;
+
+use crate::split_gpx_bytes;
+use crate::splitter::simple_options;
+
+/// Allocates `len` bytes for the page to copy a file into.
+#[no_mangle]
+pub extern "C" fn gpxsplit_alloc(len: usize) -> *mut u8 {
+    let mut buffer = vec![0u8; len].into_boxed_slice();
+    let pointer = buffer.as_mut_ptr();
+    std::mem::forget(buffer);
+    pointer
+}
+
+/// Frees a buffer from `gpxsplit_alloc` or `gpxsplit_split`.
+///
+/// # Safety
+///
+/// `pointer` has to be from one of those functions, with the same `len`, and
+/// can't be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn gpxsplit_free(pointer: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(pointer, 0, len));
+}
+
+/// Splits the GPX file in the `len` bytes at `input` into files of
+/// `km_per_file` kilometers and at most `max_points` points, either of which
+/// can be zero to not use it.
+///
+/// The result is a buffer of little-endian `u32`s: the length of the whole
+/// buffer, then the number of parts, then the length and contents of each
+/// part. If splitting fails, the number of parts is `u32::MAX`, followed by
+/// the length and contents of the error message.
+///
+/// # Safety
+///
+/// `input` has to point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gpxsplit_split(
+    input: *const u8,
+    len: usize,
+    km_per_file: f64,
+    max_points: u32,
+) -> *mut u8 {
+    let input = slice::from_raw_parts(input, len);
+
+    let parts =
+        simple_options(km_per_file, max_points).and_then(|options| split_gpx_bytes(input, options));
+
+    // the total length is filled in at the end
+    let mut output = vec![0; 4];
+    match parts {
+        Ok(parts) => {
+            output.extend((parts.len() as u32).to_le_bytes());
+            for part in &parts {
+                push_bytes(&mut output, part);
+            }
+        }
+        Err(error) => {
+            output.extend(u32::MAX.to_le_bytes());
+            push_bytes(&mut output, error.describe().as_bytes());
+        }
+    }
+    let total = (output.len() as u32).to_le_bytes();
+    output[..4].copy_from_slice(&total);
+
+    let mut output = output.into_boxed_slice();
+    let pointer = output.as_mut_ptr();
+    std::mem::forget(output);
+    pointer
+}
+
+/// Adds the length of `bytes` and then `bytes` to the end of `output`.
+fn push_bytes(output: &mut Vec<u8>, bytes: &[u8]) {
+    output.extend((bytes.len() as u32).to_le_bytes());
+    output.extend(bytes);
+}
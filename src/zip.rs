@@ -0,0 +1,193 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::io;
+use std::io::Write;
+
+use anyhow::anyhow;
+use anyhow::bail;
+use anyhow::Context;
+use anyhow::Result;
+use time::OffsetDateTime;
+
+use crate::gzip::inflate;
+
+/// Writes a zip archive of `entries`, each a file name and its contents. The
+/// files are stored without compression, which every zip reader supports, and
+/// the archive is limited to what fits without the zip64 extensions.
+pub fn write_zip<W: Write>(mut writer: W, entries: &[(String, Vec<u8>)]) -> io::Result<()> {
+    let (time, date) = dos_time(OffsetDateTime::now_utc());
+    let too_large = || io::Error::other("too much data for a zip archive");
+
+    let mut central_directory = Vec::new();
+    let mut offset = 0u32;
+
+    for (name, data) in entries {
+        let crc = crc32(data);
+        let size = u32::try_from(data.len()).map_err(|_| too_large())?;
+        let name_length = u16::try_from(name.len()).map_err(|_| too_large())?;
+
+        // the header before each file's data
+        let mut header = Vec::new();
+        header.extend(0x04034b50u32.to_le_bytes());
+        header.extend(20u16.to_le_bytes()); // version needed to extract
+        header.extend(UTF8_NAMES.to_le_bytes());
+        header.extend(0u16.to_le_bytes()); // stored, without compression
+        header.extend(time.to_le_bytes());
+        header.extend(date.to_le_bytes());
+        header.extend(crc.to_le_bytes());
+        header.extend(size.to_le_bytes()); // compressed size
+        header.extend(size.to_le_bytes()); // uncompressed size
+        header.extend(name_length.to_le_bytes());
+        header.extend(0u16.to_le_bytes()); // extra field length
+        header.extend(name.as_bytes());
+        writer.write_all(&header)?;
+        writer.write_all(data)?;
+
+        // the matching entry in the directory at the end of the archive
+        central_directory.extend(0x02014b50u32.to_le_bytes());
+        central_directory.extend(20u16.to_le_bytes()); // version made by
+        central_directory.extend(20u16.to_le_bytes()); // version needed to extract
+        central_directory.extend(UTF8_NAMES.to_le_bytes());
+        central_directory.extend(0u16.to_le_bytes());
+        central_directory.extend(time.to_le_bytes());
+        central_directory.extend(date.to_le_bytes());
+        central_directory.extend(crc.to_le_bytes());
+        central_directory.extend(size.to_le_bytes());
+        central_directory.extend(size.to_le_bytes());
+        central_directory.extend(name_length.to_le_bytes());
+        central_directory.extend([0; 12]); // extra, comment, disk, and attributes
+        central_directory.extend(offset.to_le_bytes());
+        central_directory.extend(name.as_bytes());
+
+        offset = u32::try_from(header.len() + data.len())
+            .ok()
+            .and_then(|length| offset.checked_add(length))
+            .ok_or_else(too_large)?;
+    }
+
+    let count = u16::try_from(entries.len()).map_err(|_| too_large())?;
+    let directory_size = u32::try_from(central_directory.len()).map_err(|_| too_large())?;
+    writer.write_all(&central_directory)?;
+
+    let mut end = Vec::new();
+    end.extend(0x06054b50u32.to_le_bytes());
+    end.extend(0u16.to_le_bytes()); // number of this disk
+    end.extend(0u16.to_le_bytes()); // disk where the directory starts
+    end.extend(count.to_le_bytes()); // entries on this disk
+    end.extend(count.to_le_bytes()); // total entries
+    end.extend(directory_size.to_le_bytes());
+    end.extend(offset.to_le_bytes());
+    end.extend(0u16.to_le_bytes()); // comment length
+    writer.write_all(&end)
+}
+
+/// Whether `data` starts like a zip archive.
+pub fn is_zip(data: &[u8]) -> bool {
+    data.starts_with(b"PK\x03\x04")
+}
+
+/// Finds the first file in a zip archive with a name that `matches`, and
+/// returns its contents, if there is one. Only files that are stored or
+/// deflated can be read, which is what every common zip writer uses.
+pub fn read_zip_file(data: &[u8], matches: impl Fn(&str) -> bool) -> Result<Option<Vec<u8>>> {
+    let truncated = || anyhow!("zip archive is truncated");
+    let u16_at = |position: usize| -> Result<u16> {
+        let bytes = data.get(position..position + 2).ok_or_else(truncated)?;
+        Ok(u16::from_le_bytes(bytes.try_into()?))
+    };
+    let u32_at = |position: usize| -> Result<u32> {
+        let bytes = data.get(position..position + 4).ok_or_else(truncated)?;
+        Ok(u32::from_le_bytes(bytes.try_into()?))
+    };
+
+    // the end record is last, but there can be a comment of any length after
+    // it, so search backwards for its signature
+    let Some(end) = (0..data.len().saturating_sub(21))
+        .rev()
+        .find(|position| data[*position..].starts_with(&0x06054b50u32.to_le_bytes()))
+    else {
+        bail!("zip archive has no central directory");
+    };
+    let count = u16_at(end + 10)?;
+    let mut position = u32_at(end + 16)? as usize;
+
+    for _ in 0..count {
+        if u32_at(position)? != 0x02014b50 {
+            bail!("zip archive is corrupt");
+        }
+        let method = u16_at(position + 10)?;
+        let crc = u32_at(position + 16)?;
+        let compressed_size = u32_at(position + 20)? as usize;
+        let name_length = u16_at(position + 28)? as usize;
+        let extra_length = u16_at(position + 30)? as usize;
+        let comment_length = u16_at(position + 32)? as usize;
+        let offset = u32_at(position + 42)? as usize;
+        let name = data
+            .get(position + 46..position + 46 + name_length)
+            .ok_or_else(truncated)?;
+        let name = String::from_utf8_lossy(name);
+        position += 46 + name_length + extra_length + comment_length;
+
+        if !matches(&name) {
+            continue;
+        }
+
+        // the local header's extra field can differ from the directory's
+        let start = offset + 30 + u16_at(offset + 26)? as usize + u16_at(offset + 28)? as usize;
+        let compressed = data
+            .get(start..start + compressed_size)
+            .ok_or_else(truncated)?;
+        let contents = match method {
+            0 => compressed.to_vec(),
+            8 => inflate(compressed).with_context(|| format!("failed to decompress {name}"))?,
+            _ => bail!("{name} is compressed with an unsupported method"),
+        };
+        if crc32(&contents) != crc {
+            bail!("{name} in zip archive is corrupt");
+        }
+        return Ok(Some(contents));
+    }
+    Ok(None)
+}
+
+/// General purpose flag for file names encoded as UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// Time and date in the MS-DOS format that zip archives use, which only has
+/// two second precision and starts in 1980.
+fn dos_time(now: OffsetDateTime) -> (u16, u16) {
+    let time =
+        ((now.hour() as u16) << 11) | ((now.minute() as u16) << 5) | (now.second() as u16 / 2);
+    let date = (((now.year() - 1980).clamp(0, 127) as u16) << 9)
+        | ((now.month() as u16) << 5)
+        | now.day() as u16;
+    (time, date)
+}
+
+/// CRC-32 checksum, as used by zip and gzip.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
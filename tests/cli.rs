@@ -0,0 +1,238 @@
+// Copyright 2025 natesm@gmail.com
+//
+// Permission to use, copy, modify, and/or distribute this software for any
+// purpose with or without fee is hereby granted, provided that the above
+// copyright notice and this permission notice appear in all copies.
+//
+// THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+// WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+// MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+// ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+// WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+// ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF OR
+// IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+
+/// A directory of its own for a test, with `gpx` written to `route.gpx` in
+/// it.
+fn directory(test: &str, gpx: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("gpxsplit-{test}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("route.gpx"), gpx).unwrap();
+    directory
+}
+
+/// Splits `route.gpx` in `directory` with `arguments` after it, writing the files to
+/// `out` in it.
+fn run(directory: &Path, arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gpxsplit"))
+        .arg("--quiet")
+        .arg("--output-dir")
+        .arg(directory.join("out"))
+        .arg(directory.join("route.gpx"))
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+/// The contents of the files that were written, in order of their names.
+fn written(directory: &Path) -> Vec<String> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory.join("out"))
+        .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+        .unwrap_or_default();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| fs::read_to_string(path).unwrap())
+        .collect()
+}
+
+/// A GPX file with a track through `points`, given as latitude and
+/// longitude, a minute apart, with `extensions` in each point.
+fn track(points: &[(f64, f64)], extensions: impl Fn(usize) -> String) -> String {
+    let mut gpx = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="test" version="1.1" xmlns="http://www.topografix.com/GPX/1/1" xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+ <trk>
+  <name>Route</name>
+  <trkseg>
+"#,
+    );
+    for (index, (latitude, longitude)) in points.iter().enumerate() {
+        gpx.push_str(&format!(
+            "   <trkpt lat=\"{latitude}\" lon=\"{longitude}\"><time>2025-06-13T13:{:02}:00Z</time>{}</trkpt>\n",
+            index % 60,
+            extensions(index),
+        ));
+    }
+    gpx.push_str("  </trkseg>\n </trk>\n</gpx>\n");
+    gpx
+}
+
+/// A ride with a heart rate at each point.
+fn ride() -> String {
+    let points: Vec<(f64, f64)> = (0..4)
+        .map(|index| (45. + index as f64 * 0.1, -110.))
+        .collect();
+    track(&points, |index| {
+        format!(
+            "<extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>12{index}</gpxtpx:hr></gpxtpx:TrackPointExtension></extensions>"
+        )
+    })
+}
+
+fn heart_rates(file: &str) -> Vec<&str> {
+    file.split("<gpxtpx:hr>")
+        .skip(1)
+        .map(|rest| &rest[..rest.find('<').unwrap()])
+        .collect()
+}
+
+#[test]
+fn extensions_survive_strip_time() {
+    let directory = directory("strip-time", &ride());
+    let output = run(&directory, &["--max-points", "3", "--strip-time"]);
+    assert!(output.status.success(), "{output:?}");
+    let files = written(&directory);
+    assert_eq!(files.len(), 2);
+    assert!(files.iter().all(|file| !file.contains("<time>")));
+    assert_eq!(heart_rates(&files[0]), ["120", "121", "122"]);
+    assert_eq!(heart_rates(&files[1]), ["122", "123"]);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn extensions_survive_synthesize_time() {
+    let directory = directory("synthesize-time", &ride());
+    let output = run(
+        &directory,
+        &[
+            "--max-points",
+            "3",
+            "--synthesize-time",
+            "--start",
+            "2025-07-01T07:00",
+        ],
+    );
+    assert!(output.status.success(), "{output:?}");
+    let files = written(&directory);
+    assert_eq!(files.len(), 2);
+    assert!(files[0].contains("2025-07-01T07:00:00"));
+    assert_eq!(heart_rates(&files[0]), ["120", "121", "122"]);
+    assert_eq!(heart_rates(&files[1]), ["122", "123"]);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+/// Runs with `arguments` on a small route, expecting it to fail, and returns
+/// what it printed.
+fn rejected(test: &str, arguments: &[&str]) -> String {
+    let directory = directory(test, &ride());
+    let output = run(&directory, arguments);
+    assert!(!output.status.success(), "{arguments:?} was accepted");
+    assert!(written(&directory).is_empty());
+    fs::remove_dir_all(directory).unwrap();
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn rejects_non_positive_km_per_file() {
+    for value in ["0", "-5", "NaN", "inf"] {
+        let error = rejected("km-per-file", &[&format!("--km-per-file={value}")]);
+        assert!(error.contains("expected a positive number"), "{error}");
+    }
+    let error = rejected("km-per-file-positional", &["0"]);
+    assert!(error.contains("KM_PER_FILE"), "{error}");
+}
+
+#[test]
+fn rejects_non_positive_gain_per_file() {
+    for value in ["0", "-100", "NaN"] {
+        let error = rejected("gain-per-file", &[&format!("--gain-per-file={value}")]);
+        assert!(error.contains("expected a positive number"), "{error}");
+    }
+}
+
+#[test]
+fn rejects_non_positive_estimates() {
+    for value in ["0", "-10", "NaN"] {
+        for arguments in [
+            ["--est-hours-per-file=2", &format!("--speed={value}")],
+            [&format!("--est-hours-per-file={value}"), "--speed=20"],
+            ["--km-per-file=50", &format!("--speed={value}")],
+        ] {
+            let error = rejected("estimates", &arguments);
+            assert!(error.contains("expected a positive number"), "{error}");
+        }
+    }
+}
+
+#[test]
+fn rejects_non_positive_grid_degrees() {
+    for value in ["0", "-1", "NaN"] {
+        let error = rejected("grid-degrees", &[&format!("--grid-degrees={value}")]);
+        assert!(error.contains("expected a positive number"), "{error}");
+    }
+}
+
+#[test]
+fn rejects_non_positive_hours_per_file() {
+    for value in ["0", "-1", "NaN"] {
+        let error = rejected("hours-per-file", &[&format!("--hours-per-file={value}")]);
+        assert!(error.contains("expected a positive number"), "{error}");
+    }
+}
+
+/// A route through points a tenth of a degree of latitude apart, at each of
+/// `steps` from the first one.
+fn route(steps: &[i32]) -> String {
+    let points: Vec<(f64, f64)> = steps
+        .iter()
+        .map(|step| (45. + *step as f64 * 0.1, -110.))
+        .collect();
+    track(&points, |_| String::new())
+}
+
+#[test]
+fn splits_out_and_back_at_turnaround() {
+    let directory = directory("out-and-back", &route(&[0, 1, 2, 3, 4, 3, 2, 1, 0]));
+    let output = run(&directory, &["--split-at-turnaround"]);
+    assert!(output.status.success(), "{output:?}");
+    let files = written(&directory);
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[0].matches("<trkpt").count(), 5);
+    assert_eq!(files[1].matches("<trkpt").count(), 5);
+    assert!(files[1].contains(r#"lat="45.4""#));
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn leaves_one_way_route_whole_at_turnaround() {
+    let directory = directory("one-way", &route(&[0, 1, 2, 3, 4, 5, 6, 7, 6]));
+    let output = run(&directory, &["--split-at-turnaround"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(written(&directory).len(), 1);
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn rejects_cuts_outside_the_route() {
+    // the ride is about 33 km long
+    let error = rejected("at-km", &["--at=10,40"]);
+    assert!(error.contains("past the end of the route"), "{error}");
+    for value in ["0", "100", "150", "-10"] {
+        let error = rejected("at-percent", &[&format!("--at-percent={value}")]);
+        assert!(error.contains(value), "{error}");
+    }
+
+    let directory = directory("at", &ride());
+    let output = run(&directory, &["--at=15"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(written(&directory).len(), 2);
+    fs::remove_dir_all(directory).unwrap();
+}